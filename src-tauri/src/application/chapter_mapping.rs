@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value;
+
+use super::{parse_chapter_number, tag_slug};
+use crate::domain::DbRecord;
+
+/// Words that mark a chapter outside the numbered run.
+const SPECIAL_MARKERS: [&str; 8] = [
+    "extra", "special", "especial", "omake", "oneshot", "bonus", "prologue", "epilogue",
+];
+/// Words that only introduce a number and say nothing about the title.
+const TITLE_FILLER: [&str; 10] = [
+    "chapter", "chap", "ch", "cap", "capitulo", "episode", "ep", "vol", "volume", "volumen",
+];
+/// Title similarity needed to match chapters that cannot be matched by number.
+const MIN_TITLE_SIMILARITY: f64 = 0.6;
+/// Matches scoring below this are flagged for review.
+pub const REVIEW_CONFIDENCE: f64 = 0.8;
+
+/// Number and title of a chapter, normalized for matching.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChapterKey {
+    /// Label the number was read from, as written.
+    pub label: String,
+    pub number: Option<f64>,
+    /// Title words without accents, chapter markers or numbers.
+    pub title: Vec<String>,
+    /// Extras, specials, one-shots and the like.
+    pub special: bool,
+}
+
+impl ChapterKey {
+    /// Reads `number` and `name` from a chapter record, taking the number from the name when
+    /// the record has none.
+    pub fn from_record(record: &DbRecord) -> Self {
+        let text = |key: &str| match record.data.get(key) {
+            Some(Value::String(text)) => text.trim().to_string(),
+            Some(Value::Number(number)) => number.to_string(),
+            _ => String::new(),
+        };
+        Self::new(&text("number"), &text("name"))
+    }
+
+    pub fn new(number: &str, name: &str) -> Self {
+        let number_value = parse_chapter_number(number).or_else(|| parse_chapter_number(name));
+        let words = tag_slug(&format!("{number} {name}"))
+            .split('-')
+            .filter(|word| !word.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let special = words
+            .iter()
+            .any(|word| SPECIAL_MARKERS.contains(&word.as_str()));
+        let title = tag_slug(name)
+            .split('-')
+            .filter(|word| {
+                !word.is_empty()
+                    && !TITLE_FILLER.contains(word)
+                    && !word.chars().all(|ch| ch.is_ascii_digit())
+            })
+            .map(str::to_string)
+            .collect();
+        Self {
+            label: if number.is_empty() {
+                name.to_string()
+            } else {
+                number.to_string()
+            },
+            number: number_value,
+            title,
+            special,
+        }
+    }
+
+    /// Groups chapters that would become the same canonical chapter.
+    fn token(&self) -> String {
+        match self.number {
+            Some(number) => format!("{}#{number}", self.special),
+            None => format!("{}:{}", self.special, self.title.join(" ")),
+        }
+    }
+}
+
+/// Where a variant was mapped.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MappingTarget {
+    Existing(String),
+    /// Index into [`ChapterMappingPlan::new_chapters`].
+    New(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChapterMatch {
+    pub variant_id: String,
+    pub target: MappingTarget,
+    pub confidence: f64,
+    pub strategy: &'static str,
+    pub needs_review: bool,
+}
+
+/// A canonical chapter to create for variants nothing matched.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NewCanonicalChapter {
+    pub number: String,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ChapterMappingPlan {
+    pub matches: Vec<ChapterMatch>,
+    pub new_chapters: Vec<NewCanonicalChapter>,
+}
+
+/// Maps every variant to the canonical chapter it most likely is. Chapter numbers decide
+/// first and titles break ties; variants matching nothing get a new canonical chapter,
+/// shared by variants with the same number or title.
+pub fn plan_chapter_mappings(canonicals: &[DbRecord], variants: &[DbRecord]) -> ChapterMappingPlan {
+    let canonical_keys = canonicals
+        .iter()
+        .map(|record| (record.id.as_str(), ChapterKey::from_record(record)))
+        .collect::<Vec<_>>();
+    let mut plan = ChapterMappingPlan::default();
+    let mut new_by_token = HashMap::<String, usize>::new();
+
+    for variant in variants {
+        let key = ChapterKey::from_record(variant);
+        let best = canonical_keys
+            .iter()
+            .filter_map(|(id, canonical)| {
+                match_confidence(&key, canonical)
+                    .map(|(confidence, strategy)| (*id, confidence, strategy))
+            })
+            .fold(
+                None::<(&str, f64, &'static str)>,
+                |best, candidate| match best {
+                    Some(best) if best.1 >= candidate.1 => Some(best),
+                    _ => Some(candidate),
+                },
+            );
+
+        let chapter_match = match best {
+            Some((id, confidence, strategy)) => ChapterMatch {
+                variant_id: variant.id.clone(),
+                target: MappingTarget::Existing(id.to_string()),
+                confidence,
+                strategy,
+                needs_review: confidence < REVIEW_CONFIDENCE,
+            },
+            None => {
+                let index = *new_by_token.entry(key.token()).or_insert_with(|| {
+                    let name = match variant.data.get("name") {
+                        Some(Value::String(name)) if !name.trim().is_empty() => {
+                            name.trim().to_string()
+                        }
+                        _ => key.label.clone(),
+                    };
+                    plan.new_chapters.push(NewCanonicalChapter {
+                        number: key.label.clone(),
+                        name,
+                    });
+                    plan.new_chapters.len() - 1
+                });
+                ChapterMatch {
+                    variant_id: variant.id.clone(),
+                    target: MappingTarget::New(index),
+                    confidence: 1.0,
+                    strategy: "created",
+                    needs_review: false,
+                }
+            }
+        };
+        plan.matches.push(chapter_match);
+    }
+    plan
+}
+
+/// How likely two chapters are the same one, from 0 to 1, and what decided it. `None` when
+/// they cannot be: different numbers, or titles too far apart when a number is missing.
+pub fn match_confidence(
+    variant: &ChapterKey,
+    canonical: &ChapterKey,
+) -> Option<(f64, &'static str)> {
+    let similarity = title_similarity(&variant.title, &canonical.title);
+    match (variant.number, canonical.number) {
+        (Some(left), Some(right)) if (left - right).abs() < 1e-6 => {
+            let mut confidence: f64 = if variant.special == canonical.special {
+                0.9
+            } else {
+                0.6
+            };
+            let mut strategy = "number-match";
+            match similarity {
+                Some(similarity) if similarity >= MIN_TITLE_SIMILARITY => {
+                    confidence += 0.1;
+                    strategy = "number-title-match";
+                }
+                Some(similarity) if similarity < 0.2 => confidence -= 0.1,
+                _ => {}
+            }
+            Some((confidence.min(1.0), strategy))
+        }
+        (Some(_), Some(_)) => None,
+        _ => similarity
+            .filter(|similarity| {
+                *similarity >= MIN_TITLE_SIMILARITY && variant.special == canonical.special
+            })
+            .map(|similarity| (0.5 + 0.35 * similarity, "title-match")),
+    }
+}
+
+/// Shared words over all words of both titles; `None` when either title has no words.
+fn title_similarity(left: &[String], right: &[String]) -> Option<f64> {
+    if left.is_empty() || right.is_empty() {
+        return None;
+    }
+    let left = left.iter().collect::<HashSet<_>>();
+    let right = right.iter().collect::<HashSet<_>>();
+    let shared = left.intersection(&right).count();
+    Some(shared as f64 / left.union(&right).count() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chapter(id: &str, number: &str, name: &str) -> DbRecord {
+        DbRecord {
+            id: id.to_string(),
+            data: json!({ "number": number, "name": name }),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn maps_variants_by_number_and_title() {
+        let key = ChapterKey::new("Ch. 10,5", "Capítulo 10.5: O Retorno");
+        assert_eq!(key.number, Some(10.5));
+        assert_eq!(key.title, ["o", "retorno"]);
+        assert!(ChapterKey::new("", "Extra").special);
+
+        let canonicals = [
+            chapter("c-10", "10", "The Return"),
+            chapter("c-10-5", "10.5", "Side Story"),
+            chapter("c-extra", "", "Beach Episode"),
+        ];
+        let variants = [
+            chapter("v-1", "Ch. 10", "The Return"),
+            chapter("v-2", "10,5", "Capítulo 10,5"),
+            chapter("v-3", "", "Beach Episode!"),
+            chapter("v-4", "Extra", "Beach"),
+            chapter("v-5", "11", "Chapter 11"),
+            chapter("v-6", "11.0", ""),
+            chapter("v-7", "10", "Extra: Omake"),
+        ];
+        let plan = plan_chapter_mappings(&canonicals, &variants);
+        let summary = plan
+            .matches
+            .iter()
+            .map(|chapter_match| {
+                (
+                    chapter_match.variant_id.as_str(),
+                    chapter_match.target.clone(),
+                    chapter_match.strategy,
+                    chapter_match.needs_review,
+                )
+            })
+            .collect::<Vec<_>>();
+        let existing = |id: &str| MappingTarget::Existing(id.to_string());
+        assert_eq!(
+            summary,
+            [
+                ("v-1", existing("c-10"), "number-title-match", false),
+                ("v-2", existing("c-10-5"), "number-match", false),
+                ("v-3", existing("c-extra"), "title-match", false),
+                ("v-4", MappingTarget::New(0), "created", false),
+                ("v-5", MappingTarget::New(1), "created", false),
+                ("v-6", MappingTarget::New(1), "created", false),
+                ("v-7", existing("c-10"), "number-match", true),
+            ]
+        );
+        assert_eq!(
+            plan.new_chapters[1],
+            NewCanonicalChapter {
+                number: "11".to_string(),
+                name: "Chapter 11".to_string(),
+            }
+        );
+    }
+}
@@ -1,12 +1,25 @@
+mod chapter_mapping;
+mod tags;
+
 use std::sync::Arc;
 
 use serde_json::Value;
 
-use crate::domain::{AppError, DbRecord, LegacyImportReport, Table};
+use crate::domain::{
+    AppError, ChapterSelection, ComicInfo, ComicProgress, ComicTag, DbRecord, DocumentWrite,
+    LegacyImportReport, LibraryFilter, ReadingStats, ReadingStatsRange, Table, TagSummary,
+};
+pub use chapter_mapping::{plan_chapter_mappings, MappingTarget};
+pub use tags::{canonical_tag, genre_names, tag_slug, CanonicalTag};
+
+/// Document key remembering which values were filled from ComicInfo.xml, so later merges can
+/// tell them apart from user edits.
+const COMIC_INFO_FIELDS_KEY: &str = "comicInfoFields";
 
 pub trait DocumentStore: Send + Sync {
     fn upsert(&self, table: Table, id: Option<String>, data: Value) -> Result<DbRecord, AppError>;
     fn get(&self, table: Table, id: &str) -> Result<Option<DbRecord>, AppError>;
+    fn get_many(&self, table: Table, ids: &[String]) -> Result<Vec<DbRecord>, AppError>;
     fn list(
         &self,
         table: Table,
@@ -21,11 +34,39 @@ pub trait DocumentStore: Send + Sync {
         limit: Option<u32>,
     ) -> Result<Vec<DbRecord>, AppError>;
     fn delete(&self, table: Table, id: &str) -> Result<bool, AppError>;
+    fn write_batch(&self, writes: &[DocumentWrite]) -> Result<Vec<DbRecord>, AppError>;
+    fn set_json_field(
+        &self,
+        table: Table,
+        id: &str,
+        field: &str,
+        value: &Value,
+    ) -> Result<bool, AppError>;
     fn mark_chapters_read_state(
         &self,
         chapter_ids: &[String],
+        selection: Option<&ChapterSelection>,
         read: bool,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
     ) -> Result<(usize, usize), AppError>;
+    fn repair_progress_page_counts(
+        &self,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
+    ) -> Result<(usize, usize), AppError>;
+    fn reading_stats(&self, range: &ReadingStatsRange) -> Result<ReadingStats, AppError>;
+    fn comic_progress(&self, comic_id: Option<&str>) -> Result<Vec<ComicProgress>, AppError>;
+    fn list_tags(&self) -> Result<Vec<TagSummary>, AppError>;
+    fn update_tag(
+        &self,
+        tag_id: &str,
+        name: Option<&str>,
+        aliases: Option<&[String]>,
+    ) -> Result<Option<TagSummary>, AppError>;
+    fn comic_tags(&self, comic_id: &str) -> Result<Vec<ComicTag>, AppError>;
+    fn set_comic_user_tags(&self, comic_id: &str, names: &[String]) -> Result<(), AppError>;
+    fn filter_comics(&self, filter: &LibraryFilter) -> Result<Vec<DbRecord>, AppError>;
+    fn recently_read_comics(&self, limit: u32) -> Result<Vec<DbRecord>, AppError>;
+    fn chapters_stored_in_folders(&self, folders: &[String]) -> Result<Vec<DbRecord>, AppError>;
 }
 
 pub trait LegacyImporter: Send + Sync {
@@ -60,6 +101,12 @@ impl DocumentService {
         self.store.get(table, id)
     }
 
+    /// The documents with the given ids, in no particular order. Missing ids are skipped.
+    pub fn get_many(&self, table_name: &str, ids: &[String]) -> Result<Vec<DbRecord>, AppError> {
+        let table = Table::parse(table_name)?;
+        self.store.get_many(table, ids)
+    }
+
     pub fn list(
         &self,
         table_name: &str,
@@ -87,12 +134,453 @@ impl DocumentService {
         self.store.delete(table, id)
     }
 
+    /// Applies the writes in order in one transaction, so either all of them land or none
+    /// does. Returns the upserted documents in the order they were written.
+    pub fn write_batch(&self, writes: &[DocumentWrite]) -> Result<Vec<DbRecord>, AppError> {
+        self.store.write_batch(writes)
+    }
+
+    /// Writes one top-level field of a stored document in place, so fields changed since the
+    /// document was read are kept. Returns whether the document exists.
+    pub fn set_json_field(
+        &self,
+        table_name: &str,
+        id: &str,
+        field: &str,
+        value: &Value,
+    ) -> Result<bool, AppError> {
+        let table = Table::parse(table_name)?;
+        self.store.set_json_field(table, id, field, value)
+    }
+
+    /// Marks the listed chapters, plus the chapters picked by `selection`, in one transaction.
+    /// Chapters without a known page count get theirs from `page_count`.
     pub fn mark_chapters_read_state(
         &self,
         chapter_ids: &[String],
+        selection: Option<&ChapterSelection>,
         read: bool,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
     ) -> Result<(usize, usize), AppError> {
-        self.store.mark_chapters_read_state(chapter_ids, read)
+        self.store
+            .mark_chapters_read_state(chapter_ids, selection, read, page_count)
+    }
+
+    /// Recounts the pages of progress rows stuck at one page, keeping read chapters read.
+    /// Returns how many rows were checked and how many were repaired.
+    pub fn repair_progress_page_counts(
+        &self,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
+    ) -> Result<(usize, usize), AppError> {
+        self.store.repair_progress_page_counts(page_count)
+    }
+
+    pub fn reading_stats(&self, range: &ReadingStatsRange) -> Result<ReadingStats, AppError> {
+        self.store.reading_stats(range)
+    }
+
+    /// Comics with progress on a chapter and an unread chapter left, most recently read
+    /// first.
+    pub fn recently_read_comics(&self, limit: u32) -> Result<Vec<DbRecord>, AppError> {
+        self.store.recently_read_comics(limit)
+    }
+
+    /// Chapters whose stored `localPath` lies in one of the top-level comic `folders`.
+    pub fn chapters_stored_in_folders(
+        &self,
+        folders: &[String],
+    ) -> Result<Vec<DbRecord>, AppError> {
+        self.store.chapters_stored_in_folders(folders)
+    }
+
+    /// Progress summary of every comic in the library.
+    pub fn library_progress(&self) -> Result<Vec<ComicProgress>, AppError> {
+        self.store.comic_progress(None)
+    }
+
+    pub fn list_tags(&self) -> Result<Vec<TagSummary>, AppError> {
+        self.store.list_tags()
+    }
+
+    /// Renames a tag or replaces its aliases. Tags named by a new alias are merged into it.
+    pub fn update_tag(
+        &self,
+        tag_id: &str,
+        name: Option<&str>,
+        aliases: Option<&[String]>,
+    ) -> Result<Option<TagSummary>, AppError> {
+        self.store.update_tag(tag_id, name, aliases)
+    }
+
+    pub fn comic_tags(&self, comic_id: &str) -> Result<Vec<ComicTag>, AppError> {
+        self.store.comic_tags(comic_id)
+    }
+
+    /// Replaces the tags added to a comic by hand. Tags from its genres are kept.
+    pub fn set_comic_user_tags(
+        &self,
+        comic_id: &str,
+        names: &[String],
+    ) -> Result<Vec<ComicTag>, AppError> {
+        self.store.set_comic_user_tags(comic_id, names)?;
+        self.store.comic_tags(comic_id)
+    }
+
+    pub fn filter_comics(&self, filter: &LibraryFilter) -> Result<Vec<DbRecord>, AppError> {
+        self.store.filter_comics(filter)
+    }
+
+    pub fn comic_progress(&self, comic_id: &str) -> Result<Option<ComicProgress>, AppError> {
+        Ok(self
+            .store
+            .comic_progress(Some(comic_id))?
+            .into_iter()
+            .next())
+    }
+
+    /// Merges ComicInfo.xml metadata into a chapter and its comic. Only the merged fields are
+    /// written, so fields changed meanwhile by others are kept. Returns whether anything was
+    /// written.
+    pub fn apply_comic_info(&self, chapter_id: &str, info: &ComicInfo) -> Result<bool, AppError> {
+        let Some(chapter) = self.store.get(Table::Chapters, chapter_id)? else {
+            return Ok(false);
+        };
+
+        let mut chapter_data = chapter.data;
+        let changed = merge_metadata_fields(&mut chapter_data, chapter_comic_info_fields(info));
+        let mut writes = field_writes(Table::Chapters, &chapter.id, &chapter_data, changed);
+
+        let comic_id = chapter_data
+            .get("comicId")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+        if let Some(comic) = comic_id
+            .map(|comic_id| self.store.get(Table::Comics, comic_id))
+            .transpose()?
+            .flatten()
+        {
+            let mut comic_data = comic.data;
+            let changed = merge_metadata_fields(&mut comic_data, comic_comic_info_fields(info));
+            writes.extend(field_writes(Table::Comics, &comic.id, &comic_data, changed));
+        }
+
+        if writes.is_empty() {
+            return Ok(false);
+        }
+        self.store.write_batch(&writes)?;
+        Ok(true)
+    }
+}
+
+fn field_writes(table: Table, id: &str, data: &Value, fields: Vec<&str>) -> Vec<DocumentWrite> {
+    fields
+        .into_iter()
+        .map(|field| DocumentWrite::SetField {
+            table,
+            id: id.to_string(),
+            field: field.to_string(),
+            value: data.get(field).cloned().unwrap_or(Value::Null),
+        })
+        .collect()
+}
+
+struct MetadataField {
+    key: &'static str,
+    value: Option<Value>,
+    /// Identity fields (names, numbers) drive archive lookup, so they are only ever filled in.
+    fill_only: bool,
+}
+
+impl MetadataField {
+    fn new(key: &'static str, value: Option<Value>) -> Self {
+        Self {
+            key,
+            value,
+            fill_only: false,
+        }
+    }
+
+    fn fill_only(key: &'static str, value: Option<Value>) -> Self {
+        Self {
+            key,
+            value,
+            fill_only: true,
+        }
+    }
+}
+
+fn chapter_comic_info_fields(info: &ComicInfo) -> Vec<MetadataField> {
+    vec![
+        MetadataField::fill_only("name", info.title.clone().map(Value::from)),
+        MetadataField::fill_only("number", info.number.clone().map(Value::from)),
+        MetadataField::new("volume", info.volume.map(Value::from)),
+        MetadataField::new("summary", info.summary.clone().map(Value::from)),
+        MetadataField::new(
+            "languageCodes",
+            info.language_iso
+                .clone()
+                .map(|language| Value::Array(vec![Value::String(language)])),
+        ),
+        MetadataField::new("pageCount", info.page_count.map(Value::from)),
+        MetadataField::new("writer", info.writer.clone().map(Value::from)),
+        MetadataField::new("penciller", info.penciller.clone().map(Value::from)),
+    ]
+}
+
+fn comic_comic_info_fields(info: &ComicInfo) -> Vec<MetadataField> {
+    vec![
+        MetadataField::fill_only("name", info.series.clone().map(Value::from)),
+        // ComicInfo summaries describe a single issue, so they only seed an empty description.
+        MetadataField::fill_only("description", info.summary.clone().map(Value::from)),
+        MetadataField::fill_only("year", info.year.map(Value::from)),
+        MetadataField::new("publisher", info.publisher.clone().map(Value::from)),
+        MetadataField::new("writer", info.writer.clone().map(Value::from)),
+        MetadataField::new("penciller", info.penciller.clone().map(Value::from)),
+        MetadataField::new(
+            "genres",
+            info.genre.as_deref().map(|genre| {
+                Value::Array(
+                    genre
+                        .split([',', ';'])
+                        .map(str::trim)
+                        .filter(|value| !value.is_empty())
+                        .map(Value::from)
+                        .collect(),
+                )
+            }),
+        ),
+    ]
+}
+
+/// The ComicInfo.xml to write into an exported chapter: the records' metadata on top of what
+/// the chapter's own archive carried, mirroring the fields merged in by `apply_comic_info`.
+pub fn export_comic_info(
+    comic: &DbRecord,
+    chapter: &DbRecord,
+    archived: Option<ComicInfo>,
+) -> ComicInfo {
+    let text = |record: &DbRecord, key: &str| {
+        record
+            .data
+            .get(key)
+            .and_then(|value| match value {
+                Value::String(text) => Some(text.trim().to_string()),
+                Value::Number(number) => Some(number.to_string()),
+                _ => None,
+            })
+            .filter(|value| !value.is_empty())
+    };
+    let list = |record: &DbRecord, key: &str| {
+        record
+            .data
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|values| {
+                values
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|values| !values.is_empty())
+    };
+
+    let mut info = archived.unwrap_or_default();
+    let overrides = [
+        (&mut info.title, text(chapter, "name")),
+        (&mut info.series, text(comic, "name")),
+        (&mut info.number, text(chapter, "number")),
+        (
+            &mut info.summary,
+            text(chapter, "summary").or_else(|| text(comic, "description")),
+        ),
+        (&mut info.publisher, text(comic, "publisher")),
+        (
+            &mut info.writer,
+            text(chapter, "writer").or_else(|| text(comic, "writer")),
+        ),
+        (
+            &mut info.penciller,
+            text(chapter, "penciller").or_else(|| text(comic, "penciller")),
+        ),
+        (
+            &mut info.genre,
+            list(comic, "genres").map(|genres| genres.join(", ")),
+        ),
+        (
+            &mut info.language_iso,
+            list(chapter, "languageCodes").map(|languages| languages[0].clone()),
+        ),
+    ];
+    for (field, value) in overrides {
+        if value.is_some() {
+            *field = value;
+        }
+    }
+    if let Some(volume) = text(chapter, "volume").and_then(|value| value.parse().ok()) {
+        info.volume = Some(volume);
+    }
+    if let Some(year) = text(comic, "year").and_then(|value| value.parse().ok()) {
+        info.year = Some(year);
+    }
+    info
+}
+
+/// Words that introduce the chapter number in labels such as "Vol. 2 Ch. 10.5".
+const CHAPTER_NUMBER_MARKERS: [&str; 6] = ["chapter", "chap", "ch", "cap", "episode", "ep"];
+
+/// Numeric value of a chapter document's `number`, stored as a number or as a label.
+pub fn chapter_number(chapter: &Value) -> Option<f64> {
+    match chapter.get("number")? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(label) => parse_chapter_number(label),
+        _ => None,
+    }
+}
+
+/// A page position from a progress document. Older clients stored pages as floats.
+pub fn page_number(value: Option<&Value>) -> Option<i64> {
+    value.and_then(|value| {
+        value
+            .as_i64()
+            .or_else(|| value.as_f64().map(|number| number as i64))
+    })
+}
+
+/// Numeric value of a chapter label: "12", "10,5", "Ch. 10.5" or "Vol. 2 Chapter 7". Labels
+/// without a number, such as "Extra", have none.
+pub fn parse_chapter_number(label: &str) -> Option<f64> {
+    let label = label.trim().to_lowercase();
+    let numbers = numeric_tokens(&label);
+    let marked = numbers.iter().find(|(start, _)| {
+        let prefix =
+            label[..*start].trim_end_matches(|c: char| c == '.' || c == '#' || c.is_whitespace());
+        CHAPTER_NUMBER_MARKERS.iter().any(|marker| {
+            prefix
+                .strip_suffix(marker)
+                .is_some_and(|rest| !rest.ends_with(char::is_alphabetic))
+        })
+    });
+    marked
+        .or(numbers.first())
+        .and_then(|(_, token)| token.replace(',', ".").parse::<f64>().ok())
+}
+
+/// Numbers in `text` with their byte offset; a single `.` or `,` between digits is a
+/// decimal separator.
+fn numeric_tokens(text: &str) -> Vec<(usize, String)> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if !chars[index].1.is_ascii_digit() {
+            index += 1;
+            continue;
+        }
+        let start = chars[index].0;
+        let mut token = String::new();
+        let mut has_separator = false;
+        while index < chars.len() {
+            let current = chars[index].1;
+            let next_is_digit = chars
+                .get(index + 1)
+                .is_some_and(|(_, next)| next.is_ascii_digit());
+            if current.is_ascii_digit() {
+                token.push(current);
+            } else if matches!(current, '.' | ',') && !has_separator && next_is_digit {
+                token.push(current);
+                has_separator = true;
+            } else {
+                break;
+            }
+            index += 1;
+        }
+        tokens.push((start, token));
+    }
+    tokens
+}
+
+/// Whether a chapter document falls within `selection`. The comic is matched by the caller.
+pub fn chapter_in_selection(selection: &ChapterSelection, chapter: &Value) -> bool {
+    if selection.from_number.is_some() || selection.up_to_number.is_some() {
+        let Some(number) = chapter_number(chapter) else {
+            return false;
+        };
+        if selection.from_number.is_some_and(|from| number < from)
+            || selection.up_to_number.is_some_and(|up_to| number > up_to)
+        {
+            return false;
+        }
+    }
+
+    let Some(language) = selection.language.as_deref().map(normalize_language_code) else {
+        return true;
+    };
+    let single = chapter.get("language").into_iter();
+    let lists = ["languageCodes", "availableLanguageCodes"]
+        .iter()
+        .filter_map(|key| chapter.get(*key).and_then(Value::as_array))
+        .flatten();
+    single
+        .chain(lists)
+        .filter_map(Value::as_str)
+        .any(|code| normalize_language_code(code) == language)
+}
+
+fn normalize_language_code(code: &str) -> String {
+    code.trim().to_lowercase().replace('_', "-")
+}
+
+/// Fills empty fields, and refreshes fields whose current value is still the one a previous
+/// merge wrote. Anything the user changed since is left alone. Returns the top-level keys
+/// that changed.
+fn merge_metadata_fields(data: &mut Value, fields: Vec<MetadataField>) -> Vec<&'static str> {
+    let Some(object) = data.as_object_mut() else {
+        return Vec::new();
+    };
+
+    let mut applied = object
+        .get(COMIC_INFO_FIELDS_KEY)
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let mut changed = Vec::new();
+
+    for field in fields {
+        let Some(value) = field.value.filter(|value| !is_empty_metadata(Some(value))) else {
+            continue;
+        };
+        let current = object.get(field.key);
+        if current == Some(&value) {
+            continue;
+        }
+
+        let owned_by_merge =
+            !field.fill_only && current.is_some() && applied.get(field.key) == current;
+        if is_empty_metadata(current) || owned_by_merge {
+            object.insert(field.key.to_string(), value.clone());
+            applied.insert(field.key.to_string(), value);
+            changed.push(field.key);
+        }
+    }
+
+    if !changed.is_empty() {
+        object.insert(COMIC_INFO_FIELDS_KEY.to_string(), Value::Object(applied));
+        changed.push(COMIC_INFO_FIELDS_KEY);
+    }
+    changed
+}
+
+fn is_empty_metadata(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => true,
+        Some(Value::String(text)) => text.trim().is_empty(),
+        Some(Value::Array(items)) => items.is_empty(),
+        Some(Value::Object(map)) => map.is_empty(),
+        Some(_) => false,
     }
 }
 
@@ -159,6 +647,14 @@ mod tests {
             Ok(None)
         }
 
+        fn get_many(&self, table: Table, _ids: &[String]) -> Result<Vec<DbRecord>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("get_many:{}", table.as_str()));
+            Ok(Vec::new())
+        }
+
         fn list(
             &self,
             table: Table,
@@ -194,10 +690,53 @@ mod tests {
             Ok(true)
         }
 
+        fn write_batch(&self, writes: &[DocumentWrite]) -> Result<Vec<DbRecord>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("batch:{}", writes.len()));
+            Ok(Vec::new())
+        }
+
+        fn set_json_field(
+            &self,
+            table: Table,
+            id: &str,
+            field: &str,
+            _value: &Value,
+        ) -> Result<bool, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("set:{}:{id}:{field}", table.as_str()));
+            Ok(true)
+        }
+
+        fn recently_read_comics(&self, _limit: u32) -> Result<Vec<DbRecord>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("recent:comics".to_string());
+            Ok(Vec::new())
+        }
+
+        fn chapters_stored_in_folders(
+            &self,
+            _folders: &[String],
+        ) -> Result<Vec<DbRecord>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("folders:chapters".to_string());
+            Ok(Vec::new())
+        }
+
         fn mark_chapters_read_state(
             &self,
             _chapter_ids: &[String],
+            _selection: Option<&ChapterSelection>,
             _read: bool,
+            _page_count: &dyn Fn(&DbRecord) -> Option<i64>,
         ) -> Result<(usize, usize), AppError> {
             self.calls
                 .lock()
@@ -205,6 +744,78 @@ mod tests {
                 .push("mark:chapters".to_string());
             Ok((0, 0))
         }
+
+        fn repair_progress_page_counts(
+            &self,
+            _page_count: &dyn Fn(&DbRecord) -> Option<i64>,
+        ) -> Result<(usize, usize), AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("repair:read_progress".to_string());
+            Ok((0, 0))
+        }
+
+        fn reading_stats(&self, _range: &ReadingStatsRange) -> Result<ReadingStats, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("stats:reading".to_string());
+            Ok(ReadingStats::default())
+        }
+
+        fn comic_progress(&self, _comic_id: Option<&str>) -> Result<Vec<ComicProgress>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("progress:comics".to_string());
+            Ok(Vec::new())
+        }
+
+        fn list_tags(&self) -> Result<Vec<TagSummary>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("list:tags".to_string());
+            Ok(Vec::new())
+        }
+
+        fn update_tag(
+            &self,
+            tag_id: &str,
+            _name: Option<&str>,
+            _aliases: Option<&[String]>,
+        ) -> Result<Option<TagSummary>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("update:tags:{tag_id}"));
+            Ok(None)
+        }
+
+        fn comic_tags(&self, comic_id: &str) -> Result<Vec<ComicTag>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("find:comic_tags:{comic_id}"));
+            Ok(Vec::new())
+        }
+
+        fn set_comic_user_tags(&self, comic_id: &str, _names: &[String]) -> Result<(), AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push(format!("set:comic_tags:{comic_id}"));
+            Ok(())
+        }
+
+        fn filter_comics(&self, _filter: &LibraryFilter) -> Result<Vec<DbRecord>, AppError> {
+            self.calls
+                .lock()
+                .expect("poisoned")
+                .push("filter:comics".to_string());
+            Ok(Vec::new())
+        }
     }
 
     #[test]
@@ -223,4 +834,80 @@ mod tests {
         let calls = store.calls.lock().expect("poisoned");
         assert!(calls.iter().any(|value| value == "upsert:comics"));
     }
+
+    #[test]
+    fn parses_chapter_number_labels() {
+        assert_eq!(parse_chapter_number("12"), Some(12.0));
+        assert_eq!(parse_chapter_number("10,5"), Some(10.5));
+        assert_eq!(parse_chapter_number("Ch. 10.5"), Some(10.5));
+        assert_eq!(parse_chapter_number("Vol. 2 Chapter 7"), Some(7.0));
+        assert_eq!(parse_chapter_number("#3 - The Return"), Some(3.0));
+        assert_eq!(parse_chapter_number("Extra"), None);
+    }
+
+    #[test]
+    fn selects_chapters_by_number_range_and_language() {
+        let selection = ChapterSelection {
+            comic_id: "comic-1".to_string(),
+            up_to_number: Some(120.0),
+            language: Some("pt_BR".to_string()),
+            ..ChapterSelection::default()
+        };
+        let chapter = |number: Value, codes: Value| {
+            let mut data = serde_json::json!({ "languageCodes": codes });
+            data["number"] = number;
+            data
+        };
+
+        assert!(chapter_in_selection(
+            &selection,
+            &chapter(Value::from("Ch. 99.5"), serde_json::json!(["pt-br"]))
+        ));
+        assert!(chapter_in_selection(
+            &selection,
+            &chapter(Value::from(120), serde_json::json!(["en", "pt-BR"]))
+        ));
+        assert!(!chapter_in_selection(
+            &selection,
+            &chapter(Value::from("121"), serde_json::json!(["pt-br"]))
+        ));
+        assert!(!chapter_in_selection(
+            &selection,
+            &chapter(Value::from("Extra"), serde_json::json!(["pt-br"]))
+        ));
+        assert!(!chapter_in_selection(
+            &selection,
+            &chapter(Value::from("3"), serde_json::json!(["en"]))
+        ));
+    }
+
+    #[test]
+    fn comic_info_merge_keeps_user_edits() {
+        let info = ComicInfo {
+            title: Some("The Return".to_string()),
+            summary: Some("From ComicInfo".to_string()),
+            writer: Some("Ana".to_string()),
+            ..ComicInfo::default()
+        };
+        let mut data = serde_json::json!({ "name": "Chapter 1", "summary": "" });
+
+        assert_eq!(
+            merge_metadata_fields(&mut data, chapter_comic_info_fields(&info)),
+            ["summary", "writer", COMIC_INFO_FIELDS_KEY]
+        );
+        assert_eq!(data["name"], "Chapter 1");
+        assert_eq!(data["summary"], "From ComicInfo");
+        assert_eq!(data["writer"], "Ana");
+
+        data["summary"] = Value::from("Edited by hand");
+        let updated = ComicInfo {
+            summary: Some("Newer ComicInfo".to_string()),
+            writer: Some("Ana & Luis".to_string()),
+            ..info
+        };
+        assert!(!merge_metadata_fields(&mut data, chapter_comic_info_fields(&updated)).is_empty());
+        assert_eq!(data["summary"], "Edited by hand");
+        assert_eq!(data["writer"], "Ana & Luis");
+        assert!(merge_metadata_fields(&mut data, chapter_comic_info_fields(&updated)).is_empty());
+    }
 }
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Genres that plugins and imports name in different languages or spellings, with the
+/// canonical id and name each one is stored under.
+const GENRE_VOCABULARY: &[(&str, &str, &[&str])] = &[
+    ("action", "Action", &["ação", "acción"]),
+    ("adventure", "Adventure", &["aventura", "aventuras"]),
+    ("comedy", "Comedy", &["comédia", "comedia", "humor"]),
+    ("cooking", "Cooking", &["culinária", "cocina", "gourmet"]),
+    ("drama", "Drama", &[]),
+    ("ecchi", "Ecchi", &[]),
+    ("fantasy", "Fantasy", &["fantasia", "fantasía"]),
+    ("harem", "Harem", &["harém", "harén"]),
+    (
+        "historical",
+        "Historical",
+        &["histórico", "history", "história", "historia"],
+    ),
+    ("horror", "Horror", &["terror"]),
+    ("isekai", "Isekai", &[]),
+    ("josei", "Josei", &[]),
+    (
+        "martial-arts",
+        "Martial Arts",
+        &["artes marciais", "artes marciales"],
+    ),
+    ("mecha", "Mecha", &["robots"]),
+    ("music", "Music", &["música"]),
+    ("mystery", "Mystery", &["mistério", "misterio"]),
+    ("psychological", "Psychological", &["psicológico"]),
+    (
+        "romance",
+        "Romance",
+        &["romântico", "romántico", "romantic"],
+    ),
+    (
+        "school-life",
+        "School Life",
+        &["school", "escolar", "vida escolar"],
+    ),
+    (
+        "sci-fi",
+        "Sci-Fi",
+        &[
+            "scifi",
+            "science fiction",
+            "ficção científica",
+            "ciencia ficción",
+        ],
+    ),
+    ("seinen", "Seinen", &[]),
+    ("shoujo", "Shoujo", &["shojo"]),
+    ("shounen", "Shounen", &["shonen"]),
+    (
+        "slice-of-life",
+        "Slice of Life",
+        &["cotidiano", "vida cotidiana", "recuentos de la vida"],
+    ),
+    (
+        "sports",
+        "Sports",
+        &["sport", "esporte", "esportes", "deporte", "deportes"],
+    ),
+    ("supernatural", "Supernatural", &["sobrenatural"]),
+    ("thriller", "Thriller", &["suspense", "suspenso"]),
+    ("tragedy", "Tragedy", &["tragédia", "tragedia"]),
+];
+
+/// The tag a genre or tag name resolves to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CanonicalTag {
+    pub id: String,
+    pub name: String,
+}
+
+/// Genre names held in a document value, in order and without repeats: a string separated by
+/// commas, semicolons or pipes, a JSON array encoded as a string, or an array of strings or
+/// of objects with a `name`, `title` or `label`.
+pub fn genre_names(value: &Value) -> Vec<String> {
+    let names = match value {
+        Value::String(text) => match serde_json::from_str::<Value>(text) {
+            Ok(parsed @ Value::Array(_)) => return genre_names(&parsed),
+            _ => text.split([',', ';', '|']).map(str::to_string).collect(),
+        },
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|value| match value {
+                Value::String(name) => Some(name.clone()),
+                Value::Object(object) => ["name", "title", "label"]
+                    .iter()
+                    .find_map(|key| object.get(*key).and_then(Value::as_str))
+                    .map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut unique = Vec::<String>::new();
+    for name in names {
+        let name = name.trim().nfc().collect::<String>();
+        if !name.is_empty() && !unique.iter().any(|seen| tag_slug(seen) == tag_slug(&name)) {
+            unique.push(name);
+        }
+    }
+    unique
+}
+
+/// Lowercase name without accents, with every run of other characters turned into a `-`.
+/// Letters from every script are kept.
+pub fn tag_slug(name: &str) -> String {
+    let mut slug = String::new();
+    for ch in name.nfd().filter(|ch| !is_combining_mark(*ch)) {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Resolves a name through `aliases` (alias slugs to tag ids, taken from the stored tags),
+/// then the built-in genre vocabulary. Unknown names become tags of their own.
+pub fn canonical_tag(name: &str, aliases: &HashMap<String, String>) -> Option<CanonicalTag> {
+    let slug = tag_slug(name);
+    if slug.is_empty() {
+        return None;
+    }
+    if let Some(id) = aliases.get(&slug) {
+        return Some(CanonicalTag {
+            id: id.clone(),
+            name: name.trim().to_string(),
+        });
+    }
+
+    let known = GENRE_VOCABULARY.iter().find(|(id, canonical, names)| {
+        *id == slug
+            || tag_slug(canonical) == slug
+            || names.iter().any(|alias| tag_slug(alias) == slug)
+    });
+    Some(match known {
+        Some((id, canonical, _)) => CanonicalTag {
+            id: id.to_string(),
+            name: canonical.to_string(),
+        },
+        None => CanonicalTag {
+            name: name.trim().nfc().collect(),
+            id: slug,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_and_normalizes_genre_values() {
+        assert_eq!(
+            genre_names(&json!("Ação, Comédia; ação")),
+            ["Ação", "Comédia"]
+        );
+        assert_eq!(
+            genre_names(&json!(r#"["Drama","Horror"]"#)),
+            ["Drama", "Horror"]
+        );
+        assert_eq!(
+            genre_names(&json!([{ "name": "Romance" }, "Slice of Life", 3])),
+            ["Romance", "Slice of Life"]
+        );
+
+        let resolve = |name: &str| canonical_tag(name, &HashMap::new()).map(|tag| tag.id);
+        assert_eq!(resolve("Ação").as_deref(), Some("action"));
+        assert_eq!(resolve("Ciencia Ficción").as_deref(), Some("sci-fi"));
+        assert_eq!(resolve("SCI-FI").as_deref(), Some("sci-fi"));
+        assert_eq!(resolve("Cyberpunk").as_deref(), Some("cyberpunk"));
+        assert_eq!(resolve(" - "), None);
+
+        let aliases = HashMap::from([("cyber-punk".to_string(), "cyberpunk".to_string())]);
+        assert_eq!(
+            canonical_tag("Cyber Punk", &aliases).map(|tag| tag.id),
+            Some("cyberpunk".to_string())
+        );
+    }
+}
@@ -10,6 +10,7 @@ pub struct AppPaths {
     pub covers: PathBuf,
     pub settings: PathBuf,
     pub wallpapers: PathBuf,
+    pub cache: PathBuf,
 }
 
 pub fn resolve_app_paths(_app: &tauri::App) -> Result<AppPaths, String> {
@@ -41,6 +42,7 @@ pub fn resolve_app_paths(_app: &tauri::App) -> Result<AppPaths, String> {
         covers: root.join("comics").join("covers"),
         settings: root.join("settings"),
         wallpapers: root.join("wallpapers"),
+        cache: root.join("cache"),
         root,
     };
 
@@ -54,6 +56,8 @@ pub fn resolve_app_paths(_app: &tauri::App) -> Result<AppPaths, String> {
         .map_err(|e| format!("Unable to create settings dir: {e}"))?;
     std::fs::create_dir_all(&paths.wallpapers)
         .map_err(|e| format!("Unable to create wallpapers dir: {e}"))?;
+    std::fs::create_dir_all(&paths.cache)
+        .map_err(|e| format!("Unable to create cache dir: {e}"))?;
 
     Ok(paths)
 }
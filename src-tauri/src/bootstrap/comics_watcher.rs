@@ -0,0 +1,194 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use notify_debouncer_mini::{
+    new_debouncer,
+    notify::{RecommendedWatcher, RecursiveMode},
+    DebounceEventResult, Debouncer,
+};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+use super::{
+    comic_names_by_id, list_all_records, record_value_as_string, refresh_chapters_offline_status,
+};
+use crate::{
+    application::DocumentService,
+    domain::DbRecord,
+    infrastructure::library_files::comic_dir_matches,
+    presentation::{LibraryEvent, LibraryEvents, OfflineChangedEvent},
+};
+
+/// Archives are usually copied or extracted as bursts of writes; wait for them to settle.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(750);
+const OFFLINE_CHANGED_EVENT: &str = "library://offline-changed";
+
+/// Keeps the watcher running for as long as it is managed by the app. The mutex only makes
+/// the debouncer shareable as Tauri state; it is never locked.
+pub struct ComicsWatcher {
+    _debouncer: Mutex<Debouncer<RecommendedWatcher>>,
+}
+
+/// Watches `comics_dir` recursively and, for every comic folder that changed, recomputes
+/// the offline availability of that comic's chapters only.
+pub fn watch_comics_dir(
+    app_handle: AppHandle,
+    service: DocumentService,
+    comics_dir: PathBuf,
+    events: LibraryEvents,
+) -> Result<ComicsWatcher, String> {
+    let root = comics_dir.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |result: DebounceEventResult| {
+        let changes = match result {
+            Ok(changes) => changes,
+            Err(error) => {
+                eprintln!("Comics watcher error: {error}");
+                return;
+            }
+        };
+
+        let folders = changed_comic_folders(&root, changes.iter().map(|change| &*change.path));
+        if folders.is_empty() {
+            return;
+        }
+
+        match refresh_comic_folders(&service, &root, &folders) {
+            Ok(updates) => {
+                for update in updates {
+                    let _ = app_handle.emit(OFFLINE_CHANGED_EVENT, &update);
+                    events.publish(LibraryEvent::OfflineChanged(update));
+                }
+            }
+            Err(error) => eprintln!("Failed to refresh offline chapter status: {error}"),
+        }
+    })
+    .map_err(|e| format!("Failed to create comics watcher: {e}"))?;
+
+    debouncer
+        .watcher()
+        .watch(&comics_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {e}", comics_dir.display()))?;
+
+    Ok(ComicsWatcher {
+        _debouncer: Mutex::new(debouncer),
+    })
+}
+
+/// Names of the top-level folders below `comics_dir` that contain a changed path. The
+/// covers folder and hidden entries never hold chapter archives.
+fn changed_comic_folders<'a>(
+    comics_dir: &Path,
+    paths: impl Iterator<Item = &'a Path>,
+) -> BTreeSet<String> {
+    paths
+        .filter_map(
+            |path| match path.strip_prefix(comics_dir).ok()?.components().next()? {
+                Component::Normal(name) => name.to_str().map(str::to_string),
+                _ => None,
+            },
+        )
+        .filter(|name| name != "covers" && !name.starts_with('.'))
+        .collect()
+}
+
+/// Refreshes the chapters of the comics the changed `folders` belong to. Folder names are
+/// derived from comic names, so matching them needs the comics; only the matched comics'
+/// chapters, and chapters stored in those folders, are loaded.
+fn refresh_comic_folders(
+    service: &DocumentService,
+    comics_dir: &Path,
+    folders: &BTreeSet<String>,
+) -> Result<Vec<OfflineChangedEvent>, String> {
+    let comics = list_all_records(service, "comics", 500)?;
+    let comic_names = comic_names_by_id(&comics);
+    let watched_comics = comics
+        .iter()
+        .filter(|comic| {
+            let comic_name =
+                record_value_as_string(&comic.data, "name").unwrap_or_else(|| comic.id.clone());
+            folders
+                .iter()
+                .any(|folder| comic_dir_matches(&comic.id, &comic_name, folder))
+        })
+        .map(|comic| comic.id.clone())
+        .collect::<Vec<_>>();
+
+    let mut chapters = Vec::new();
+    for comic_id in watched_comics {
+        chapters.extend(
+            service
+                .find_by_json_field(
+                    "chapters",
+                    "comicId",
+                    Value::String(comic_id),
+                    Some(u32::MAX),
+                )
+                .map_err(|e| e.to_string())?,
+        );
+    }
+    // Chapters keep their stored folder even after the comic has been renamed.
+    let folders = folders.iter().cloned().collect::<Vec<_>>();
+    chapters.extend(
+        service
+            .chapters_stored_in_folders(&folders)
+            .map_err(|e| e.to_string())?,
+    );
+
+    let mut seen = HashSet::new();
+    let mut affected: BTreeMap<String, Vec<DbRecord>> = BTreeMap::new();
+    for chapter in chapters {
+        let Some(comic_id) = record_value_as_string(&chapter.data, "comicId") else {
+            continue;
+        };
+        if seen.insert(chapter.id.clone()) {
+            affected.entry(comic_id).or_default().push(chapter);
+        }
+    }
+
+    let mut comic_dirs_cache = HashMap::new();
+    let mut updates = Vec::new();
+    for (comic_id, chapters) in affected {
+        let changed = refresh_chapters_offline_status(
+            service,
+            comics_dir,
+            chapters,
+            &comic_names,
+            &mut comic_dirs_cache,
+        )?;
+        if !changed.is_empty() {
+            updates.push(OfflineChangedEvent {
+                comic_id,
+                chapters: changed,
+            });
+        }
+    }
+
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_changes_to_top_level_comic_folders() {
+        let root = Path::new("/library/comics");
+        let changed = [
+            root.join("Pablo").join("Chapter 1.cbz"),
+            root.join("Pablo (2)").join("Ch.2").join("001.jpg"),
+            root.join("covers").join("pablo.jpg"),
+            root.join(".DS_Store"),
+            PathBuf::from("/elsewhere/Chapter 1.cbz"),
+        ];
+
+        let folders = changed_comic_folders(root, changed.iter().map(PathBuf::as_path));
+        assert_eq!(
+            folders.into_iter().collect::<Vec<_>>(),
+            vec!["Pablo".to_string(), "Pablo (2)".to_string()]
+        );
+    }
+}
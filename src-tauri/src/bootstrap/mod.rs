@@ -1,11 +1,12 @@
 mod app_paths;
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+mod comics_watcher;
 mod system;
 
 use std::sync::Arc;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::HashMap,
     error::Error,
-    fs,
     io,
     path::{Path, PathBuf},
 };
@@ -20,8 +21,16 @@ use tauri_plugin_deep_link::DeepLinkExt;
 
 use crate::{
     application::{AdminService, DocumentService},
-    infrastructure::SqliteDocumentStore,
-    presentation::{start_rest_api, ApiEndpointPayload, RestApiState},
+    domain::{DbRecord, DocumentWrite},
+    infrastructure::{
+        archive::set_cb7_cache_dir,
+        library_files::{
+            comic_dir_candidates, guess_chapter_archive, resolve_local_path, to_local_path,
+            LOCAL_PATH_FIELD,
+        },
+        SqliteDocumentStore,
+    },
+    presentation::{start_rest_api, ApiEndpointPayload, ChapterAvailability, RestApiState},
 };
 
 use self::system::resolve_machine_hostname;
@@ -96,9 +105,13 @@ fn setup_app(app: &mut tauri::App) -> Result<(), Box<dyn Error>> {
     println!("Comics dir: {}", paths.comics.display());
     println!("Settings dir: {}", paths.settings.display());
     println!("Wallpapers dir: {}", paths.wallpapers.display());
+    println!("Cache dir: {}", paths.cache.display());
+
+    set_cb7_cache_dir(paths.cache.join("cb7"));
 
     let store = Arc::new(
-        SqliteDocumentStore::initialize(&paths.database).map_err(|e| boxed_error(e.to_string()))?,
+        SqliteDocumentStore::initialize(&paths.database, &paths.comics)
+            .map_err(|e| boxed_error(e.to_string()))?,
     );
     let service = DocumentService::new(store.clone());
     let admin_service = AdminService::new(store.clone());
@@ -107,7 +120,13 @@ fn setup_app(app: &mut tauri::App) -> Result<(), Box<dyn Error>> {
     sync_chapters_offline_status(&service, &paths.comics)
         .map_err(|error| boxed_error(format!("Failed to sync offline chapter status: {error}")))?;
 
-    match start_rest_api(service, admin_service, paths.comics.clone()) {
+    match start_rest_api(
+        service.clone(),
+        admin_service,
+        paths.comics.clone(),
+        paths.covers.clone(),
+        paths.cache.join("images"),
+    ) {
         Ok(api) => {
             let endpoint = api.endpoint();
             println!(
@@ -115,6 +134,20 @@ fn setup_app(app: &mut tauri::App) -> Result<(), Box<dyn Error>> {
                 endpoint.base_url, endpoint.port
             );
             app.manage(ApiEndpointState(endpoint));
+
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            match comics_watcher::watch_comics_dir(
+                app.handle().clone(),
+                service,
+                paths.comics.clone(),
+                api.events(),
+            ) {
+                Ok(watcher) => {
+                    app.manage(watcher);
+                }
+                Err(error) => eprintln!("Failed to watch comics dir: {error}"),
+            }
+
             app.manage(api);
         }
         Err(error) => {
@@ -189,19 +222,47 @@ fn seed_default_plugins(service: &DocumentService) -> Result<(), String> {
 fn sync_chapters_offline_status(service: &DocumentService, comics_dir: &Path) -> Result<(), String> {
     let chapters = list_all_records(service, "chapters", 1000)?;
     let comics = list_all_records(service, "comics", 500)?;
-    let mut comic_names = HashMap::new();
-    for comic in comics {
-        if let Some(name) = record_value_as_string(&comic.data, "name") {
-            comic_names.insert(comic.id, name);
-        }
-    }
+    let comic_names = comic_names_by_id(&comics);
+    let checked = chapters.len();
+    let updated = refresh_chapters_offline_status(
+        service,
+        comics_dir,
+        chapters,
+        &comic_names,
+        &mut HashMap::new(),
+    )?
+    .len();
+
+    println!(
+        "Offline chapter status sync finished: checked {}, updated {}",
+        checked, updated
+    );
 
-    let mut comic_dirs_cache: HashMap<String, Vec<PathBuf>> = HashMap::new();
-    let mut checked = 0usize;
-    let mut updated = 0usize;
+    Ok(())
+}
+
+fn comic_names_by_id(comics: &[DbRecord]) -> HashMap<String, String> {
+    comics
+        .iter()
+        .filter_map(|comic| {
+            record_value_as_string(&comic.data, "name").map(|name| (comic.id.clone(), name))
+        })
+        .collect()
+}
+
+/// Recomputes `hasOffline`/`offline` for `chapters` from the archives on disk and returns
+/// the chapters whose availability changed. The stored `localPath` is tried first; a file
+/// found by name guessing becomes the chapter's `localPath`.
+fn refresh_chapters_offline_status(
+    service: &DocumentService,
+    comics_dir: &Path,
+    chapters: Vec<DbRecord>,
+    comic_names: &HashMap<String, String>,
+    comic_dirs_cache: &mut HashMap<String, Vec<PathBuf>>,
+) -> Result<Vec<ChapterAvailability>, String> {
+    let mut changed = Vec::new();
 
     for chapter in chapters {
-        checked += 1;
         let comic_id = match record_value_as_string(&chapter.data, "comicId") {
             Some(value) => value,
             None => continue,
@@ -212,14 +273,20 @@ fn sync_chapters_offline_status(service: &DocumentService, comics_dir: &Path) ->
             .unwrap_or_else(|| comic_id.clone());
         let chapter_name = chapter_display_name(&chapter.data);
         let chapter_number = record_value_as_string(&chapter.data, "number");
-        let has_local_cbz = chapter_has_local_cbz(
-            comics_dir,
-            &comic_id,
-            &comic_name,
-            &chapter_name,
-            chapter_number.as_deref(),
-            &mut comic_dirs_cache,
-        );
+        let stored_path = record_value_as_string(&chapter.data, LOCAL_PATH_FIELD);
+        let archive_path = stored_path
+            .as_deref()
+            .and_then(|local_path| resolve_local_path(comics_dir, local_path))
+            .or_else(|| {
+                let dir_candidates = comic_dirs_cache
+                    .entry(comic_id.clone())
+                    .or_insert_with(|| comic_dir_candidates(comics_dir, &comic_id, &comic_name));
+                guess_chapter_archive(dir_candidates, &chapter_name, chapter_number.as_deref())
+            });
+        let has_local_cbz = archive_path.is_some();
+        let local_path = archive_path
+            .as_deref()
+            .and_then(|path| to_local_path(comics_dir, path));
 
         let current_has_offline = chapter
             .data
@@ -232,38 +299,47 @@ fn sync_chapters_offline_status(service: &DocumentService, comics_dir: &Path) ->
             .and_then(Value::as_i64)
             .unwrap_or(0)
             != 0;
-        if current_has_offline == has_local_cbz && current_offline == has_local_cbz {
+        let availability_changed =
+            current_has_offline != has_local_cbz || current_offline != has_local_cbz;
+        // A missing file keeps its stored path, so it is found again if it comes back.
+        let path_changed = local_path.is_some() && local_path != stored_path;
+        if !availability_changed && !path_changed {
             continue;
         }
 
-        let mut next_data = chapter.data.clone();
-        if let Value::Object(ref mut map) = next_data {
-            map.insert("hasOffline".to_string(), Value::Bool(has_local_cbz));
-            map.insert(
-                "offline".to_string(),
-                Value::Number(serde_json::Number::from(if has_local_cbz { 1 } else { 0 })),
-            );
+        // Only the availability fields are written: this also runs from the watcher while
+        // the app is in use, and the chapter read above may be stale by now.
+        let set_field = |field: &str, value: Value| {
+            DocumentWrite::set_field("chapters", &chapter.id, field, value)
+        };
+        let mut writes = vec![
+            set_field("hasOffline", Value::Bool(has_local_cbz)),
+            set_field("offline", Value::from(if has_local_cbz { 1 } else { 0 })),
+        ];
+        if let Some(local_path) = local_path {
+            writes.push(set_field(LOCAL_PATH_FIELD, Value::String(local_path)));
         }
-
-        service
-            .upsert("chapters", Some(chapter.id), next_data)
+        let writes = writes
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
             .map_err(|e| e.to_string())?;
-        updated += 1;
+        service.write_batch(&writes).map_err(|e| e.to_string())?;
+        if availability_changed {
+            changed.push(ChapterAvailability {
+                chapter_id: chapter.id,
+                has_offline: has_local_cbz,
+            });
+        }
     }
 
-    println!(
-        "Offline chapter status sync finished: checked {}, updated {}",
-        checked, updated
-    );
-
-    Ok(())
+    Ok(changed)
 }
 
 fn list_all_records(
     service: &DocumentService,
     table_name: &str,
     page_size: u32,
-) -> Result<Vec<crate::domain::DbRecord>, String> {
+) -> Result<Vec<DbRecord>, String> {
     let mut all = Vec::new();
     let mut offset = 0u32;
     loop {
@@ -297,107 +373,6 @@ fn chapter_display_name(data: &Value) -> String {
         .unwrap_or_else(|| "chapter".to_string())
 }
 
-fn chapter_has_local_cbz(
-    comics_dir: &Path,
-    comic_id: &str,
-    comic_name: &str,
-    chapter_name: &str,
-    chapter_number: Option<&str>,
-    comic_dirs_cache: &mut HashMap<String, Vec<PathBuf>>,
-) -> bool {
-    let chapter_file_candidates = chapter_file_candidates(chapter_name, chapter_number);
-    let dir_candidates = comic_dirs_cache
-        .entry(comic_id.to_string())
-        .or_insert_with(|| build_comic_dir_candidates(comics_dir, comic_id, comic_name));
-
-    for dir in dir_candidates {
-        for candidate in &chapter_file_candidates {
-            if dir.join(candidate).is_file() {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-fn build_comic_dir_candidates(comics_dir: &Path, comic_id: &str, comic_name: &str) -> Vec<PathBuf> {
-    let comic_base = sanitize_segment(comic_name);
-    let mut dir_candidates = vec![
-        comics_dir.join(&comic_base),
-        comics_dir.join(sanitize_segment(comic_id)),
-    ];
-
-    if let Ok(entries) = fs::read_dir(comics_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-            if let Some(name) = path.file_name().and_then(|v| v.to_str()) {
-                if name == comic_base || name.starts_with(&format!("{comic_base} (")) {
-                    dir_candidates.push(path);
-                }
-            }
-        }
-    }
-
-    let mut unique = HashSet::new();
-    dir_candidates
-        .into_iter()
-        .filter(|path| unique.insert(path.clone()))
-        .collect()
-}
-
-fn chapter_file_candidates(chapter_name: &str, chapter_number: Option<&str>) -> Vec<String> {
-    let mut values = Vec::new();
-    let mut seen = HashSet::new();
-    let sanitized_name = sanitize_segment(chapter_name);
-
-    if seen.insert(sanitized_name.clone()) {
-        values.push(format!("{sanitized_name}.cbz"));
-    }
-
-    if let Some(number) = chapter_number {
-        let sanitized_number = sanitize_segment(number);
-        if seen.insert(sanitized_number.clone()) {
-            values.push(format!("{sanitized_number}.cbz"));
-        }
-
-        let old_format = sanitize_segment(&format!("{number} - {chapter_name}"));
-        if seen.insert(old_format.clone()) {
-            values.push(format!("{old_format}.cbz"));
-        }
-
-        let fallback = sanitize_segment(&format!("Chapter {number}"));
-        if seen.insert(fallback.clone()) {
-            values.push(format!("{fallback}.cbz"));
-        }
-    }
-
-    values
-}
-
-fn sanitize_segment(value: &str) -> String {
-    let mut out = String::new();
-    for ch in value.chars() {
-        let accepted = ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | ' ');
-        if accepted {
-            out.push(ch);
-        } else {
-            out.push('_');
-        }
-    }
-
-    let cleaned = out.trim().trim_start_matches('.').to_string();
-    if cleaned.is_empty() {
-        "untitled".to_string()
-    } else if cleaned.len() > 180 {
-        cleaned[..180].to_string()
-    } else {
-        cleaned
-    }
-}
-
 fn emit_endpoint_on_page_load(window: &tauri::Webview) {
     if let Some(endpoint_state) = window.app_handle().try_state::<ApiEndpointState>() {
         let _ = window.emit("api://endpoint", &endpoint_state.0);
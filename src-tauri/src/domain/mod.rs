@@ -18,6 +18,270 @@ pub struct LegacyImportReport {
     pub imported_rows: usize,
 }
 
+/// Metadata embedded in a chapter archive as `ComicInfo.xml` (ComicRack schema).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicInfo {
+    pub title: Option<String>,
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub count: Option<i64>,
+    pub volume: Option<i64>,
+    pub summary: Option<String>,
+    pub notes: Option<String>,
+    pub year: Option<i32>,
+    pub month: Option<i32>,
+    pub day: Option<i32>,
+    pub writer: Option<String>,
+    pub penciller: Option<String>,
+    pub inker: Option<String>,
+    pub colorist: Option<String>,
+    pub letterer: Option<String>,
+    pub cover_artist: Option<String>,
+    pub editor: Option<String>,
+    pub translator: Option<String>,
+    pub publisher: Option<String>,
+    pub genre: Option<String>,
+    pub tags: Option<String>,
+    pub web: Option<String>,
+    pub page_count: Option<u32>,
+    pub language_iso: Option<String>,
+    pub format: Option<String>,
+    pub manga: Option<String>,
+    pub age_rating: Option<String>,
+    pub pages: Vec<ComicInfoPage>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicInfoPage {
+    pub image: usize,
+    pub page_type: Option<String>,
+    pub double_page: bool,
+    pub image_width: Option<u32>,
+    pub image_height: Option<u32>,
+    pub image_size: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveHealthStatus {
+    Ok,
+    CorruptPages,
+    Unreadable,
+}
+
+/// Result of verifying a chapter archive, stored on the chapter as `archiveHealth`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveHealth {
+    pub status: ArchiveHealthStatus,
+    pub page_count: usize,
+    /// Indices of the pages that failed their CRC check or did not decode.
+    pub corrupt_pages: Vec<usize>,
+    pub error: Option<String>,
+    /// Unix time in milliseconds.
+    pub checked_at: u64,
+    /// Size and modification time (Unix seconds) of the verified file, so an unchanged
+    /// archive is not read again.
+    pub archive_size: u64,
+    pub archive_modified: u64,
+}
+
+/// Chapters of one comic picked for a bulk read-state change. Number bounds are inclusive and
+/// compared numerically; chapters without a number only match when no bound is set.
+#[derive(Clone, Debug, Default)]
+pub struct ChapterSelection {
+    pub comic_id: String,
+    pub from_number: Option<f64>,
+    pub up_to_number: Option<f64>,
+    pub language: Option<String>,
+}
+
+/// Which comics to list from the library. Tags are matched by name or alias.
+#[derive(Clone, Debug, Default)]
+pub struct LibraryFilter {
+    /// Comics must carry every one of these tags.
+    pub include_tags: Vec<String>,
+    /// Comics carrying any of these tags are left out.
+    pub exclude_tags: Vec<String>,
+    /// Only favorites, or only comics that are not.
+    pub favorite: Option<bool>,
+    /// Lowest personal rating, 0 to 10. Unrated comics are left out when set.
+    pub min_rating: Option<i64>,
+    pub sort: LibrarySort,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LibrarySort {
+    #[default]
+    Name,
+    /// Highest personal rating first, unrated comics last.
+    Rating,
+    /// Favorites first, then by name.
+    Favorite,
+}
+
+impl LibrarySort {
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        match input {
+            "name" => Ok(Self::Name),
+            "rating" => Ok(Self::Rating),
+            "favorite" => Ok(Self::Favorite),
+            _ => Err(AppError::Validation(format!("Unsupported sort: {input}"))),
+        }
+    }
+}
+
+/// Local date range for reading statistics. Dates are `YYYY-MM-DD`; missing bounds default to
+/// the last 30 days.
+#[derive(Clone, Debug, Default)]
+pub struct ReadingStatsRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// Minutes east of UTC, used to split sessions into the reader's local days.
+    pub utc_offset_minutes: i32,
+    pub top_limit: usize,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingDay {
+    pub date: String,
+    pub pages_read: i64,
+    /// Chapters finished that day.
+    pub chapters_read: i64,
+    pub seconds_read: i64,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingComicStat {
+    pub comic_id: String,
+    pub name: Option<String>,
+    pub pages_read: i64,
+    pub chapters_read: i64,
+    pub seconds_read: i64,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingGenreStat {
+    pub genre: String,
+    pub pages_read: i64,
+    pub seconds_read: i64,
+    pub comic_count: usize,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSummary {
+    /// Normalized tag name, also used to refer to the tag.
+    pub id: String,
+    pub name: String,
+    /// `genre` for tags first seen in comic genres, `user` for tags added by hand.
+    pub kind: String,
+    /// Other names, in any language, that resolve to this tag.
+    pub aliases: Vec<String>,
+    pub comic_count: i64,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicTag {
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    /// Where the comic got the tag: `genre`, `user`, or both.
+    pub sources: Vec<String>,
+}
+
+/// How far the reader is through a comic. A chapter is read once its saved page reaches its
+/// page count, and in progress when any page was saved before that.
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicProgress {
+    pub comic_id: String,
+    pub total_chapters: i64,
+    pub read_chapters: i64,
+    pub in_progress_chapters: i64,
+    /// Chapters not read to the end yet, in-progress ones included.
+    pub unread_chapters: i64,
+    /// Read chapters out of all chapters, from 0 to 100.
+    pub percent_complete: f64,
+    pub last_read_at: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStats {
+    pub from: String,
+    pub to: String,
+    pub pages_read: i64,
+    pub chapters_read: i64,
+    pub seconds_read: i64,
+    /// Consecutive reading days up to `to`, or up to the day before if nothing was read on
+    /// `to` yet.
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    /// Days with at least one reading session, oldest first.
+    pub days: Vec<ReadingDay>,
+    pub top_comics: Vec<ReadingComicStat>,
+    pub top_genres: Vec<ReadingGenreStat>,
+}
+
+/// One write of a batch applied in a single transaction.
+#[derive(Debug, Clone)]
+pub enum DocumentWrite {
+    Upsert {
+        table: Table,
+        id: Option<String>,
+        data: Value,
+    },
+    Delete {
+        table: Table,
+        id: String,
+    },
+    /// Writes one top-level field in place, keeping the rest of the stored document.
+    SetField {
+        table: Table,
+        id: String,
+        field: String,
+        value: Value,
+    },
+}
+
+impl DocumentWrite {
+    pub fn upsert(table_name: &str, id: Option<String>, data: Value) -> Result<Self, AppError> {
+        Ok(Self::Upsert {
+            table: Table::parse(table_name)?,
+            id,
+            data,
+        })
+    }
+
+    pub fn delete(table_name: &str, id: impl Into<String>) -> Result<Self, AppError> {
+        Ok(Self::Delete {
+            table: Table::parse(table_name)?,
+            id: id.into(),
+        })
+    }
+
+    pub fn set_field(
+        table_name: &str,
+        id: impl Into<String>,
+        field: &str,
+        value: Value,
+    ) -> Result<Self, AppError> {
+        Ok(Self::SetField {
+            table: Table::parse(table_name)?,
+            id: id.into(),
+            field: field.to_string(),
+            value,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum Table {
     Comics,
@@ -30,6 +294,13 @@ pub enum Table {
     CanonicalChapters,
     ChapterVariants,
     ChapterMappings,
+    ReadingSessions,
+    Bookmarks,
+    Collections,
+    CollectionItems,
+    Tags,
+    ComicTags,
+    PersonalRatings,
 }
 
 impl Table {
@@ -45,6 +316,13 @@ impl Table {
             "canonical_chapters" => Ok(Self::CanonicalChapters),
             "chapter_variants" => Ok(Self::ChapterVariants),
             "chapter_mappings" => Ok(Self::ChapterMappings),
+            "reading_sessions" => Ok(Self::ReadingSessions),
+            "bookmarks" => Ok(Self::Bookmarks),
+            "collections" => Ok(Self::Collections),
+            "collection_items" => Ok(Self::CollectionItems),
+            "tags" => Ok(Self::Tags),
+            "comic_tags" => Ok(Self::ComicTags),
+            "personal_ratings" => Ok(Self::PersonalRatings),
             _ => Err(AppError::InvalidTable(input.to_string())),
         }
     }
@@ -61,6 +339,13 @@ impl Table {
             Self::CanonicalChapters => "canonical_chapters",
             Self::ChapterVariants => "chapter_variants",
             Self::ChapterMappings => "chapter_mappings",
+            Self::ReadingSessions => "reading_sessions",
+            Self::Bookmarks => "bookmarks",
+            Self::Collections => "collections",
+            Self::CollectionItems => "collection_items",
+            Self::Tags => "tags",
+            Self::ComicTags => "comic_tags",
+            Self::PersonalRatings => "personal_ratings",
         }
     }
 }
@@ -0,0 +1,975 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex, OnceLock},
+    time::UNIX_EPOCH,
+};
+
+use zip::ZipArchive;
+
+use super::content_hash::stable_hash;
+use crate::domain::AppError;
+
+/// File extensions accepted as chapter archives, in lookup order.
+pub const CHAPTER_ARCHIVE_EXTENSIONS: [&str; 4] = ["cbz", "cb7", "cbt", "cbr"];
+/// Unpacked 7z archives kept on disk; the least recently read ones are removed past this.
+const CB7_CACHED_ARCHIVES: usize = 8;
+
+/// Directory 7z archives are unpacked into, set from the profile's cache dir at startup.
+static CB7_CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+/// Serializes 7z extraction so concurrent page requests unpack an archive only once, and
+/// tracks which unpacked archives are being read.
+static CB7_CACHE: LazyLock<Mutex<Cb7Cache>> = LazyLock::new(Mutex::default);
+
+/// Unpacks 7z archives under `dir` instead of the OS temp dir.
+pub fn set_cb7_cache_dir(dir: PathBuf) {
+    let _ = CB7_CACHE_DIR.set(dir);
+}
+
+#[derive(Clone)]
+pub struct ArchiveEntry {
+    pub archive_index: usize,
+    pub file_name: String,
+    pub size: u64,
+    pub crc32: Option<u32>,
+}
+
+/// Read-only access to the files stored in a chapter archive or folder.
+pub trait ArchiveReader: Send + Sync {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError>;
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError>;
+
+    /// Reads at most `limit` bytes from the start of an entry, enough to sniff a header
+    /// without inflating the whole file where the format allows it.
+    fn read_entry_prefix(&self, entry: &ArchiveEntry, limit: u64) -> Result<Vec<u8>, AppError> {
+        let mut bytes = self.read_entry(entry)?;
+        bytes.truncate(usize::try_from(limit).unwrap_or(usize::MAX));
+        Ok(bytes)
+    }
+
+    /// Path of an entry that is stored as a plain file and can be served without decoding.
+    fn file_path(&self, _entry: &ArchiveEntry) -> Option<PathBuf> {
+        None
+    }
+}
+
+pub fn open_archive(path: &Path) -> Result<Box<dyn ArchiveReader>, AppError> {
+    if path.is_dir() {
+        return Ok(Box::new(DirectoryReader::new(path)));
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|value| value.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "cbz" | "zip" => Ok(Box::new(CbzReader::new(path))),
+        "cb7" | "7z" => Ok(Box::new(Cb7Reader::new(path))),
+        "cbt" | "tar" => Ok(Box::new(CbtReader::new(path))),
+        "cbr" | "rar" => open_rar_archive(path),
+        _ => Err(AppError::Validation(format!(
+            "Unsupported chapter archive: {}",
+            path.display()
+        ))),
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+fn open_rar_archive(path: &Path) -> Result<Box<dyn ArchiveReader>, AppError> {
+    Ok(Box::new(CbrReader::new(path)))
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+fn open_rar_archive(path: &Path) -> Result<Box<dyn ArchiveReader>, AppError> {
+    Err(AppError::Validation(format!(
+        "CBR archives are not supported on this platform: {}",
+        path.display()
+    )))
+}
+
+/// Looks for `{stem}.cbz`, `{stem}.cb7`, `{stem}.cbt`, `{stem}.cbr` or a `{stem}` folder of
+/// images inside `dir`.
+pub fn find_chapter_archive(dir: &Path, stem: &str) -> Option<PathBuf> {
+    for extension in CHAPTER_ARCHIVE_EXTENSIONS {
+        let candidate = dir.join(format!("{stem}.{extension}"));
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+
+    let folder = dir.join(stem);
+    if folder.is_dir() && directory_has_images(&folder) {
+        return Some(folder);
+    }
+
+    None
+}
+
+/// Whether `path` is a chapter archive file or a folder of images.
+pub fn is_chapter_archive_path(path: &Path) -> bool {
+    if path.is_dir() {
+        return directory_has_images(path);
+    }
+
+    path.extension()
+        .and_then(|value| value.to_str())
+        .map(|extension| {
+            CHAPTER_ARCHIVE_EXTENSIONS
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        })
+        .unwrap_or(false)
+}
+
+/// Lists the readable pages of an archive in reading order.
+pub fn list_image_entries(reader: &dyn ArchiveReader) -> Result<Vec<ArchiveEntry>, AppError> {
+    let (mut pages, comic_info) = scan_entries(reader)?;
+
+    // ComicInfo.xml page indexes refer to the sorted image list, so read it last.
+    if let Some(entry) = comic_info {
+        if let Ok(bytes) = reader.read_entry(&entry) {
+            let xml = String::from_utf8_lossy(&bytes);
+            if let Some(order) = comic_info_page_order(&xml) {
+                pages = apply_page_order(pages, &order);
+            }
+        }
+    }
+
+    Ok(pages)
+}
+
+/// Lists the images of an archive in natural file name order, the list ComicInfo.xml
+/// page indexes refer to.
+pub fn sorted_image_entries(reader: &dyn ArchiveReader) -> Result<Vec<ArchiveEntry>, AppError> {
+    scan_entries(reader).map(|(pages, _)| pages)
+}
+
+fn scan_entries(
+    reader: &dyn ArchiveReader,
+) -> Result<(Vec<ArchiveEntry>, Option<ArchiveEntry>), AppError> {
+    let mut pages = Vec::new();
+    let mut comic_info = None;
+    for entry in reader.entries()? {
+        if is_ignored_archive_entry(&entry.file_name) {
+            continue;
+        }
+
+        if is_comic_info_entry(&entry.file_name) {
+            comic_info.get_or_insert(entry);
+            continue;
+        }
+
+        if is_image_file(&entry.file_name) {
+            pages.push(entry);
+        }
+    }
+
+    pages.sort_by(|left, right| natural_path_cmp(&left.file_name, &right.file_name));
+    Ok((pages, comic_info))
+}
+
+pub fn is_image_file(path: &str) -> bool {
+    path.rsplit('.')
+        .next()
+        .map(|ext| {
+            matches!(
+                ext.to_ascii_lowercase().as_str(),
+                "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" | "avif"
+            )
+        })
+        .unwrap_or(false)
+}
+
+pub struct CbzReader {
+    path: PathBuf,
+}
+
+impl CbzReader {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn open(&self) -> Result<ZipArchive<File>, AppError> {
+        let file = File::open(&self.path).map_err(io_error)?;
+        ZipArchive::new(file).map_err(|error| AppError::infrastructure(error.to_string()))
+    }
+}
+
+impl ArchiveReader for CbzReader {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError> {
+        let mut archive = self.open()?;
+        let mut entries = Vec::new();
+        for index in 0..archive.len() {
+            let entry = archive
+                .by_index(index)
+                .map_err(|error| AppError::infrastructure(error.to_string()))?;
+            if !entry.is_file() {
+                continue;
+            }
+
+            entries.push(ArchiveEntry {
+                archive_index: index,
+                file_name: entry.name().to_string(),
+                size: entry.size(),
+                crc32: Some(entry.crc32()),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError> {
+        self.read_entry_prefix(entry, u64::MAX)
+    }
+
+    fn read_entry_prefix(&self, entry: &ArchiveEntry, limit: u64) -> Result<Vec<u8>, AppError> {
+        let mut archive = self.open()?;
+        let file = archive
+            .by_index(entry.archive_index)
+            .map_err(|error| AppError::infrastructure(error.to_string()))?;
+        let mut bytes = Vec::new();
+        file.take(limit).read_to_end(&mut bytes).map_err(io_error)?;
+        Ok(bytes)
+    }
+}
+
+pub struct Cb7Reader {
+    path: PathBuf,
+}
+
+impl Cb7Reader {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl ArchiveReader for Cb7Reader {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError> {
+        let archive = sevenz_rust::Archive::open(&self.path)
+            .map_err(|error| AppError::infrastructure(error.to_string()))?;
+        Ok(archive
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, file)| !file.is_directory && !file.is_anti_item)
+            .map(|(index, file)| ArchiveEntry {
+                archive_index: index,
+                file_name: file.name().to_string(),
+                size: file.size,
+                crc32: file.has_crc.then_some(file.crc as u32),
+            })
+            .collect())
+    }
+
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError> {
+        self.read_entry_prefix(entry, u64::MAX)
+    }
+
+    fn read_entry_prefix(&self, entry: &ArchiveEntry, limit: u64) -> Result<Vec<u8>, AppError> {
+        let lease = self.extracted_dir()?;
+        let path = lease.dir.join(stable_hash(&[&entry.file_name]));
+        let file = File::open(&path).map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => {
+                AppError::infrastructure(format!("Archive entry not found: {}", entry.file_name))
+            }
+            _ => io_error(error),
+        })?;
+        let mut bytes = Vec::new();
+        file.take(limit).read_to_end(&mut bytes).map_err(io_error)?;
+        Ok(bytes)
+    }
+}
+
+impl Cb7Reader {
+    /// Directory holding every entry of the archive, unpacked on first use. Solid 7z blocks
+    /// only decode front to back, so reading pages one at a time would decode the stream
+    /// again up to each page. The directory is not pruned while the lease is held.
+    fn extracted_dir(&self) -> Result<Cb7Lease, AppError> {
+        let metadata = fs::metadata(&self.path).map_err(io_error)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_nanos())
+            .unwrap_or(0);
+        let root = CB7_CACHE_DIR
+            .get()
+            .cloned()
+            .unwrap_or_else(|| std::env::temp_dir().join("comic-universe-cb7"));
+        let dir = root.join(stable_hash(&[
+            &self.path.display().to_string(),
+            &metadata.len().to_string(),
+            &modified.to_string(),
+        ]));
+
+        let mut cache = CB7_CACHE
+            .lock()
+            .map_err(|_| AppError::infrastructure("7z extraction lock poisoned"))?;
+        if !dir.is_dir() {
+            let staging = dir.with_extension("partial");
+            let _ = fs::remove_dir_all(&staging);
+            fs::create_dir_all(&staging).map_err(io_error)?;
+            if let Err(error) = self
+                .extract_to(&staging)
+                .and_then(|_| fs::rename(&staging, &dir).map_err(io_error))
+            {
+                let _ = fs::remove_dir_all(&staging);
+                return Err(error);
+            }
+        }
+        cache.acquire(&dir);
+        cache.prune(&root);
+        Ok(Cb7Lease { dir })
+    }
+
+    /// Decodes the archive in one pass, naming each file after a hash of its entry name.
+    fn extract_to(&self, dir: &Path) -> Result<(), AppError> {
+        let mut reader =
+            sevenz_rust::SevenZReader::open(&self.path, sevenz_rust::Password::empty())
+                .map_err(|error| AppError::infrastructure(error.to_string()))?;
+        reader
+            .for_each_entries(|file, data| {
+                if file.is_directory || file.is_anti_item {
+                    return Ok(true);
+                }
+                let mut output = File::create(dir.join(stable_hash(&[file.name()])))
+                    .map_err(sevenz_rust::Error::io)?;
+                io::copy(data, &mut output).map_err(sevenz_rust::Error::io)?;
+                Ok(true)
+            })
+            .map_err(|error| AppError::infrastructure(error.to_string()))
+    }
+}
+
+/// Unpacked 7z archives being read, and the order they were last opened in. Archives
+/// unpacked before a restart count as the least recently read.
+#[derive(Default)]
+struct Cb7Cache {
+    readers: HashMap<PathBuf, usize>,
+    last_used: HashMap<PathBuf, u64>,
+    uses: u64,
+}
+
+impl Cb7Cache {
+    fn acquire(&mut self, dir: &Path) {
+        self.uses += 1;
+        *self.readers.entry(dir.to_path_buf()).or_default() += 1;
+        self.last_used.insert(dir.to_path_buf(), self.uses);
+    }
+
+    fn release(&mut self, dir: &Path) {
+        if let Some(readers) = self.readers.get_mut(dir) {
+            *readers -= 1;
+            if *readers == 0 {
+                self.readers.remove(dir);
+            }
+        }
+    }
+
+    /// Removes unpacked archives beyond [`CB7_CACHED_ARCHIVES`], least recently read
+    /// first. Archives being read are kept.
+    fn prune(&mut self, root: &Path) {
+        let Ok(read_dir) = fs::read_dir(root) else {
+            return;
+        };
+        let mut extracted = read_dir
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_none())
+            .map(|path| (self.last_used.get(&path).copied().unwrap_or(0), path))
+            .collect::<Vec<_>>();
+        extracted.sort_by_key(|(last_used, _)| std::cmp::Reverse(*last_used));
+        for (_, path) in extracted.into_iter().skip(CB7_CACHED_ARCHIVES) {
+            if self.readers.contains_key(&path) {
+                continue;
+            }
+            let _ = fs::remove_dir_all(&path);
+            self.last_used.remove(&path);
+        }
+    }
+}
+
+/// Keeps an unpacked 7z archive on disk while a page is read from it.
+struct Cb7Lease {
+    dir: PathBuf,
+}
+
+impl Drop for Cb7Lease {
+    fn drop(&mut self) {
+        if let Ok(mut cache) = CB7_CACHE.lock() {
+            cache.release(&self.dir);
+        }
+    }
+}
+
+pub struct CbtReader {
+    path: PathBuf,
+    /// Where each file's data starts in the tar and how long it is, by archive index.
+    offsets: OnceLock<HashMap<usize, (u64, u64)>>,
+}
+
+impl CbtReader {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offsets: OnceLock::new(),
+        }
+    }
+
+    fn open(&self) -> Result<tar::Archive<File>, AppError> {
+        let file = File::open(&self.path).map_err(io_error)?;
+        Ok(tar::Archive::new(file))
+    }
+
+    /// Indexes the tar once, seeking past file data, so pages are read without rescanning
+    /// the archive from the start.
+    fn offsets(&self) -> Result<&HashMap<usize, (u64, u64)>, AppError> {
+        if let Some(offsets) = self.offsets.get() {
+            return Ok(offsets);
+        }
+        let mut archive = self.open()?;
+        let mut offsets = HashMap::new();
+        for (index, entry) in archive.entries_with_seek().map_err(io_error)?.enumerate() {
+            let entry = entry.map_err(io_error)?;
+            offsets.insert(index, (entry.raw_file_position(), entry.size()));
+        }
+        Ok(self.offsets.get_or_init(|| offsets))
+    }
+}
+
+impl ArchiveReader for CbtReader {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError> {
+        let mut archive = self.open()?;
+        let mut entries = Vec::new();
+        for (index, entry) in archive.entries().map_err(io_error)?.enumerate() {
+            let entry = entry.map_err(io_error)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let file_name = entry
+                .path()
+                .map_err(io_error)?
+                .to_string_lossy()
+                .to_string();
+            entries.push(ArchiveEntry {
+                archive_index: index,
+                file_name,
+                size: entry.size(),
+                crc32: None,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError> {
+        self.read_entry_prefix(entry, u64::MAX)
+    }
+
+    fn read_entry_prefix(&self, entry: &ArchiveEntry, limit: u64) -> Result<Vec<u8>, AppError> {
+        let (offset, size) = *self.offsets()?.get(&entry.archive_index).ok_or_else(|| {
+            AppError::infrastructure(format!("Archive entry not found: {}", entry.file_name))
+        })?;
+        let mut file = File::open(&self.path).map_err(io_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+        let mut bytes = Vec::new();
+        file.take(size.min(limit))
+            .read_to_end(&mut bytes)
+            .map_err(io_error)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub struct CbrReader {
+    path: PathBuf,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+impl CbrReader {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+impl ArchiveReader for CbrReader {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError> {
+        let archive = unrar::Archive::new(&self.path)
+            .open_for_listing()
+            .map_err(|error| AppError::infrastructure(error.to_string()))?;
+        let mut entries = Vec::new();
+        for (index, header) in archive.enumerate() {
+            let header = header.map_err(|error| AppError::infrastructure(error.to_string()))?;
+            if !header.is_file() {
+                continue;
+            }
+
+            entries.push(ArchiveEntry {
+                archive_index: index,
+                file_name: header.filename.to_string_lossy().replace('\\', "/"),
+                size: header.unpacked_size,
+                crc32: Some(header.file_crc),
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError> {
+        let mut archive = unrar::Archive::new(&self.path)
+            .open_for_processing()
+            .map_err(|error| AppError::infrastructure(error.to_string()))?;
+        let mut index = 0usize;
+        while let Some(header) = archive
+            .read_header()
+            .map_err(|error| AppError::infrastructure(error.to_string()))?
+        {
+            if index == entry.archive_index {
+                let (bytes, _) = header
+                    .read()
+                    .map_err(|error| AppError::infrastructure(error.to_string()))?;
+                return Ok(bytes);
+            }
+
+            archive = header
+                .skip()
+                .map_err(|error| AppError::infrastructure(error.to_string()))?;
+            index += 1;
+        }
+
+        Err(AppError::infrastructure(format!(
+            "Archive entry not found: {}",
+            entry.file_name
+        )))
+    }
+}
+
+/// Serves a plain folder of images as if it were an archive. Entry names are relative paths.
+pub struct DirectoryReader {
+    root: PathBuf,
+}
+
+impl DirectoryReader {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+impl ArchiveReader for DirectoryReader {
+    fn entries(&self) -> Result<Vec<ArchiveEntry>, AppError> {
+        let mut files = Vec::new();
+        collect_directory_files(&self.root, &self.root, &mut files)?;
+        files.sort_by(|left, right| left.0.cmp(&right.0));
+
+        Ok(files
+            .into_iter()
+            .enumerate()
+            .map(|(index, (file_name, size))| ArchiveEntry {
+                archive_index: index,
+                file_name,
+                size,
+                crc32: None,
+            })
+            .collect())
+    }
+
+    fn read_entry(&self, entry: &ArchiveEntry) -> Result<Vec<u8>, AppError> {
+        if entry.file_name.split('/').any(|segment| segment == "..") {
+            return Err(AppError::Validation(format!(
+                "Invalid entry name: {}",
+                entry.file_name
+            )));
+        }
+
+        fs::read(self.root.join(&entry.file_name)).map_err(io_error)
+    }
+
+    fn file_path(&self, entry: &ArchiveEntry) -> Option<PathBuf> {
+        if entry.file_name.split('/').any(|segment| segment == "..") {
+            return None;
+        }
+
+        Some(self.root.join(&entry.file_name))
+    }
+}
+
+fn collect_directory_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, u64)>,
+) -> Result<(), AppError> {
+    for entry in fs::read_dir(dir).map_err(io_error)?.flatten() {
+        let path = entry.path();
+        let metadata = entry.metadata().map_err(io_error)?;
+        if metadata.is_dir() {
+            collect_directory_files(root, &path, files)?;
+            continue;
+        }
+
+        if let Ok(relative) = path.strip_prefix(root) {
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push((name, metadata.len()));
+        }
+    }
+    Ok(())
+}
+
+fn directory_has_images(dir: &Path) -> bool {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten().any(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .map(|name| !name.starts_with('.') && is_image_file(name))
+                    .unwrap_or(false)
+            })
+        })
+        .unwrap_or(false)
+}
+
+fn io_error(error: io::Error) -> AppError {
+    AppError::infrastructure(error.to_string())
+}
+
+/// Hidden files, macOS resource forks and thumbnail folders are not part of the chapter.
+fn is_ignored_archive_entry(path: &str) -> bool {
+    let segments = path
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let Some((file_name, folders)) = segments.split_last() else {
+        return true;
+    };
+
+    let hidden = |segment: &&str| segment.starts_with('.');
+    if folders.iter().any(hidden) || hidden(file_name) {
+        return true;
+    }
+
+    if folders.iter().any(|folder| {
+        matches!(
+            folder.to_ascii_lowercase().as_str(),
+            "__macosx" | "thumbs" | "thumbnails"
+        )
+    }) {
+        return true;
+    }
+
+    let lower_name = file_name.to_ascii_lowercase();
+    let stem = lower_name
+        .rsplit_once('.')
+        .map(|(stem, _)| stem)
+        .unwrap_or(&lower_name);
+    matches!(stem, "thumb" | "thumbnail")
+}
+
+pub fn is_comic_info_entry(path: &str) -> bool {
+    path.rsplit(['/', '\\'])
+        .next()
+        .map(|name| name.eq_ignore_ascii_case("comicinfo.xml"))
+        .unwrap_or(false)
+}
+
+/// Reading order declared by the `<Pages>` block of a ComicInfo.xml, expressed as indexes
+/// into the naturally sorted image list. Pages typed `Deleted` are collected separately.
+struct ComicInfoPageOrder {
+    images: Vec<usize>,
+    deleted: HashSet<usize>,
+}
+
+fn comic_info_page_order(xml: &str) -> Option<ComicInfoPageOrder> {
+    let document = roxmltree::Document::parse(xml).ok()?;
+    let mut order = ComicInfoPageOrder {
+        images: Vec::new(),
+        deleted: HashSet::new(),
+    };
+
+    for node in document
+        .descendants()
+        .filter(|node| node.has_tag_name("Page"))
+    {
+        let Some(image) = node
+            .attribute("Image")
+            .and_then(|value| value.trim().parse::<usize>().ok())
+        else {
+            continue;
+        };
+        let deleted = node
+            .attribute("Type")
+            .map(|kind| kind.trim().eq_ignore_ascii_case("deleted"))
+            .unwrap_or(false);
+        if deleted {
+            order.deleted.insert(image);
+        } else {
+            order.images.push(image);
+        }
+    }
+
+    if order.images.is_empty() && order.deleted.is_empty() {
+        None
+    } else {
+        Some(order)
+    }
+}
+
+fn apply_page_order(pages: Vec<ArchiveEntry>, order: &ComicInfoPageOrder) -> Vec<ArchiveEntry> {
+    let mut slots = pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, page)| (!order.deleted.contains(&index)).then_some(page))
+        .collect::<Vec<_>>();
+    let mut ordered = Vec::with_capacity(slots.len());
+
+    for image in &order.images {
+        if let Some(page) = slots.get_mut(*image).and_then(Option::take) {
+            ordered.push(page);
+        }
+    }
+
+    // Images the ComicInfo.xml does not mention keep their natural order after the listed ones.
+    ordered.extend(slots.into_iter().flatten());
+    ordered
+}
+
+/// Compares archive paths folder by folder, treating digit runs as numbers so that
+/// `page2.jpg` sorts before `page10.jpg`.
+pub fn natural_path_cmp(left: &str, right: &str) -> Ordering {
+    let mut left_segments = left
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty());
+    let mut right_segments = right
+        .split(['/', '\\'])
+        .filter(|segment| !segment.is_empty());
+
+    loop {
+        match (left_segments.next(), right_segments.next()) {
+            (Some(left), Some(right)) => match natural_cmp(left, right) {
+                Ordering::Equal => continue,
+                other => return other,
+            },
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (None, None) => return left.cmp(right),
+        }
+    }
+}
+
+pub fn natural_cmp(left: &str, right: &str) -> Ordering {
+    let mut left_chars = left.chars().peekable();
+    let mut right_chars = right.chars().peekable();
+
+    loop {
+        match (left_chars.peek().copied(), right_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(l), Some(r)) if l.is_ascii_digit() && r.is_ascii_digit() => {
+                let left_number = take_digits(&mut left_chars);
+                let right_number = take_digits(&mut right_chars);
+                let left_trimmed = left_number.trim_start_matches('0');
+                let right_trimmed = right_number.trim_start_matches('0');
+                let ordering = left_trimmed
+                    .len()
+                    .cmp(&right_trimmed.len())
+                    .then_with(|| left_trimmed.cmp(right_trimmed))
+                    .then_with(|| left_number.len().cmp(&right_number.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(l), Some(r)) => {
+                let ordering = l.to_lowercase().cmp(r.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                left_chars.next();
+                right_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(ch) = chars.peek().copied().filter(char::is_ascii_digit) {
+        digits.push(ch);
+        chars.next();
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn page(archive_index: usize, file_name: &str) -> ArchiveEntry {
+        ArchiveEntry {
+            archive_index,
+            file_name: file_name.to_string(),
+            size: 0,
+            crc32: None,
+        }
+    }
+
+    #[test]
+    fn sorts_archive_paths_naturally_across_folders() {
+        let mut names = vec![
+            "vol2/page10.jpg",
+            "vol2/page2.jpg",
+            "vol1/page010.jpg",
+            "vol1/page9.jpg",
+            "Vol1/page1.jpg",
+        ];
+        names.sort_by(|left, right| natural_path_cmp(left, right));
+        assert_eq!(
+            names,
+            vec![
+                "Vol1/page1.jpg",
+                "vol1/page9.jpg",
+                "vol1/page010.jpg",
+                "vol2/page2.jpg",
+                "vol2/page10.jpg",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_hidden_resource_fork_and_thumbnail_entries() {
+        assert!(is_ignored_archive_entry("__MACOSX/._001.jpg"));
+        assert!(is_ignored_archive_entry("chapter/._002.jpg"));
+        assert!(is_ignored_archive_entry(".thumbnails/001.jpg"));
+        assert!(is_ignored_archive_entry("thumb.jpg"));
+        assert!(!is_ignored_archive_entry("chapter/001.jpg"));
+    }
+
+    #[test]
+    fn applies_comic_info_page_order() {
+        let order = comic_info_page_order(
+            r#"<ComicInfo><Pages>
+                <Page Image="1" />
+                <Page Image="0" Type="FrontCover" />
+                <Page Image="2" Type="Deleted" />
+            </Pages></ComicInfo>"#,
+        )
+        .expect("page order");
+        let pages = vec![
+            page(4, "a.jpg"),
+            page(5, "b.jpg"),
+            page(6, "c.jpg"),
+            page(7, "d.jpg"),
+        ];
+
+        let ordered = apply_page_order(pages, &order)
+            .into_iter()
+            .map(|entry| entry.archive_index)
+            .collect::<Vec<_>>();
+        assert_eq!(ordered, vec![5, 4, 7]);
+    }
+
+    #[test]
+    fn reads_pages_from_cbz_and_plain_folder() {
+        let temp = tempfile::tempdir().expect("create temp root");
+        let root = temp.path();
+        let cbz_path = root.join("Chapter 1.cbz");
+        let mut writer = zip::ZipWriter::new(File::create(&cbz_path).expect("create cbz"));
+        for name in ["10.jpg", "2.jpg", "__MACOSX/._2.jpg"] {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .expect("start entry");
+            writer.write_all(name.as_bytes()).expect("write entry");
+        }
+        writer.finish().expect("finish cbz");
+
+        let folder = root.join("Chapter 2");
+        fs::create_dir_all(&folder).expect("create chapter folder");
+        fs::write(folder.join("1.png"), b"one").expect("write page");
+
+        let reader = open_archive(&cbz_path).expect("open cbz");
+        let pages = list_image_entries(reader.as_ref()).expect("list cbz pages");
+        let names = pages
+            .iter()
+            .map(|entry| entry.file_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["2.jpg", "10.jpg"]);
+        assert_eq!(reader.read_entry(&pages[0]).expect("read page"), b"2.jpg");
+
+        assert_eq!(find_chapter_archive(root, "Chapter 1"), Some(cbz_path));
+        assert_eq!(
+            find_chapter_archive(root, "Chapter 2"),
+            Some(folder.clone())
+        );
+        let folder_reader = open_archive(&folder).expect("open folder");
+        let folder_pages = list_image_entries(folder_reader.as_ref()).expect("list folder");
+        assert_eq!(folder_pages.len(), 1);
+    }
+    #[test]
+    fn reads_cb7_pages_from_one_extraction() {
+        let temp = tempfile::tempdir().expect("create temp root");
+        let cb7_path = temp.path().join("Chapter 1.cb7");
+        let mut writer = sevenz_rust::SevenZWriter::create(&cb7_path).expect("create cb7");
+        for name in ["10.jpg", "2.jpg", "1.jpg"] {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            writer
+                .push_archive_entry(entry, Some(name.as_bytes()))
+                .expect("write entry");
+        }
+        writer.finish().expect("finish cb7");
+
+        let reader = open_archive(&cb7_path).expect("open cb7");
+        let pages = list_image_entries(reader.as_ref()).expect("list cb7 pages");
+        for _ in 0..2 {
+            let contents = pages
+                .iter()
+                .map(|entry| reader.read_entry(entry).expect("read page"))
+                .collect::<Vec<_>>();
+            assert_eq!(contents, [b"1.jpg", b"2.jpg", b"10.jpg".as_slice()]);
+        }
+    }
+    #[test]
+    fn reads_cbt_pages_by_offset() {
+        let temp = tempfile::tempdir().expect("create temp root");
+        let cbt_path = temp.path().join("Chapter 1.cbt");
+        let mut builder = tar::Builder::new(File::create(&cbt_path).expect("create cbt"));
+        for name in ["10.jpg", "2.jpg", "1.jpg"] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(name.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, name.as_bytes())
+                .expect("write entry");
+        }
+        builder.finish().expect("finish cbt");
+
+        let reader = open_archive(&cbt_path).expect("open cbt");
+        let pages = list_image_entries(reader.as_ref()).expect("list cbt pages");
+        let contents = pages
+            .iter()
+            .map(|entry| reader.read_entry(entry).expect("read page"))
+            .collect::<Vec<_>>();
+        assert_eq!(contents, [b"1.jpg", b"2.jpg", b"10.jpg".as_slice()]);
+        assert_eq!(
+            reader.read_entry_prefix(&pages[2], 2).expect("read prefix"),
+            b"10"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    domain::{ArchiveHealth, ArchiveHealthStatus},
+    infrastructure::archive::{list_image_entries, open_archive, ArchiveEntry, ArchiveReader},
+};
+
+/// Reads every page of the archive at `path`. Zip readers check each entry's CRC once it
+/// has been read to the end; pages are then decoded, or only their header is parsed for
+/// formats the image crate is built without.
+pub fn verify_archive(path: &Path) -> ArchiveHealth {
+    let (archive_size, archive_modified) = archive_fingerprint(path);
+    let mut health = match open_archive(path) {
+        Ok(archive) => verify_archive_pages(archive.as_ref()),
+        Err(error) => unreadable(error.to_string()),
+    };
+    health.archive_size = archive_size;
+    health.archive_modified = archive_modified;
+    health
+}
+
+/// Checks the pages of an opened archive like [`verify_archive`], for files whose name does
+/// not tell their format. The result carries no file fingerprint.
+pub fn verify_archive_pages(archive: &dyn ArchiveReader) -> ArchiveHealth {
+    let entries = match list_image_entries(archive) {
+        Ok(entries) if entries.is_empty() => {
+            return unreadable("The archive contains no pages".to_string())
+        }
+        Ok(entries) => entries,
+        Err(error) => return unreadable(error.to_string()),
+    };
+
+    let mut health = unchecked(ArchiveHealthStatus::Ok, None);
+    health.page_count = entries.len();
+    for (index, entry) in entries.iter().enumerate() {
+        if let Err(error) = verify_page(archive, entry) {
+            health
+                .error
+                .get_or_insert(format!("{}: {error}", entry.file_name));
+            health.corrupt_pages.push(index);
+        }
+    }
+    if !health.corrupt_pages.is_empty() {
+        health.status = ArchiveHealthStatus::CorruptPages;
+    }
+    health
+}
+
+fn unreadable(error: String) -> ArchiveHealth {
+    unchecked(ArchiveHealthStatus::Unreadable, Some(error))
+}
+
+fn unchecked(status: ArchiveHealthStatus, error: Option<String>) -> ArchiveHealth {
+    ArchiveHealth {
+        status,
+        page_count: 0,
+        corrupt_pages: Vec::new(),
+        error,
+        checked_at: unix_millis(SystemTime::now()),
+        archive_size: 0,
+        archive_modified: 0,
+    }
+}
+
+/// Whether `health` was recorded for the file as it is now.
+pub fn is_current(health: &ArchiveHealth, path: &Path) -> bool {
+    archive_fingerprint(path) == (health.archive_size, health.archive_modified)
+}
+
+fn verify_page(archive: &dyn ArchiveReader, entry: &ArchiveEntry) -> Result<(), String> {
+    let bytes = archive.read_entry(entry).map_err(|e| e.to_string())?;
+    match image::guess_format(&bytes) {
+        Ok(format) if format.reading_enabled() => {
+            image::load_from_memory_with_format(&bytes, format)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        }
+        _ => imagesize::blob_size(&bytes)
+            .map(|_| ())
+            .map_err(|e| format!("Not a readable image: {e}")),
+    }
+}
+
+fn archive_fingerprint(path: &Path) -> (u64, u64) {
+    fs::metadata(path)
+        .map(|metadata| {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            (metadata.len(), modified)
+        })
+        .unwrap_or((0, 0))
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn png_page() -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::RgbImage::new(2, 2)
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn reports_pages_that_do_not_decode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chapter.cbz");
+        let mut zip = ZipWriter::new(fs::File::create(&path).unwrap());
+        let mut truncated = png_page();
+        truncated.truncate(truncated.len() / 2);
+        for (name, bytes) in [("001.png", png_page()), ("002.png", truncated)] {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let health = verify_archive(&path);
+        assert_eq!(health.status, ArchiveHealthStatus::CorruptPages);
+        assert_eq!(health.page_count, 2);
+        assert_eq!(health.corrupt_pages, vec![1]);
+        assert!(is_current(&health, &path));
+
+        fs::write(&path, b"not a zip").unwrap();
+        assert_eq!(
+            verify_archive(&path).status,
+            ArchiveHealthStatus::Unreadable
+        );
+    }
+}
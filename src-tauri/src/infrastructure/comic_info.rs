@@ -0,0 +1,260 @@
+use roxmltree::{Document, Node};
+
+use crate::{
+    domain::{AppError, ComicInfo, ComicInfoPage},
+    infrastructure::archive::{is_comic_info_entry, ArchiveReader},
+};
+
+/// Reads and parses the `ComicInfo.xml` of an archive, if it has one.
+pub fn read_comic_info(reader: &dyn ArchiveReader) -> Result<Option<ComicInfo>, AppError> {
+    let Some(entry) = reader
+        .entries()?
+        .into_iter()
+        .find(|entry| is_comic_info_entry(&entry.file_name))
+    else {
+        return Ok(None);
+    };
+
+    let bytes = reader.read_entry(&entry)?;
+    Ok(parse_comic_info(&String::from_utf8_lossy(&bytes)))
+}
+
+/// Parses a ComicInfo.xml document. Unknown elements are ignored and malformed values are
+/// treated as absent, since these files are often hand-written.
+pub fn parse_comic_info(xml: &str) -> Option<ComicInfo> {
+    let document = Document::parse(xml.trim_start_matches('\u{feff}')).ok()?;
+    let root = document.root_element();
+    if !root.has_tag_name("ComicInfo") {
+        return None;
+    }
+
+    let text = |name: &str| child_text(root, name);
+    let number = |name: &str| text(name).and_then(|value| value.parse().ok());
+
+    Some(ComicInfo {
+        title: text("Title"),
+        series: text("Series"),
+        number: text("Number"),
+        count: number("Count").filter(|count| *count >= 0),
+        volume: number("Volume").filter(|volume| *volume >= 0),
+        summary: text("Summary"),
+        notes: text("Notes"),
+        year: text("Year").and_then(|value| value.parse().ok()),
+        month: text("Month").and_then(|value| value.parse().ok()),
+        day: text("Day").and_then(|value| value.parse().ok()),
+        writer: text("Writer"),
+        penciller: text("Penciller"),
+        inker: text("Inker"),
+        colorist: text("Colorist"),
+        letterer: text("Letterer"),
+        cover_artist: text("CoverArtist"),
+        editor: text("Editor"),
+        translator: text("Translator"),
+        publisher: text("Publisher"),
+        genre: text("Genre"),
+        tags: text("Tags"),
+        web: text("Web"),
+        page_count: text("PageCount").and_then(|value| value.parse().ok()),
+        language_iso: text("LanguageISO"),
+        format: text("Format"),
+        manga: text("Manga"),
+        age_rating: text("AgeRating"),
+        pages: root
+            .children()
+            .filter(|node| node.has_tag_name("Pages"))
+            .flat_map(|pages| pages.children().filter(|node| node.has_tag_name("Page")))
+            .filter_map(parse_page)
+            .collect(),
+    })
+}
+
+fn parse_page(node: Node) -> Option<ComicInfoPage> {
+    let attribute = |name: &str| {
+        node.attribute(name)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+
+    Some(ComicInfoPage {
+        image: attribute("Image")?.parse().ok()?,
+        page_type: attribute("Type").map(str::to_string),
+        double_page: attribute("DoublePage")
+            .map(|value| value.eq_ignore_ascii_case("true"))
+            .unwrap_or(false),
+        image_width: attribute("ImageWidth").and_then(|value| value.parse().ok()),
+        image_height: attribute("ImageHeight").and_then(|value| value.parse().ok()),
+        image_size: attribute("ImageSize").and_then(|value| value.parse().ok()),
+    })
+}
+
+/// Serializes `info` as a ComicInfo.xml document, in the element order of the ComicRack
+/// schema. Absent fields are left out.
+pub fn write_comic_info(info: &ComicInfo) -> String {
+    let number = |value: Option<i64>| value.map(|value| value.to_string());
+    let fields = [
+        ("Title", info.title.clone()),
+        ("Series", info.series.clone()),
+        ("Number", info.number.clone()),
+        ("Count", number(info.count)),
+        ("Volume", number(info.volume)),
+        ("Summary", info.summary.clone()),
+        ("Notes", info.notes.clone()),
+        ("Year", info.year.map(|value| value.to_string())),
+        ("Month", info.month.map(|value| value.to_string())),
+        ("Day", info.day.map(|value| value.to_string())),
+        ("Writer", info.writer.clone()),
+        ("Penciller", info.penciller.clone()),
+        ("Inker", info.inker.clone()),
+        ("Colorist", info.colorist.clone()),
+        ("Letterer", info.letterer.clone()),
+        ("CoverArtist", info.cover_artist.clone()),
+        ("Editor", info.editor.clone()),
+        ("Translator", info.translator.clone()),
+        ("Publisher", info.publisher.clone()),
+        ("Genre", info.genre.clone()),
+        ("Tags", info.tags.clone()),
+        ("Web", info.web.clone()),
+        ("PageCount", info.page_count.map(|value| value.to_string())),
+        ("LanguageISO", info.language_iso.clone()),
+        ("Format", info.format.clone()),
+        ("Manga", info.manga.clone()),
+        ("AgeRating", info.age_rating.clone()),
+    ];
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ComicInfo \
+         xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    for (name, value) in fields {
+        if let Some(value) = value.filter(|value| !value.trim().is_empty()) {
+            xml.push_str(&format!("  <{name}>{}</{name}>\n", escape_xml(&value)));
+        }
+    }
+
+    if !info.pages.is_empty() {
+        xml.push_str("  <Pages>\n");
+        for page in &info.pages {
+            xml.push_str(&format!("    <Page Image=\"{}\"", page.image));
+            if let Some(page_type) = &page.page_type {
+                xml.push_str(&format!(" Type=\"{}\"", escape_xml(page_type)));
+            }
+            if page.double_page {
+                xml.push_str(" DoublePage=\"true\"");
+            }
+            let dimensions = [
+                ("ImageSize", page.image_size),
+                ("ImageWidth", page.image_width.map(u64::from)),
+                ("ImageHeight", page.image_height.map(u64::from)),
+            ];
+            for (name, value) in dimensions {
+                if let Some(value) = value {
+                    xml.push_str(&format!(" {name}=\"{value}\""));
+                }
+            }
+            xml.push_str(" />\n");
+        }
+        xml.push_str("  </Pages>\n");
+    }
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0.
+            character if character.is_control() && !matches!(character, '\t' | '\n' | '\r') => {}
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_comic_info_fields_and_pages() {
+        let info = parse_comic_info(
+            r#"<?xml version="1.0"?>
+            <ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+              <Title>The Return</Title>
+              <Series>Pablo</Series>
+              <Number>15.5</Number>
+              <Volume>2</Volume>
+              <Summary>  A long way home.  </Summary>
+              <Writer>Ana</Writer>
+              <Penciller>Luis</Penciller>
+              <PageCount>abc</PageCount>
+              <LanguageISO>es</LanguageISO>
+              <Pages>
+                <Page Image="0" Type="FrontCover" ImageWidth="800" ImageHeight="1200" />
+                <Page Image="1" DoublePage="True" />
+              </Pages>
+            </ComicInfo>"#,
+        )
+        .expect("comic info");
+
+        assert_eq!(info.title.as_deref(), Some("The Return"));
+        assert_eq!(info.series.as_deref(), Some("Pablo"));
+        assert_eq!(info.number.as_deref(), Some("15.5"));
+        assert_eq!(info.volume, Some(2));
+        assert_eq!(info.summary.as_deref(), Some("A long way home."));
+        assert_eq!(info.page_count, None);
+        assert_eq!(info.language_iso.as_deref(), Some("es"));
+        assert_eq!(info.pages.len(), 2);
+        assert_eq!(info.pages[0].page_type.as_deref(), Some("FrontCover"));
+        assert_eq!(info.pages[0].image_width, Some(800));
+        assert!(info.pages[1].double_page);
+    }
+
+    #[test]
+    fn written_comic_info_parses_back() {
+        let info = ComicInfo {
+            title: Some("Tom & Jerry <1>".to_string()),
+            series: Some("Pablo".to_string()),
+            number: Some("3".to_string()),
+            volume: Some(1),
+            page_count: Some(2),
+            pages: vec![
+                ComicInfoPage {
+                    image: 0,
+                    page_type: Some("FrontCover".to_string()),
+                    image_width: Some(800),
+                    ..Default::default()
+                },
+                ComicInfoPage {
+                    image: 1,
+                    double_page: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(parse_comic_info(&write_comic_info(&info)), Some(info));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(parse_comic_info("<Book><Title>x</Title></Book>").is_none());
+        assert!(parse_comic_info("not xml").is_none());
+    }
+}
@@ -0,0 +1,168 @@
+use rusqlite::{params, Connection};
+
+use super::row_to_record;
+use crate::domain::{AppError, ComicProgress, DbRecord};
+
+/// Every chapter of a comic with whether it is read or in progress and when its progress
+/// last changed.
+const CHAPTER_STATE: &str = "
+WITH chapter_state AS (
+  SELECT
+    c.comic_id,
+    c.id,
+    p.total_pages > 0 AND p.page >= p.total_pages AS is_read,
+    p.page > 0 AND NOT (p.total_pages > 0 AND p.page >= p.total_pages) AS in_progress,
+    p.updated_at
+  FROM chapters c
+  LEFT JOIN (
+    SELECT
+      chapter_id,
+      CAST(COALESCE(json_extract(data, '$.page'), 0) AS INTEGER) AS page,
+      CAST(COALESCE(json_extract(data, '$.totalPages'), 0) AS INTEGER) AS total_pages,
+      updated_at
+    FROM read_progress
+  ) p ON p.chapter_id = c.id
+  WHERE c.comic_id IS NOT NULL
+)
+";
+
+/// Read, in-progress and unread chapter counts per comic, from the progress row of each
+/// chapter. Only the comic with `comic_id` is summarized when one is given; comics
+/// without chapters are included with zero counts.
+pub(super) fn comic_progress(
+    conn: &Connection,
+    comic_id: Option<&str>,
+) -> Result<Vec<ComicProgress>, AppError> {
+    let sql = format!(
+        "
+        {CHAPTER_STATE}
+        SELECT
+          co.id,
+          COUNT(cs.id),
+          COALESCE(SUM(cs.is_read), 0),
+          COALESCE(SUM(cs.in_progress), 0),
+          MAX(cs.updated_at)
+        FROM comics co
+        LEFT JOIN chapter_state cs ON cs.comic_id = co.id
+        WHERE ?1 IS NULL OR co.id = ?1
+        GROUP BY co.id
+        ORDER BY co.id;
+        "
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map(params![comic_id], |row| {
+            let total_chapters: i64 = row.get(1)?;
+            let read_chapters: i64 = row.get(2)?;
+            Ok(ComicProgress {
+                comic_id: row.get(0)?,
+                total_chapters,
+                read_chapters,
+                in_progress_chapters: row.get(3)?,
+                unread_chapters: total_chapters - read_chapters,
+                percent_complete: if total_chapters > 0 {
+                    read_chapters as f64 * 100.0 / total_chapters as f64
+                } else {
+                    0.0
+                },
+                last_read_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+/// Comics with progress on a chapter and an unread chapter left, most recently read first.
+pub(super) fn recently_read_comics(
+    conn: &Connection,
+    limit: u32,
+) -> Result<Vec<DbRecord>, AppError> {
+    let sql = format!(
+        "
+        {CHAPTER_STATE}
+        SELECT co.id, co.data, co.created_at, co.updated_at
+        FROM comics co
+        JOIN (
+          SELECT comic_id, MAX(updated_at) AS last_read_at
+          FROM chapter_state
+          GROUP BY comic_id
+          HAVING last_read_at IS NOT NULL AND MIN(COALESCE(is_read, 0)) = 0
+        ) recent ON recent.comic_id = co.id
+        ORDER BY recent.last_read_at DESC
+        LIMIT ?1;
+        "
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map(params![limit], row_to_record)
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::migrations::{MigrationRunner, SqliteMigrationRunner};
+
+    #[test]
+    fn counts_read_and_started_chapters() {
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        conn.execute_batch(
+            r#"
+            INSERT INTO comics (id, data) VALUES ('comic-1', json('{"name":"Pablo"}'));
+            INSERT INTO comics (id, data) VALUES ('comic-2', json('{"name":"Empty"}'));
+            INSERT INTO chapters (id, data) VALUES
+              ('c-1', json('{"comicId":"comic-1"}')),
+              ('c-2', json('{"comicId":"comic-1"}')),
+              ('c-3', json('{"comicId":"comic-1"}')),
+              ('c-4', json('{"comicId":"comic-1"}'));
+            INSERT INTO read_progress (id, data, updated_at) VALUES
+              ('p-1', json('{"chapterId":"c-1","comicId":"comic-1","page":20,"totalPages":20}'), '2026-03-01T10:00:00.000Z'),
+              ('p-2', json('{"chapterId":"c-2","comicId":"comic-1","page":4,"totalPages":18}'), '2026-03-02T10:00:00.000Z'),
+              ('p-3', json('{"chapterId":"c-3","comicId":"comic-1","page":0,"totalPages":18}'), '2026-02-02T10:00:00.000Z');
+            "#,
+        )
+        .expect("seed library");
+
+        let recent = recently_read_comics(&conn, 10).expect("recently read comics");
+        assert_eq!(
+            recent
+                .iter()
+                .map(|comic| comic.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["comic-1"]
+        );
+
+        let progress = comic_progress(&conn, None).expect("library progress");
+        assert_eq!(progress.len(), 2);
+        assert_eq!(
+            progress[0],
+            ComicProgress {
+                comic_id: "comic-1".to_string(),
+                total_chapters: 4,
+                read_chapters: 1,
+                in_progress_chapters: 1,
+                unread_chapters: 3,
+                percent_complete: 25.0,
+                last_read_at: Some("2026-03-02T10:00:00.000Z".to_string()),
+            }
+        );
+        assert_eq!(progress[1].total_chapters, 0);
+        assert_eq!(progress[1].last_read_at, None);
+
+        let single = comic_progress(&conn, Some("comic-2")).expect("comic progress");
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].comic_id, "comic-2");
+    }
+}
@@ -0,0 +1,31 @@
+use sha2::{Digest, Sha256};
+
+/// Hex digest of `parts`, stable across builds and toolchains so it can name files on disk
+/// and back HTTP validators. Parts are length-prefixed, so `["ab", "c"]` and `["a", "bc"]`
+/// hash differently.
+pub fn stable_hash(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_parts_stably() {
+        assert_eq!(
+            stable_hash(&["chapter.cbz", "1"]),
+            stable_hash(&["chapter.cbz", "1"])
+        );
+        assert_ne!(stable_hash(&["ab", "c"]), stable_hash(&["a", "bc"]));
+        assert_eq!(stable_hash(&[]).len(), 32);
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    domain::{AppError, ComicInfo},
+    infrastructure::{
+        archive::{list_image_entries, sorted_image_entries, ArchiveReader},
+        comic_info::read_comic_info,
+        image_variants::{render_variant, ImageFit, ImageVariantFormat, ImageVariantSpec},
+        library_files::sanitize_segment,
+    },
+};
+
+/// Extracted covers are scaled down to fit this box, which is enough for detail headers.
+const COVER_WIDTH: u32 = 800;
+const COVER_HEIGHT: u32 = 1200;
+const FRONT_COVER_PAGE_TYPE: &str = "FrontCover";
+
+/// Index into the naturally sorted image list of the page ComicInfo.xml marks as
+/// `FrontCover`, which is where its `Image` attributes point.
+pub fn front_cover_image(page_count: usize, info: Option<&ComicInfo>) -> Option<usize> {
+    info.into_iter()
+        .flat_map(|info| info.pages.iter())
+        .find(|page| {
+            page.page_type
+                .as_deref()
+                .is_some_and(|page_type| page_type.eq_ignore_ascii_case(FRONT_COVER_PAGE_TYPE))
+        })
+        .map(|page| page.image)
+        .filter(|index| *index < page_count)
+}
+
+/// Renders the cover page of an archive as a normalized JPEG: the `FrontCover` page, or
+/// else the first page in reading order. Archives without pages have no cover.
+pub fn extract_archive_cover(reader: &dyn ArchiveReader) -> Result<Option<Vec<u8>>, AppError> {
+    let mut images = sorted_image_entries(reader)?;
+    if images.is_empty() {
+        return Ok(None);
+    }
+
+    // A malformed ComicInfo.xml only costs the FrontCover hint.
+    let info = read_comic_info(reader).ok().flatten();
+    let entry = match front_cover_image(images.len(), info.as_ref()) {
+        Some(index) => images.swap_remove(index),
+        None => match list_image_entries(reader)?.into_iter().next() {
+            Some(entry) => entry,
+            None => images.swap_remove(0),
+        },
+    };
+    let bytes = reader.read_entry(&entry)?;
+    let spec = ImageVariantSpec {
+        width: Some(COVER_WIDTH),
+        height: Some(COVER_HEIGHT),
+        fit: ImageFit::Contain,
+        format: ImageVariantFormat::Jpeg,
+    };
+    render_variant(&bytes, &spec).map(|rendered| Some(rendered.bytes))
+}
+
+/// Where the extracted cover of `comic_id` is saved.
+pub fn extracted_cover_path(covers_dir: &Path, comic_id: &str) -> PathBuf {
+    covers_dir.join(format!("{}.jpg", sanitize_segment(comic_id)))
+}
+
+/// Writes an extracted cover for `comic_id` into `covers_dir`, replacing an older one.
+pub fn save_extracted_cover(
+    covers_dir: &Path,
+    comic_id: &str,
+    bytes: &[u8],
+) -> Result<PathBuf, AppError> {
+    fs::create_dir_all(covers_dir)
+        .map_err(|e| AppError::infrastructure(format!("Failed to create covers dir: {e}")))?;
+
+    let path = extracted_cover_path(covers_dir, comic_id);
+    let temp_path = path.with_extension("jpg.tmp");
+    fs::write(&temp_path, bytes)
+        .and_then(|_| fs::rename(&temp_path, &path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            AppError::infrastructure(format!("Failed to save cover: {e}"))
+        })?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{domain::ComicInfoPage, infrastructure::archive::DirectoryReader};
+    use image::{GenericImageView, RgbImage};
+
+    fn page(image: usize, page_type: Option<&str>) -> ComicInfoPage {
+        ComicInfoPage {
+            image,
+            page_type: page_type.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn prefers_the_front_cover_page() {
+        let info = ComicInfo {
+            pages: vec![page(0, Some("Story")), page(2, Some("FrontCover"))],
+            ..Default::default()
+        };
+        assert_eq!(front_cover_image(5, Some(&info)), Some(2));
+        assert_eq!(front_cover_image(2, Some(&info)), None);
+        assert_eq!(front_cover_image(5, None), None);
+    }
+
+    #[test]
+    fn finds_the_front_cover_of_a_reordered_archive() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        for (name, size) in [("a.png", 10), ("b.png", 20), ("c.png", 30)] {
+            RgbImage::new(size, size)
+                .save(dir.path().join(name))
+                .expect("write page");
+        }
+        // Reading order is c, a, b; `Image` still counts in file name order.
+        fs::write(
+            dir.path().join("ComicInfo.xml"),
+            r#"<ComicInfo><Pages><Page Image="2"/><Page Image="0" Type="FrontCover"/><Page Image="1"/></Pages></ComicInfo>"#,
+        )
+        .expect("write ComicInfo.xml");
+
+        let reader = DirectoryReader::new(dir.path());
+        let cover = extract_archive_cover(&reader)
+            .expect("extract")
+            .expect("cover");
+        let cover = image::load_from_memory(&cover).expect("decode cover");
+        assert_eq!(cover.dimensions(), (10, 10));
+
+        fs::write(
+            dir.path().join("ComicInfo.xml"),
+            r#"<ComicInfo><Pages><Page Image="2"/><Page Image="0"/><Page Image="1"/></Pages></ComicInfo>"#,
+        )
+        .expect("write ComicInfo.xml");
+        let cover = extract_archive_cover(&reader)
+            .expect("extract")
+            .expect("cover");
+        let cover = image::load_from_memory(&cover).expect("decode cover");
+        assert_eq!(cover.dimensions(), (30, 30));
+    }
+}
@@ -0,0 +1,297 @@
+use std::{collections::HashMap, fs, io::Write, path::PathBuf, time::Duration};
+
+use serde_json::Value;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    domain::{AppError, ComicInfo, ComicInfoPage},
+    infrastructure::{
+        archive::{list_image_entries, open_archive, sorted_image_entries},
+        comic_info::write_comic_info,
+        library_files::sanitize_segment,
+    },
+};
+
+/// Remote pages are fetched one by one while the export streams; a stalled host fails it.
+const REMOTE_PAGE_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_REMOTE_PAGE_BYTES: u64 = 64 * 1024 * 1024;
+const COMIC_INFO_FILE_NAME: &str = "ComicInfo.xml";
+/// Personal rating, favorite flag and review saved next to the chapters of a comic export.
+pub const PERSONAL_RATING_FILE_NAME: &str = "personal.json";
+
+/// Where the pages of an exported chapter are read from.
+pub enum ExportPages {
+    /// A local archive or image folder, in page order.
+    Archive(PathBuf),
+    /// Individual page files or URLs, in reading order.
+    Sources(Vec<PageSource>),
+}
+
+pub enum PageSource {
+    File(PathBuf),
+    Url(String),
+}
+
+pub struct ExportChapter {
+    /// Chapter name, used for the chapter's CBZ inside a comic export.
+    pub name: String,
+    pub info: ComicInfo,
+    pub pages: ExportPages,
+}
+
+/// Writes `chapter` as a CBZ: its pages renamed `0001.jpg`, `0002.png`, ... followed by a
+/// generated ComicInfo.xml whose page count matches what was written. Page entries of an
+/// archive's ComicInfo.xml are renumbered to the written order, since pages are written in
+/// reading order and deleted ones are left out. The writer need not seek, so the archive
+/// can go straight into a response body.
+pub fn write_chapter_cbz<W: Write>(writer: W, chapter: &ExportChapter) -> Result<W, AppError> {
+    let mut zip = ZipWriter::new_stream(writer);
+    // Images are already compressed; deflating them again only costs time.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut page_count = 0u32;
+    let mut write_page = |bytes: Vec<u8>, source_name: &str| -> Result<(), AppError> {
+        page_count += 1;
+        let name = format!("{page_count:04}.{}", page_extension(source_name, &bytes));
+        zip.start_file(name, stored).map_err(zip_error)?;
+        zip.write_all(&bytes).map_err(io_error)
+    };
+
+    let mut described_pages = Vec::new();
+    match &chapter.pages {
+        ExportPages::Archive(path) => {
+            let archive = open_archive(path)?;
+            let entries = list_image_entries(archive.as_ref())?;
+            // `Image` attributes count the naturally sorted images of the source archive.
+            let images = sorted_image_entries(archive.as_ref())?
+                .into_iter()
+                .enumerate()
+                .map(|(image, entry)| (entry.archive_index, image))
+                .collect::<HashMap<_, _>>();
+            for (index, entry) in entries.iter().enumerate() {
+                let described = images.get(&entry.archive_index).and_then(|image| {
+                    chapter
+                        .info
+                        .pages
+                        .iter()
+                        .find(|page| page.image == *image && !is_deleted_page(page))
+                });
+                if let Some(page) = described {
+                    described_pages.push(ComicInfoPage {
+                        image: index,
+                        ..page.clone()
+                    });
+                }
+                write_page(archive.read_entry(entry)?, &entry.file_name)?;
+            }
+        }
+        ExportPages::Sources(sources) => {
+            let agent = remote_agent();
+            for source in sources {
+                let (bytes, source_name) = match source {
+                    PageSource::File(path) => (
+                        fs::read(path).map_err(io_error)?,
+                        path.to_string_lossy().into_owned(),
+                    ),
+                    PageSource::Url(url) => (fetch_remote_page(&agent, url)?, url.clone()),
+                };
+                write_page(bytes, &source_name)?;
+            }
+        }
+    }
+
+    let mut info = chapter.info.clone();
+    info.page_count = Some(page_count);
+    info.pages = described_pages;
+    zip.start_file(COMIC_INFO_FILE_NAME, SimpleFileOptions::default())
+        .map_err(zip_error)?;
+    zip.write_all(write_comic_info(&info).as_bytes())
+        .map_err(io_error)?;
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+/// Writes a comic export: one CBZ per chapter inside a folder named after the comic, the
+/// layout a library scan imports back, plus the comic's personal rating when it has one.
+/// Each chapter is assembled in memory before it is added, so only one chapter is held at a
+/// time.
+pub fn write_comic_zip<W: Write>(
+    writer: W,
+    comic_name: &str,
+    personal_rating: Option<&Value>,
+    chapters: impl IntoIterator<Item = ExportChapter>,
+) -> Result<W, AppError> {
+    let mut zip = ZipWriter::new_stream(writer);
+    let folder = sanitize_segment(comic_name);
+    if let Some(personal_rating) = personal_rating {
+        zip.start_file(
+            format!("{folder}/{PERSONAL_RATING_FILE_NAME}"),
+            SimpleFileOptions::default(),
+        )
+        .map_err(zip_error)?;
+        zip.write_all(personal_rating.to_string().as_bytes())
+            .map_err(io_error)?;
+    }
+    let stored = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    let mut used_names = Vec::new();
+    for chapter in chapters {
+        let cbz = write_chapter_cbz(Vec::new(), &chapter)?;
+        let name = unique_name(&mut used_names, sanitize_segment(&chapter.name));
+        zip.start_file(format!("{folder}/{name}.cbz"), stored)
+            .map_err(zip_error)?;
+        zip.write_all(&cbz).map_err(io_error)?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+fn is_deleted_page(page: &ComicInfoPage) -> bool {
+    page.page_type
+        .as_deref()
+        .is_some_and(|page_type| page_type.eq_ignore_ascii_case("Deleted"))
+}
+
+/// Chapters sharing a name get ` (2)`, ` (3)`, ... so no CBZ overwrites another.
+fn unique_name(used: &mut Vec<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut suffix = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{name} ({suffix})");
+        suffix += 1;
+    }
+    used.push(candidate.clone());
+    candidate
+}
+
+/// Extension for an exported page: the source's own when it is an image extension,
+/// otherwise the one its bytes identify.
+fn page_extension(source_name: &str, bytes: &[u8]) -> String {
+    let path = source_name.split(['?', '#']).next().unwrap_or(source_name);
+    path.rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .filter(|extension| {
+            matches!(
+                extension.as_str(),
+                "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" | "avif"
+            )
+        })
+        .or_else(|| {
+            image::guess_format(bytes)
+                .ok()
+                .and_then(|format| format.extensions_str().first())
+                .map(|extension| extension.to_string())
+        })
+        .unwrap_or_else(|| "jpg".to_string())
+}
+
+fn remote_agent() -> ureq::Agent {
+    ureq::Agent::config_builder()
+        .timeout_global(Some(REMOTE_PAGE_TIMEOUT))
+        .build()
+        .into()
+}
+
+fn fetch_remote_page(agent: &ureq::Agent, url: &str) -> Result<Vec<u8>, AppError> {
+    agent
+        .get(url)
+        .call()
+        .and_then(|mut response| {
+            response
+                .body_mut()
+                .with_config()
+                .limit(MAX_REMOTE_PAGE_BYTES)
+                .read_to_vec()
+        })
+        .map_err(|error| AppError::infrastructure(format!("Failed to fetch page {url}: {error}")))
+}
+
+fn zip_error(error: zip::result::ZipError) -> AppError {
+    AppError::infrastructure(format!("Failed to write export: {error}"))
+}
+
+fn io_error(error: std::io::Error) -> AppError {
+    AppError::infrastructure(format!("Failed to write export: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::{
+        archive::{ArchiveReader, CbzReader},
+        comic_info::read_comic_info,
+    };
+
+    #[test]
+    fn exports_renumbered_pages_with_comic_info() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("b.png");
+        let second = dir.path().join("page");
+        fs::write(&first, b"png bytes").unwrap();
+        fs::write(&second, b"\xFF\xD8\xFF\xE0jpeg bytes").unwrap();
+
+        let chapter = ExportChapter {
+            name: "Chapter 1".to_string(),
+            info: ComicInfo {
+                title: Some("Chapter 1".to_string()),
+                ..Default::default()
+            },
+            pages: ExportPages::Sources(vec![PageSource::File(first), PageSource::File(second)]),
+        };
+        let cbz_path = dir.path().join("export.cbz");
+        write_chapter_cbz(fs::File::create(&cbz_path).unwrap(), &chapter).unwrap();
+
+        let reader = CbzReader::new(&cbz_path);
+        let pages = list_image_entries(&reader).unwrap();
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.file_name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["0001.png", "0002.jpg"]
+        );
+        let info = read_comic_info(&reader).unwrap().unwrap();
+        assert_eq!(info.title.as_deref(), Some("Chapter 1"));
+        assert_eq!(info.page_count, Some(2));
+    }
+
+    #[test]
+    fn reimports_a_reordered_archive_in_the_same_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        fs::create_dir_all(&source).unwrap();
+        for name in ["a.png", "b.png", "c.png"] {
+            fs::write(source.join(name), name).unwrap();
+        }
+        fs::write(
+            source.join(COMIC_INFO_FILE_NAME),
+            r#"<ComicInfo><Pages><Page Image="2"/><Page Image="0" Type="FrontCover"/><Page Image="1" Type="Deleted"/></Pages></ComicInfo>"#,
+        )
+        .unwrap();
+
+        let reader = open_archive(&source).unwrap();
+        let chapter = ExportChapter {
+            name: "Chapter 1".to_string(),
+            info: read_comic_info(reader.as_ref()).unwrap().unwrap(),
+            pages: ExportPages::Archive(source.clone()),
+        };
+        let cbz_path = dir.path().join("export.cbz");
+        write_chapter_cbz(fs::File::create(&cbz_path).unwrap(), &chapter).unwrap();
+
+        let exported = CbzReader::new(&cbz_path);
+        let pages = list_image_entries(&exported)
+            .unwrap()
+            .iter()
+            .map(|page| exported.read_entry(page).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pages, vec![b"c.png".to_vec(), b"a.png".to_vec()]);
+
+        let info = read_comic_info(&exported).unwrap().unwrap();
+        assert_eq!(info.page_count, Some(2));
+        assert_eq!(
+            info.pages
+                .iter()
+                .map(|page| (page.image, page.page_type.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![(0, None), (1, Some("FrontCover"))]
+        );
+    }
+}
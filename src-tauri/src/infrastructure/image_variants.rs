@@ -0,0 +1,430 @@
+use std::{
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use image::{
+    codecs::{jpeg::JpegEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, GenericImageView,
+};
+
+use super::content_hash::stable_hash;
+use crate::domain::AppError;
+
+/// Requested dimensions above this are clamped; scans are never upscaled anyway.
+pub const MAX_VARIANT_DIMENSION: u32 = 4096;
+const JPEG_QUALITY: u8 = 82;
+/// Eviction trims the cache below the limit so the next few writes don't evict again.
+const EVICTION_TARGET_PERCENT: u64 = 90;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageFit {
+    /// Scale to fit inside the box, keeping the aspect ratio.
+    Contain,
+    /// Scale to cover the box, keeping the aspect ratio, then crop the overflow.
+    Cover,
+    /// Stretch to exactly the requested box.
+    Fill,
+}
+
+impl ImageFit {
+    pub fn parse(raw: &str) -> Result<Self, AppError> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "contain" | "inside" => Ok(Self::Contain),
+            "cover" | "crop" => Ok(Self::Cover),
+            "fill" | "stretch" => Ok(Self::Fill),
+            other => Err(AppError::Validation(format!(
+                "Unsupported fit '{other}', expected contain, cover or fill"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ImageVariantFormat {
+    Jpeg,
+    Webp,
+}
+
+impl ImageVariantFormat {
+    pub fn parse(raw: &str) -> Result<Self, AppError> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "" | "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            other => Err(AppError::Validation(format!(
+                "Unsupported format '{other}', expected webp or jpeg"
+            ))),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Webp => "image/webp",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+/// A resized and/or re-encoded rendition of a source image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ImageVariantSpec {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: ImageFit,
+    pub format: ImageVariantFormat,
+}
+
+impl ImageVariantSpec {
+    /// Builds a spec from raw query options. Returns `None` when no option was given,
+    /// meaning the original bytes should be served untouched.
+    pub fn from_options(
+        width: Option<u32>,
+        height: Option<u32>,
+        fit: Option<&str>,
+        format: Option<&str>,
+    ) -> Result<Option<Self>, AppError> {
+        if width.is_none() && height.is_none() && fit.is_none() && format.is_none() {
+            return Ok(None);
+        }
+        if width == Some(0) || height == Some(0) {
+            return Err(AppError::Validation(
+                "w and h must be greater than zero".to_string(),
+            ));
+        }
+
+        Ok(Some(Self {
+            width: width.map(|value| value.min(MAX_VARIANT_DIMENSION)),
+            height: height.map(|value| value.min(MAX_VARIANT_DIMENSION)),
+            fit: fit
+                .map(ImageFit::parse)
+                .transpose()?
+                .unwrap_or(ImageFit::Contain),
+            format: format
+                .map(ImageVariantFormat::parse)
+                .transpose()?
+                .unwrap_or(ImageVariantFormat::Jpeg),
+        }))
+    }
+}
+
+/// Encoded variant bytes and the format they ended up in, which can differ from the
+/// requested one.
+pub struct RenderedVariant {
+    pub bytes: Vec<u8>,
+    pub format: ImageVariantFormat,
+}
+
+/// Decodes `source`, resizes it according to `spec` and encodes the result. The WebP encoder
+/// is lossless only, so opaque images fall back to JPEG whenever that comes out smaller.
+pub fn render_variant(source: &[u8], spec: &ImageVariantSpec) -> Result<RenderedVariant, AppError> {
+    let image = image::load_from_memory(source)
+        .map_err(|e| AppError::Validation(format!("Failed to decode image: {e}")))?;
+    let resized = resize(image, spec);
+
+    let jpeg = || -> Result<RenderedVariant, AppError> {
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(resized.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut output, JPEG_QUALITY))
+            .map_err(encode_error)?;
+        Ok(RenderedVariant {
+            bytes: output.into_inner(),
+            format: ImageVariantFormat::Jpeg,
+        })
+    };
+    match spec.format {
+        ImageVariantFormat::Jpeg => jpeg(),
+        ImageVariantFormat::Webp => {
+            let mut output = Cursor::new(Vec::new());
+            DynamicImage::ImageRgba8(resized.to_rgba8())
+                .write_with_encoder(WebPEncoder::new_lossless(&mut output))
+                .map_err(encode_error)?;
+            let webp = RenderedVariant {
+                bytes: output.into_inner(),
+                format: ImageVariantFormat::Webp,
+            };
+            if resized.color().has_alpha() {
+                return Ok(webp);
+            }
+            let jpeg = jpeg()?;
+            Ok(if jpeg.bytes.len() < webp.bytes.len() {
+                jpeg
+            } else {
+                webp
+            })
+        }
+    }
+}
+
+fn encode_error(error: image::ImageError) -> AppError {
+    AppError::infrastructure(format!("Failed to encode image: {error}"))
+}
+
+fn resize(image: DynamicImage, spec: &ImageVariantSpec) -> DynamicImage {
+    let (source_width, source_height) = image.dimensions();
+    let filter = FilterType::CatmullRom;
+
+    match (spec.width, spec.height, spec.fit) {
+        (None, None, _) => image,
+        (Some(width), Some(height), ImageFit::Cover) => {
+            // Shrink the box uniformly until it fits the source so covers never upscale.
+            let scale = 1f64
+                .min(source_width as f64 / width as f64)
+                .min(source_height as f64 / height as f64);
+            let width = ((width as f64 * scale).round() as u32).max(1);
+            let height = ((height as f64 * scale).round() as u32).max(1);
+            if (width, height) == (source_width, source_height) {
+                image
+            } else {
+                image.resize_to_fill(width, height, filter)
+            }
+        }
+        (Some(width), Some(height), ImageFit::Fill) => {
+            let width = width.min(source_width);
+            let height = height.min(source_height);
+            if (width, height) == (source_width, source_height) {
+                image
+            } else {
+                image.resize_exact(width, height, filter)
+            }
+        }
+        (width, height, _) => {
+            let width = width.unwrap_or(u32::MAX);
+            let height = height.unwrap_or(u32::MAX);
+            if source_width <= width && source_height <= height {
+                image
+            } else {
+                image.resize(width, height, filter)
+            }
+        }
+    }
+}
+
+/// Disk cache of rendered variants. Entries are keyed by the source validator and the spec;
+/// hits refresh the file modification time, and the least recently used files are evicted
+/// once the directory grows past `max_bytes`.
+#[derive(Clone)]
+pub struct ImageVariantCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl ImageVariantCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Cached rendition of `spec` and the format it was stored in.
+    pub fn lookup(
+        &self,
+        source_key: &str,
+        spec: &ImageVariantSpec,
+    ) -> Option<(PathBuf, ImageVariantFormat)> {
+        [spec.format, ImageVariantFormat::Jpeg]
+            .into_iter()
+            .find_map(|format| {
+                let path = self.entry_path(source_key, spec, format);
+                let file = fs::File::options().write(true).open(&path).ok()?;
+                let _ = file.set_modified(SystemTime::now());
+                Some((path, format))
+            })
+    }
+
+    pub fn store(
+        &self,
+        source_key: &str,
+        spec: &ImageVariantSpec,
+        rendered: &RenderedVariant,
+    ) -> Result<PathBuf, AppError> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .map_err(|_| AppError::infrastructure("Image cache lock poisoned"))?;
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| AppError::infrastructure(format!("Failed to create image cache: {e}")))?;
+        let path = self.entry_path(source_key, spec, rendered.format);
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, &rendered.bytes)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp_path);
+                AppError::infrastructure(format!("Failed to write image cache entry: {e}"))
+            })?;
+
+        evict_least_recently_used(&self.dir, self.max_bytes, &path);
+        Ok(path)
+    }
+
+    fn entry_path(
+        &self,
+        source_key: &str,
+        spec: &ImageVariantSpec,
+        format: ImageVariantFormat,
+    ) -> PathBuf {
+        let key = stable_hash(&[source_key, &format!("{spec:?}")]);
+        self.dir.join(format!("{key}.{}", format.extension()))
+    }
+}
+
+fn evict_least_recently_used(dir: &Path, max_bytes: u64, keep: &Path) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut entries = Vec::new();
+    let mut total = 0u64;
+    for entry in read_dir.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        total += metadata.len();
+        let accessed = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        entries.push((accessed, metadata.len(), entry.path()));
+    }
+
+    if total <= max_bytes {
+        return;
+    }
+
+    let target = max_bytes.saturating_mul(EVICTION_TARGET_PERCENT) / 100;
+    entries.sort_by_key(|(accessed, _, _)| *accessed);
+    for (_, len, path) in entries {
+        if total <= target {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        if fs::remove_file(&path).is_ok() {
+            total = total.saturating_sub(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    fn sample_png(width: u32, height: u32) -> Vec<u8> {
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut output, ImageFormat::Png)
+            .expect("encode sample");
+        output.into_inner()
+    }
+
+    fn spec(width: Option<u32>, height: Option<u32>, fit: ImageFit) -> ImageVariantSpec {
+        ImageVariantSpec {
+            width,
+            height,
+            fit,
+            format: ImageVariantFormat::Jpeg,
+        }
+    }
+
+    #[test]
+    fn parses_options_and_skips_when_absent() {
+        assert_eq!(
+            ImageVariantSpec::from_options(None, None, None, None).unwrap(),
+            None
+        );
+        let parsed = ImageVariantSpec::from_options(Some(9000), None, Some("cover"), Some("webp"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.width, Some(MAX_VARIANT_DIMENSION));
+        assert_eq!(parsed.fit, ImageFit::Cover);
+        assert_eq!(parsed.format, ImageVariantFormat::Webp);
+        assert!(ImageVariantSpec::from_options(Some(0), None, None, None).is_err());
+        assert!(ImageVariantSpec::from_options(None, None, Some("zoom"), None).is_err());
+    }
+
+    #[test]
+    fn resizes_without_upscaling() {
+        let source = sample_png(400, 600);
+
+        let contain = render_variant(&source, &spec(Some(200), None, ImageFit::Contain)).unwrap();
+        let contain = image::load_from_memory(&contain.bytes).unwrap();
+        assert_eq!(contain.dimensions(), (200, 300));
+
+        let cover = render_variant(&source, &spec(Some(100), Some(100), ImageFit::Cover)).unwrap();
+        assert_eq!(
+            image::load_from_memory(&cover.bytes).unwrap().dimensions(),
+            (100, 100)
+        );
+
+        let larger = render_variant(&source, &spec(Some(2000), None, ImageFit::Contain)).unwrap();
+        assert_eq!(
+            image::load_from_memory(&larger.bytes).unwrap().dimensions(),
+            (400, 600)
+        );
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ImageVariantCache::new(dir.path().to_path_buf(), 250);
+        let variant = spec(Some(10), None, ImageFit::Contain);
+        let rendered = RenderedVariant {
+            bytes: vec![0u8; 100],
+            format: ImageVariantFormat::Jpeg,
+        };
+
+        let first = cache.store("first", &variant, &rendered).unwrap();
+        let old = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        fs::File::options()
+            .write(true)
+            .open(&first)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        cache.store("second", &variant, &rendered).unwrap();
+        cache.store("third", &variant, &rendered).unwrap();
+
+        assert!(cache.lookup("first", &variant).is_none());
+        assert!(cache.lookup("second", &variant).is_some());
+        assert!(cache.lookup("third", &variant).is_some());
+    }
+
+    #[test]
+    fn falls_back_to_jpeg_when_lossless_webp_is_larger() {
+        let mut photo = RgbImage::new(300, 300);
+        for (x, y, pixel) in photo.enumerate_pixels_mut() {
+            *pixel = image::Rgb([((x * 7) ^ (y * 13)) as u8, (x * y) as u8, (x + y * 3) as u8]);
+        }
+        let mut source = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(photo)
+            .write_to(&mut source, ImageFormat::Png)
+            .unwrap();
+
+        let webp = ImageVariantSpec {
+            format: ImageVariantFormat::Webp,
+            ..spec(Some(200), None, ImageFit::Contain)
+        };
+        let rendered = render_variant(source.get_ref(), &webp).unwrap();
+        assert_eq!(rendered.format, ImageVariantFormat::Jpeg);
+        assert_eq!(
+            image::guess_format(&rendered.bytes).unwrap(),
+            ImageFormat::Jpeg
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use std::{
+    collections::HashSet,
+    fs,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::infrastructure::archive::{find_chapter_archive, is_chapter_archive_path, natural_cmp};
+
+/// Chapter field holding the archive location relative to the comics directory.
+pub const LOCAL_PATH_FIELD: &str = "localPath";
+/// Naming schemes tried when looking files up, current scheme first.
+pub const SEGMENT_SANITIZERS: [fn(&str) -> String; 2] = [sanitize_segment, legacy_sanitize_segment];
+const ILLEGAL_FILE_NAME_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+const WINDOWS_RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+const MAX_SEGMENT_BYTES: usize = 180;
+const VOLUME_MARKERS: [&str; 4] = ["volume", "tome", "vol", "v"];
+const CHAPTER_MARKERS: [&str; 7] = ["chapter", "chap", "ch", "episode", "ep", "c", "#"];
+
+/// Chapter archives found on disk, grouped by the comic folder that contains them.
+#[derive(Default)]
+pub struct LibraryListing {
+    pub folders: Vec<LibraryFolder>,
+    /// Archives lying directly in the comics directory, outside any comic folder.
+    pub loose_archives: Vec<PathBuf>,
+}
+
+pub struct LibraryFolder {
+    pub path: PathBuf,
+    pub name: String,
+    pub chapters: Vec<LibraryChapterFile>,
+}
+
+pub struct LibraryChapterFile {
+    pub path: PathBuf,
+    /// File name without extension (or the folder name for image folders).
+    pub stem: String,
+    pub parsed: ParsedChapterName,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ParsedChapterName {
+    pub volume: Option<String>,
+    pub number: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Walks one level of comic folders below `comics_dir`. Hidden entries and `excluded`
+/// directories (e.g. the covers folder) are skipped.
+pub fn list_library(comics_dir: &Path, excluded: &[PathBuf]) -> LibraryListing {
+    let mut listing = LibraryListing::default();
+    for path in sorted_children(comics_dir) {
+        if excluded.iter().any(|excluded| excluded == &path) {
+            continue;
+        }
+
+        // Comic folders may hold a cover image next to their chapters, so a folder only
+        // counts as a loose image chapter when it has no chapter archives of its own.
+        let chapters = if path.is_dir() {
+            list_folder_chapters(&path)
+        } else {
+            Vec::new()
+        };
+        if !chapters.is_empty() {
+            listing.folders.push(LibraryFolder {
+                name: file_name(&path).unwrap_or_default(),
+                path,
+                chapters,
+            });
+        } else if is_chapter_archive_path(&path) {
+            listing.loose_archives.push(path);
+        }
+    }
+    listing
+}
+
+fn list_folder_chapters(folder: &Path) -> Vec<LibraryChapterFile> {
+    sorted_children(folder)
+        .into_iter()
+        .filter(|child| is_chapter_archive_path(child))
+        .filter_map(|child| {
+            let stem = chapter_stem(&child)?;
+            Some(LibraryChapterFile {
+                parsed: parse_chapter_file_name(&stem),
+                path: child,
+                stem,
+            })
+        })
+        .collect()
+}
+
+fn sorted_children(dir: &Path) -> Vec<PathBuf> {
+    let mut children = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    file_name(path)
+                        .map(|name| !name.starts_with('.'))
+                        .unwrap_or(false)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    children.sort_by(|left, right| {
+        natural_cmp(
+            &file_name(left).unwrap_or_default(),
+            &file_name(right).unwrap_or_default(),
+        )
+    });
+    children
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .and_then(|value| value.to_str())
+        .map(str::to_string)
+}
+
+fn chapter_stem(path: &Path) -> Option<String> {
+    if path.is_dir() {
+        return file_name(path);
+    }
+    path.file_stem()
+        .and_then(|value| value.to_str())
+        .map(str::to_string)
+}
+
+/// Makes `value` usable as a single file or folder name on Windows, macOS and Linux. Letters
+/// from every script are kept; only characters those filesystems reject become `_`. Text is
+/// normalized to NFC so a title always maps to the same bytes.
+pub fn sanitize_segment(value: &str) -> String {
+    let replaced = value
+        .nfc()
+        .map(|ch| {
+            if ch.is_control() || ILLEGAL_FILE_NAME_CHARS.contains(&ch) {
+                '_'
+            } else {
+                ch
+            }
+        })
+        .collect::<String>();
+
+    // Windows drops trailing dots and spaces, so names ending in them would not round-trip.
+    let trim = |value: &str| {
+        value
+            .trim()
+            .trim_start_matches('.')
+            .trim_end_matches(['.', ' '])
+            .to_string()
+    };
+    let mut cleaned = trim(&replaced);
+    if cleaned.len() > MAX_SEGMENT_BYTES {
+        let mut end = MAX_SEGMENT_BYTES;
+        while !cleaned.is_char_boundary(end) {
+            end -= 1;
+        }
+        cleaned = trim(&cleaned[..end]);
+    }
+
+    if cleaned.is_empty() {
+        return "untitled".to_string();
+    }
+    // Windows reserves device names with any extension, e.g. `CON.cbz`.
+    let stem_len = cleaned.find('.').unwrap_or(cleaned.len());
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| cleaned[..stem_len].eq_ignore_ascii_case(reserved))
+    {
+        cleaned.insert(stem_len, '_');
+    }
+    cleaned
+}
+
+/// The ASCII-only scheme folders and archives were named with before
+/// [`sanitize_segment`] kept Unicode. Lookups still try it so those files keep working.
+pub fn legacy_sanitize_segment(value: &str) -> String {
+    let mut out = String::new();
+    for ch in value.chars() {
+        let accepted = ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | ' ');
+        if accepted {
+            out.push(ch);
+        } else {
+            out.push('_');
+        }
+    }
+
+    let cleaned = out.trim().trim_start_matches('.').to_string();
+    if cleaned.is_empty() {
+        "untitled".to_string()
+    } else if cleaned.len() > MAX_SEGMENT_BYTES {
+        cleaned[..MAX_SEGMENT_BYTES].to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Unsanitized names a chapter archive may have been saved under, most specific first.
+pub fn chapter_file_patterns(chapter_name: &str, chapter_number: Option<&str>) -> Vec<String> {
+    let mut patterns = vec![chapter_name.to_string()];
+    if let Some(number) = chapter_number {
+        patterns.push(number.to_string());
+        patterns.push(format!("{number} - {chapter_name}"));
+        patterns.push(format!("Chapter {number}"));
+    }
+    patterns
+}
+
+/// File stems a chapter archive may have been saved under, most specific first. Stems of
+/// the current scheme come before their legacy ASCII forms.
+pub fn chapter_file_candidates(chapter_name: &str, chapter_number: Option<&str>) -> Vec<String> {
+    let patterns = chapter_file_patterns(chapter_name, chapter_number);
+    let mut seen = HashSet::new();
+    SEGMENT_SANITIZERS
+        .iter()
+        .flat_map(|sanitize| patterns.iter().map(move |pattern| sanitize(pattern)))
+        .filter(|candidate| seen.insert(candidate.clone()))
+        .collect()
+}
+
+/// Whether `dir_name` is one of the folders a comic's files may be saved in: its sanitized
+/// name, its sanitized id, or a `{name} (n)` duplicate, under either naming scheme.
+pub fn comic_dir_matches(comic_id: &str, comic_name: &str, dir_name: &str) -> bool {
+    // Folder names read back from macOS may come decomposed.
+    let dir_name = dir_name.nfc().collect::<String>();
+    SEGMENT_SANITIZERS.iter().any(|sanitize| {
+        let comic_base = sanitize(comic_name);
+        dir_name == comic_base
+            || dir_name == sanitize(comic_id)
+            || dir_name.starts_with(&format!("{comic_base} ("))
+    })
+}
+
+/// Folders below `comics_dir` that may hold the comic's files, most likely first.
+pub fn comic_dir_candidates(comics_dir: &Path, comic_id: &str, comic_name: &str) -> Vec<PathBuf> {
+    let mut dir_candidates = SEGMENT_SANITIZERS
+        .iter()
+        .flat_map(|sanitize| {
+            [
+                comics_dir.join(sanitize(comic_name)),
+                comics_dir.join(sanitize(comic_id)),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    if let Ok(entries) = fs::read_dir(comics_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if let Some(name) = path.file_name().and_then(|v| v.to_str()) {
+                if comic_dir_matches(comic_id, comic_name, name) {
+                    dir_candidates.push(path);
+                }
+            }
+        }
+    }
+
+    let mut unique = HashSet::new();
+    dir_candidates
+        .into_iter()
+        .filter(|path| unique.insert(path.clone()))
+        .collect()
+}
+
+/// Looks for a chapter archive by the names it may have been saved under. Only used when
+/// the chapter has no usable `localPath`.
+pub fn guess_chapter_archive(
+    dir_candidates: &[PathBuf],
+    chapter_name: &str,
+    chapter_number: Option<&str>,
+) -> Option<PathBuf> {
+    let file_candidates = chapter_file_candidates(chapter_name, chapter_number);
+    dir_candidates.iter().find_map(|dir| {
+        file_candidates
+            .iter()
+            .find_map(|candidate| find_chapter_archive(dir, candidate))
+    })
+}
+
+/// Where an archive added through the app is saved: the comic's first existing folder, or a
+/// new one named after the comic, under the chapter's current-scheme name.
+pub fn canonical_chapter_archive_path(
+    comics_dir: &Path,
+    comic_id: &str,
+    comic_name: &str,
+    chapter_name: &str,
+) -> PathBuf {
+    let folder = comic_dir_candidates(comics_dir, comic_id, comic_name)
+        .into_iter()
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| comics_dir.join(sanitize_segment(comic_name)));
+    folder.join(format!("{}.cbz", sanitize_segment(chapter_name)))
+}
+
+/// The `localPath` to store for a file below `comics_dir`: relative and `/`-separated.
+pub fn to_local_path(comics_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(comics_dir).ok()?;
+    let segments = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(segment) => segment.to_str(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+/// Resolves a stored `localPath`. Paths that would leave `comics_dir` are ignored, and so
+/// are paths whose file no longer exists.
+pub fn resolve_local_path(comics_dir: &Path, local_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(local_path.trim());
+    let is_contained = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if !is_contained || relative.as_os_str().is_empty() {
+        return None;
+    }
+
+    let path = comics_dir.join(relative);
+    path.exists().then_some(path)
+}
+
+/// Extracts volume, chapter number and title from names such as `Vol.02 Ch.015.5 - Title`,
+/// `c015`, `Chapter 7` or `Series 012 (2019)`. Numbers lose their leading zeros.
+pub fn parse_chapter_file_name(stem: &str) -> ParsedChapterName {
+    // ASCII lowercasing keeps byte offsets aligned with `stem`.
+    let lower = stem.to_ascii_lowercase();
+    let volume = find_marked_number(&lower, &VOLUME_MARKERS);
+    let chapter = find_marked_number(&lower, &CHAPTER_MARKERS)
+        .filter(|chapter| !overlaps(volume.as_ref(), &chapter.span))
+        .or_else(|| find_bare_number(&lower, volume.as_ref()));
+
+    let title_start = chapter
+        .as_ref()
+        .or(volume.as_ref())
+        .map(|found| found.span.end)
+        .unwrap_or(0);
+    let title = (title_start > 0)
+        .then(|| {
+            stem[title_start..]
+                .trim_start_matches([' ', '-', '_', '.', ':'])
+                .trim()
+                .to_string()
+        })
+        .filter(|title| !title.is_empty());
+
+    ParsedChapterName {
+        volume: volume.map(|found| found.value),
+        number: chapter.map(|found| found.value),
+        title,
+    }
+}
+
+struct NumberMatch {
+    value: String,
+    span: Range<usize>,
+}
+
+fn overlaps(other: Option<&NumberMatch>, span: &Range<usize>) -> bool {
+    other
+        .map(|other| other.span.start < span.end && span.start < other.span.end)
+        .unwrap_or(false)
+}
+
+fn find_marked_number(text: &str, markers: &[&str]) -> Option<NumberMatch> {
+    let bytes = text.as_bytes();
+    for start in 0..bytes.len() {
+        if start > 0 && bytes[start - 1].is_ascii_alphanumeric() {
+            continue;
+        }
+
+        for marker in markers {
+            if !bytes[start..].starts_with(marker.as_bytes()) {
+                continue;
+            }
+
+            let mut cursor = start + marker.len();
+            while cursor < bytes.len() && matches!(bytes[cursor], b'.' | b' ' | b'_' | b'-') {
+                cursor += 1;
+            }
+            if let Some((value, end)) = read_number(bytes, cursor) {
+                return Some(NumberMatch {
+                    value,
+                    span: start..end,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// First standalone number outside brackets and outside the volume marker.
+fn find_bare_number(text: &str, volume: Option<&NumberMatch>) -> Option<NumberMatch> {
+    let bytes = text.as_bytes();
+    let mut depth = 0usize;
+    let mut index = 0usize;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth = depth.saturating_sub(1),
+            byte if byte.is_ascii_digit()
+                && depth == 0
+                && (index == 0 || !bytes[index - 1].is_ascii_alphanumeric()) =>
+            {
+                if let Some((value, end)) = read_number(bytes, index) {
+                    if !overlaps(volume, &(index..end)) {
+                        return Some(NumberMatch {
+                            value,
+                            span: index..end,
+                        });
+                    }
+                    index = end;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Reads `digits[.digits]` at `start`. The number must not run into letters, so `20th`
+/// is not a number.
+fn read_number(bytes: &[u8], start: usize) -> Option<(String, usize)> {
+    let digits_end = |from: usize| {
+        let mut end = from;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        end
+    };
+
+    let integer_end = digits_end(start);
+    if integer_end == start {
+        return None;
+    }
+
+    let mut end = integer_end;
+    let mut fraction = None;
+    if end + 1 < bytes.len() && bytes[end] == b'.' && bytes[end + 1].is_ascii_digit() {
+        let fraction_end = digits_end(end + 1);
+        fraction = Some(&bytes[end + 1..fraction_end]);
+        end = fraction_end;
+    }
+
+    if end < bytes.len() && bytes[end].is_ascii_alphabetic() {
+        return None;
+    }
+
+    let integer = std::str::from_utf8(&bytes[start..integer_end]).ok()?;
+    let integer = match integer.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    let value = match fraction.and_then(|fraction| std::str::from_utf8(fraction).ok()) {
+        Some(fraction) => format!("{integer}.{fraction}"),
+        None => integer.to_string(),
+    };
+    Some((value, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(
+        volume: Option<&str>,
+        number: Option<&str>,
+        title: Option<&str>,
+    ) -> ParsedChapterName {
+        ParsedChapterName {
+            volume: volume.map(str::to_string),
+            number: number.map(str::to_string),
+            title: title.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parses_volume_and_chapter_markers() {
+        assert_eq!(
+            parse_chapter_file_name("Vol.02 Ch.015.5"),
+            parsed(Some("2"), Some("15.5"), None)
+        );
+        assert_eq!(
+            parse_chapter_file_name("Pablo v3 c021 - The Return"),
+            parsed(Some("3"), Some("21"), Some("The Return"))
+        );
+        assert_eq!(
+            parse_chapter_file_name("Chapter 7"),
+            parsed(None, Some("7"), None)
+        );
+        assert_eq!(
+            parse_chapter_file_name("Volume 4"),
+            parsed(Some("4"), None, None)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_first_bare_number() {
+        assert_eq!(
+            parse_chapter_file_name("20th Century Boys 012 (2019) (Digital)"),
+            parsed(None, Some("12"), Some("(2019) (Digital)"))
+        );
+        assert_eq!(
+            parse_chapter_file_name("[Group] Pablo 000"),
+            parsed(None, Some("0"), None)
+        );
+        assert_eq!(parse_chapter_file_name("Extras"), parsed(None, None, None));
+    }
+
+    #[test]
+    fn groups_archives_by_comic_folder() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let comic = root.join("Pablo");
+        fs::create_dir_all(comic.join("Ch.2")).unwrap();
+        fs::create_dir_all(root.join("covers")).unwrap();
+        fs::write(comic.join("Ch.10.cbz"), b"").unwrap();
+        fs::write(comic.join("Ch.2").join("001.jpg"), b"").unwrap();
+        fs::write(comic.join("cover.jpg"), b"").unwrap();
+        fs::write(root.join("Loose 1.cbz"), b"").unwrap();
+        fs::write(root.join("covers").join("Ch 1.cbz"), b"").unwrap();
+
+        let listing = list_library(root, &[root.join("covers")]);
+        assert_eq!(listing.folders.len(), 1);
+        let stems = listing.folders[0]
+            .chapters
+            .iter()
+            .map(|chapter| chapter.stem.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(stems, vec!["Ch.2", "Ch.10"]);
+        assert_eq!(listing.loose_archives, vec![root.join("Loose 1.cbz")]);
+    }
+
+    #[test]
+    fn sanitizes_segments_without_dropping_unicode() {
+        assert_eq!(sanitize_segment("ワンピース"), "ワンピース");
+        assert_eq!(sanitize_segment("Cafe\u{301} <Noir>?"), "Café _Noir__");
+        assert_eq!(sanitize_segment(" .hidden. "), "hidden");
+        assert_eq!(sanitize_segment("con.cbz"), "con_.cbz");
+        assert_eq!(sanitize_segment("NUL"), "NUL_");
+        assert_eq!(sanitize_segment("..."), "untitled");
+        assert!(sanitize_segment(&"가".repeat(100)).len() <= MAX_SEGMENT_BYTES);
+        assert_eq!(legacy_sanitize_segment("Café"), "Caf_");
+    }
+
+    #[test]
+    fn finds_folders_and_archives_saved_under_the_legacy_scheme() {
+        assert!(comic_dir_matches("comic:1", "Café", "Café"));
+        assert!(comic_dir_matches("comic:1", "Café", "Caf_ (2)"));
+        assert!(!comic_dir_matches("comic:1", "Café", "Caf"));
+
+        let candidates = chapter_file_candidates("Capítulo 1", Some("1"));
+        assert_eq!(candidates[0], "Capítulo 1");
+        assert!(candidates.contains(&"Cap_tulo 1".to_string()));
+    }
+
+    #[test]
+    fn local_paths_stay_inside_the_comics_dir() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let archive = root.join("Pablo (2)").join("Chapter 1.cbz");
+        fs::create_dir_all(archive.parent().unwrap()).unwrap();
+        fs::write(&archive, b"").unwrap();
+
+        let local_path = to_local_path(root, &archive).expect("local path");
+        assert_eq!(local_path, "Pablo (2)/Chapter 1.cbz");
+        assert_eq!(resolve_local_path(root, &local_path), Some(archive));
+        assert_eq!(resolve_local_path(root, "../Pablo (2)/Chapter 1.cbz"), None);
+        assert_eq!(resolve_local_path(root, "Pablo (2)/Chapter 2.cbz"), None);
+        assert_eq!(
+            to_local_path(root, Path::new("/elsewhere/Chapter 1.cbz")),
+            None
+        );
+    }
+}
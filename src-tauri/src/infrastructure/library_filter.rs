@@ -0,0 +1,130 @@
+use rusqlite::{params, Connection};
+use serde_json::Value;
+
+use super::{row_to_record, tags::tag_aliases};
+use crate::{
+    application::canonical_tag,
+    domain::{AppError, DbRecord, LibraryFilter, LibrarySort},
+};
+
+/// Comics carrying every included tag and none of the excluded ones, narrowed by the
+/// personal rating and favorite flag, in the order the filter asks for.
+pub(super) fn filter_comics(
+    conn: &Connection,
+    filter: &LibraryFilter,
+) -> Result<Vec<DbRecord>, AppError> {
+    let aliases = tag_aliases(conn)?;
+    let resolve = |names: &[String]| {
+        let mut ids = names
+            .iter()
+            .filter_map(|name| canonical_tag(name, &aliases))
+            .map(|tag| tag.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    };
+    let include = resolve(&filter.include_tags);
+    let exclude = resolve(&filter.exclude_tags);
+
+    let order = match filter.sort {
+        LibrarySort::Name => "",
+        LibrarySort::Rating => "pr.rating IS NULL, pr.rating DESC,",
+        LibrarySort::Favorite => "COALESCE(pr.favorite, 0) DESC,",
+    };
+    let sql = format!(
+        "
+        SELECT c.id, c.data, c.created_at, c.updated_at
+        FROM comics c
+        LEFT JOIN personal_ratings pr ON pr.item_type = 'comic' AND pr.item_id = c.id
+        WHERE (
+            SELECT COUNT(DISTINCT ct.tag_id)
+            FROM comic_tags ct
+            WHERE ct.comic_id = c.id AND ct.tag_id IN (SELECT value FROM json_each(?1))
+          ) = ?2
+          AND NOT EXISTS (
+            SELECT 1
+            FROM comic_tags ct
+            WHERE ct.comic_id = c.id AND ct.tag_id IN (SELECT value FROM json_each(?3))
+          )
+          AND (?4 IS NULL OR COALESCE(pr.favorite, 0) = ?4)
+          AND (?5 IS NULL OR pr.rating >= ?5)
+        ORDER BY {order} json_extract(c.data, '$.name') COLLATE NOCASE, c.id;
+        "
+    );
+    let mut stmt = conn
+        .prepare(&sql)
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map(
+            params![
+                Value::from(include.clone()).to_string(),
+                include.len() as i64,
+                Value::from(exclude).to_string(),
+                filter.favorite,
+                filter.min_rating
+            ],
+            row_to_record,
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::migrations::{MigrationRunner, SqliteMigrationRunner};
+
+    #[test]
+    fn filters_and_sorts_by_personal_rating() {
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        conn.execute_batch(
+            r#"
+            INSERT INTO comics (id, data) VALUES
+              ('comic-a', json('{"name":"Akira"}')),
+              ('comic-b', json('{"name":"Berserk"}')),
+              ('comic-c', json('{"name":"Claymore"}'));
+            INSERT INTO personal_ratings (id, data) VALUES
+              ('comic:comic-b', json('{"itemType":"comic","itemId":"comic-b","rating":9,"favorite":true}')),
+              ('comic:comic-c', json('{"itemType":"comic","itemId":"comic-c","rating":6,"favorite":false}'));
+            "#,
+        )
+        .expect("seed library");
+
+        let ids = |filter: LibraryFilter| {
+            filter_comics(&conn, &filter)
+                .expect("filter")
+                .into_iter()
+                .map(|comic| comic.id)
+                .collect::<Vec<_>>()
+        };
+        let sorted = |sort| LibraryFilter {
+            sort,
+            ..LibraryFilter::default()
+        };
+        assert_eq!(
+            ids(sorted(LibrarySort::Name)),
+            ["comic-a", "comic-b", "comic-c"]
+        );
+        assert_eq!(
+            ids(sorted(LibrarySort::Rating)),
+            ["comic-b", "comic-c", "comic-a"]
+        );
+        assert_eq!(
+            ids(sorted(LibrarySort::Favorite)),
+            ["comic-b", "comic-a", "comic-c"]
+        );
+
+        let rated_non_favorites = LibraryFilter {
+            favorite: Some(false),
+            min_rating: Some(5),
+            ..LibraryFilter::default()
+        };
+        assert_eq!(ids(rated_non_favorites), ["comic-c"]);
+    }
+}
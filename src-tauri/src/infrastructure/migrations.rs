@@ -1,21 +1,37 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
 
 use rusqlite::{params, Connection};
+use serde_json::Value;
 
+use super::library_files::{comic_dir_candidates, guess_chapter_archive, to_local_path};
 use crate::domain::{AppError, LegacyImportReport};
 
 pub trait MigrationRunner: Send + Sync {
     fn run(&self, conn: &Connection) -> Result<(), AppError>;
 }
 
+/// What a backfill may need besides the database.
+pub struct BackfillContext<'a> {
+    /// Where chapter archives live; `None` when the store is opened without a library.
+    pub comics_dir: Option<&'a Path>,
+}
+
+/// Runs after a migration's SQL for data that cannot be moved with SQL alone.
+type Backfill = fn(&Connection, &BackfillContext) -> Result<(), AppError>;
+
 struct Migration {
     version: i64,
     name: &'static str,
     sql: &'static str,
+    backfill: Option<Backfill>,
 }
 
 pub struct SqliteMigrationRunner {
     migrations: Vec<Migration>,
+    comics_dir: Option<PathBuf>,
 }
 
 impl SqliteMigrationRunner {
@@ -26,38 +42,90 @@ impl SqliteMigrationRunner {
                     version: 1,
                     name: "baseline_json_schema",
                     sql: BASELINE_SCHEMA_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 3,
                     name: "drop_users_table",
                     sql: DROP_USERS_TABLE_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 4,
                     name: "remove_user_id_from_json_documents",
                     sql: REMOVE_USER_ID_FROM_JSON_DOCUMENTS_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 5,
                     name: "add_app_state_table",
                     sql: ADD_APP_STATE_TABLE_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 6,
                     name: "dedupe_read_progress_by_chapter_and_add_unique_index",
                     sql: DEDUPE_READ_PROGRESS_AND_ADD_UNIQUE_CHAPTER_INDEX_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 7,
                     name: "add_relational_columns_and_indexes",
                     sql: ADD_RELATIONAL_COLUMNS_AND_INDEXES_SQL,
+                    backfill: None,
                 },
                 Migration {
                     version: 8,
                     name: "add_metadata_content_mapping_tables",
                     sql: ADD_METADATA_CONTENT_MAPPING_TABLES_SQL,
+                    backfill: None,
+                },
+                Migration {
+                    version: 9,
+                    name: "backfill_chapter_local_paths",
+                    sql: "",
+                    backfill: Some(backfill_chapter_local_paths),
+                },
+                Migration {
+                    version: 10,
+                    name: "add_reading_sessions_table",
+                    sql: ADD_READING_SESSIONS_TABLE_SQL,
+                    backfill: None,
+                },
+                Migration {
+                    version: 11,
+                    name: "add_bookmarks_table",
+                    sql: ADD_BOOKMARKS_TABLE_SQL,
+                    backfill: None,
+                },
+                Migration {
+                    version: 12,
+                    name: "add_collection_tables",
+                    sql: ADD_COLLECTION_TABLES_SQL,
+                    backfill: None,
+                },
+                Migration {
+                    version: 13,
+                    name: "add_tag_tables",
+                    sql: ADD_TAG_TABLES_SQL,
+                    backfill: Some(|conn, _| super::tags::backfill_genre_tags(conn)),
+                },
+                Migration {
+                    version: 14,
+                    name: "add_personal_ratings_table",
+                    sql: ADD_PERSONAL_RATINGS_TABLE_SQL,
+                    backfill: None,
                 },
             ],
+            comics_dir: None,
+        }
+    }
+
+    /// Runner whose backfills can look at the chapter archives under `comics_dir`.
+    pub fn with_comics_dir(comics_dir: &Path) -> Self {
+        Self {
+            comics_dir: Some(comics_dir.to_path_buf()),
+            ..Self::new()
         }
     }
 }
@@ -101,6 +169,12 @@ impl MigrationRunner for SqliteMigrationRunner {
 
             conn.execute_batch(migration.sql)
                 .map_err(|error| AppError::infrastructure(error.to_string()))?;
+            if let Some(backfill) = migration.backfill {
+                let context = BackfillContext {
+                    comics_dir: self.comics_dir.as_deref(),
+                };
+                backfill(conn, &context)?;
+            }
             conn.execute(
                 "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2);",
                 params![migration.version, migration.name],
@@ -112,6 +186,74 @@ impl MigrationRunner for SqliteMigrationRunner {
     }
 }
 
+/// Stores the archive the name heuristics find as `localPath` on chapters saved before the
+/// field existed. Chapters without a file on disk are left alone.
+fn backfill_chapter_local_paths(
+    conn: &Connection,
+    context: &BackfillContext,
+) -> Result<(), AppError> {
+    let Some(comics_dir) = context.comics_dir else {
+        return Ok(());
+    };
+    let text = |data: &Value, key: &str| match data.get(key) {
+        Some(Value::String(text)) if !text.trim().is_empty() => Some(text.trim().to_string()),
+        Some(Value::Number(number)) => Some(number.to_string()),
+        _ => None,
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "
+            SELECT ch.id, ch.data, co.data
+            FROM chapters ch
+            LEFT JOIN comics co ON co.id = json_extract(ch.data, '$.comicId')
+            WHERE json_extract(ch.data, '$.localPath') IS NULL;
+            ",
+        )
+        .map_err(|error| AppError::infrastructure(error.to_string()))?;
+    let chapters = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|error| AppError::infrastructure(error.to_string()))?;
+
+    let mut comic_dirs = HashMap::<String, Vec<PathBuf>>::new();
+    for (chapter_id, chapter_data, comic_data) in chapters {
+        let chapter = serde_json::from_str::<Value>(&chapter_data).unwrap_or(Value::Null);
+        let comic = comic_data
+            .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+            .unwrap_or(Value::Null);
+        let Some(comic_id) = text(&chapter, "comicId") else {
+            continue;
+        };
+        let comic_name = text(&comic, "name").unwrap_or_else(|| comic_id.clone());
+        let number = text(&chapter, "number");
+        let chapter_name = text(&chapter, "name")
+            .or_else(|| number.as_ref().map(|number| format!("Chapter {number}")))
+            .unwrap_or_else(|| "chapter".to_string());
+
+        let dirs = comic_dirs
+            .entry(comic_id.clone())
+            .or_insert_with(|| comic_dir_candidates(comics_dir, &comic_id, &comic_name));
+        let Some(local_path) = guess_chapter_archive(dirs, &chapter_name, number.as_deref())
+            .and_then(|path| to_local_path(comics_dir, &path))
+        else {
+            continue;
+        };
+        conn.execute(
+            "UPDATE chapters SET data = json_set(data, '$.localPath', ?1) WHERE id = ?2;",
+            params![local_path, chapter_id],
+        )
+        .map_err(|error| AppError::infrastructure(error.to_string()))?;
+    }
+    Ok(())
+}
+
 pub fn import_legacy_database(
     conn: &Connection,
     path_override: Option<PathBuf>,
@@ -264,6 +406,9 @@ pub fn import_legacy_database(
 
     let _ = conn.execute("DETACH DATABASE legacy_db;", []);
     let imported = import_result?;
+    if imported > 0 {
+        super::tags::backfill_genre_tags(conn)?;
+    }
 
     let report = LegacyImportReport {
         legacy_db_path: legacy_path.display().to_string(),
@@ -575,6 +720,292 @@ ON chapter_mappings (
 );
 "#;
 
+const ADD_READING_SESSIONS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS reading_sessions (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  chapter_id TEXT,
+  comic_id TEXT,
+  started_at INTEGER,
+  ended_at INTEGER,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_reading_sessions_chapter_id ON reading_sessions (chapter_id, ended_at);
+CREATE INDEX IF NOT EXISTS idx_reading_sessions_comic_id ON reading_sessions (comic_id);
+CREATE INDEX IF NOT EXISTS idx_reading_sessions_started_at ON reading_sessions (started_at);
+
+CREATE TRIGGER IF NOT EXISTS trg_reading_sessions_sync_relational_after_insert
+AFTER INSERT ON reading_sessions
+FOR EACH ROW
+BEGIN
+  UPDATE reading_sessions
+  SET
+    chapter_id = json_extract(NEW.data, '$.chapterId'),
+    comic_id = json_extract(NEW.data, '$.comicId'),
+    started_at = json_extract(NEW.data, '$.startedAt'),
+    ended_at = json_extract(NEW.data, '$.endedAt')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_reading_sessions_sync_relational_after_update
+AFTER UPDATE OF data ON reading_sessions
+FOR EACH ROW
+BEGIN
+  UPDATE reading_sessions
+  SET
+    chapter_id = json_extract(NEW.data, '$.chapterId'),
+    comic_id = json_extract(NEW.data, '$.comicId'),
+    started_at = json_extract(NEW.data, '$.startedAt'),
+    ended_at = json_extract(NEW.data, '$.endedAt')
+  WHERE id = NEW.id;
+END;
+"#;
+
+const ADD_BOOKMARKS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS bookmarks (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  chapter_id TEXT,
+  comic_id TEXT,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmarks_chapter_id ON bookmarks (chapter_id);
+CREATE INDEX IF NOT EXISTS idx_bookmarks_comic_id ON bookmarks (comic_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_bookmarks_sync_relational_after_insert
+AFTER INSERT ON bookmarks
+FOR EACH ROW
+BEGIN
+  UPDATE bookmarks
+  SET
+    chapter_id = json_extract(NEW.data, '$.chapterId'),
+    comic_id = json_extract(NEW.data, '$.comicId')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_bookmarks_sync_relational_after_update
+AFTER UPDATE OF data ON bookmarks
+FOR EACH ROW
+BEGIN
+  UPDATE bookmarks
+  SET
+    chapter_id = json_extract(NEW.data, '$.chapterId'),
+    comic_id = json_extract(NEW.data, '$.comicId')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_comics_delete_bookmarks
+AFTER DELETE ON comics
+FOR EACH ROW
+BEGIN
+  DELETE FROM bookmarks WHERE comic_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_chapters_delete_bookmarks
+AFTER DELETE ON chapters
+FOR EACH ROW
+BEGIN
+  DELETE FROM bookmarks WHERE chapter_id = OLD.id;
+END;
+"#;
+
+const ADD_COLLECTION_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS collections (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE TABLE IF NOT EXISTS collection_items (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  collection_id TEXT,
+  item_type TEXT,
+  item_id TEXT,
+  comic_id TEXT,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_items_collection_id ON collection_items (collection_id);
+CREATE INDEX IF NOT EXISTS idx_collection_items_item ON collection_items (item_type, item_id);
+CREATE INDEX IF NOT EXISTS idx_collection_items_comic_id ON collection_items (comic_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_collection_items_sync_relational_after_insert
+AFTER INSERT ON collection_items
+FOR EACH ROW
+BEGIN
+  UPDATE collection_items
+  SET
+    collection_id = json_extract(NEW.data, '$.collectionId'),
+    item_type = json_extract(NEW.data, '$.itemType'),
+    item_id = json_extract(NEW.data, '$.itemId'),
+    comic_id = json_extract(NEW.data, '$.comicId')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_collection_items_sync_relational_after_update
+AFTER UPDATE OF data ON collection_items
+FOR EACH ROW
+BEGIN
+  UPDATE collection_items
+  SET
+    collection_id = json_extract(NEW.data, '$.collectionId'),
+    item_type = json_extract(NEW.data, '$.itemType'),
+    item_id = json_extract(NEW.data, '$.itemId'),
+    comic_id = json_extract(NEW.data, '$.comicId')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_collections_delete_items
+AFTER DELETE ON collections
+FOR EACH ROW
+BEGIN
+  DELETE FROM collection_items WHERE collection_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_comics_delete_collection_items
+AFTER DELETE ON comics
+FOR EACH ROW
+BEGIN
+  DELETE FROM collection_items WHERE comic_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_chapters_delete_collection_items
+AFTER DELETE ON chapters
+FOR EACH ROW
+BEGIN
+  DELETE FROM collection_items WHERE item_type = 'chapter' AND item_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_works_delete_collection_items
+AFTER DELETE ON works
+FOR EACH ROW
+BEGIN
+  DELETE FROM collection_items WHERE item_type = 'work' AND item_id = OLD.id;
+END;
+"#;
+
+const ADD_TAG_TABLES_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS tags (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE TABLE IF NOT EXISTS comic_tags (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  comic_id TEXT,
+  tag_id TEXT,
+  source TEXT,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_comic_tags_comic_id ON comic_tags (comic_id);
+CREATE INDEX IF NOT EXISTS idx_comic_tags_tag_id ON comic_tags (tag_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_comic_tags_sync_relational_after_insert
+AFTER INSERT ON comic_tags
+FOR EACH ROW
+BEGIN
+  UPDATE comic_tags
+  SET
+    comic_id = json_extract(NEW.data, '$.comicId'),
+    tag_id = json_extract(NEW.data, '$.tagId'),
+    source = json_extract(NEW.data, '$.source')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_comic_tags_sync_relational_after_update
+AFTER UPDATE OF data ON comic_tags
+FOR EACH ROW
+BEGIN
+  UPDATE comic_tags
+  SET
+    comic_id = json_extract(NEW.data, '$.comicId'),
+    tag_id = json_extract(NEW.data, '$.tagId'),
+    source = json_extract(NEW.data, '$.source')
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_tags_delete_comic_tags
+AFTER DELETE ON tags
+FOR EACH ROW
+BEGIN
+  DELETE FROM comic_tags WHERE tag_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_comics_delete_comic_tags
+AFTER DELETE ON comics
+FOR EACH ROW
+BEGIN
+  DELETE FROM comic_tags WHERE comic_id = OLD.id;
+END;
+"#;
+
+const ADD_PERSONAL_RATINGS_TABLE_SQL: &str = r#"
+CREATE TABLE IF NOT EXISTS personal_ratings (
+  id TEXT PRIMARY KEY NOT NULL,
+  data TEXT NOT NULL CHECK (json_valid(data)),
+  item_type TEXT,
+  item_id TEXT,
+  rating INTEGER,
+  favorite INTEGER,
+  created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')),
+  updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_personal_ratings_item ON personal_ratings (item_type, item_id);
+
+CREATE TRIGGER IF NOT EXISTS trg_personal_ratings_sync_relational_after_insert
+AFTER INSERT ON personal_ratings
+FOR EACH ROW
+BEGIN
+  UPDATE personal_ratings
+  SET
+    item_type = json_extract(NEW.data, '$.itemType'),
+    item_id = json_extract(NEW.data, '$.itemId'),
+    rating = json_extract(NEW.data, '$.rating'),
+    favorite = COALESCE(json_extract(NEW.data, '$.favorite'), 0)
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_personal_ratings_sync_relational_after_update
+AFTER UPDATE OF data ON personal_ratings
+FOR EACH ROW
+BEGIN
+  UPDATE personal_ratings
+  SET
+    item_type = json_extract(NEW.data, '$.itemType'),
+    item_id = json_extract(NEW.data, '$.itemId'),
+    rating = json_extract(NEW.data, '$.rating'),
+    favorite = COALESCE(json_extract(NEW.data, '$.favorite'), 0)
+  WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_comics_delete_personal_ratings
+AFTER DELETE ON comics
+FOR EACH ROW
+BEGIN
+  DELETE FROM personal_ratings WHERE item_type = 'comic' AND item_id = OLD.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_works_delete_personal_ratings
+AFTER DELETE ON works
+FOR EACH ROW
+BEGIN
+  DELETE FROM personal_ratings WHERE item_type = 'work' AND item_id = OLD.id;
+END;
+"#;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -644,6 +1075,62 @@ mod tests {
         let _ = fs::remove_dir_all(root);
     }
 
+    #[test]
+    fn backfills_local_paths_of_existing_chapters_once() {
+        let comics = tempfile::tempdir().expect("create comics dir");
+        let comic_dir = comics.path().join("Pablo");
+        fs::create_dir_all(&comic_dir).expect("create comic dir");
+        fs::write(comic_dir.join("Chapter 1.cbz"), b"").expect("write archive");
+
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        // An existing database: everything up to the backfill already applied.
+        conn.execute_batch(
+            r#"
+            DELETE FROM schema_migrations WHERE version = 9;
+            INSERT INTO comics (id, data) VALUES ('comic-1', json('{"name":"Pablo"}'));
+            INSERT INTO chapters (id, data) VALUES
+              ('chapter-1', json('{"comicId":"comic-1","name":"Chapter 1","number":1}')),
+              ('chapter-2', json('{"comicId":"comic-1","name":"Chapter 2","number":2}')),
+              ('chapter-3', json('{"comicId":"comic-1","name":"Chapter 1","localPath":"Kept/Chapter 1.cbz"}'));
+            "#,
+        )
+        .expect("seed chapters");
+
+        SqliteMigrationRunner::with_comics_dir(comics.path())
+            .run(&conn)
+            .expect("run backfill");
+
+        let local_path = |id: &str| -> Option<String> {
+            conn.query_row(
+                "SELECT json_extract(data, '$.localPath') FROM chapters WHERE id = ?1;",
+                [id],
+                |row| row.get(0),
+            )
+            .expect("query chapter")
+        };
+        assert_eq!(
+            local_path("chapter-1").as_deref(),
+            Some("Pablo/Chapter 1.cbz")
+        );
+        assert_eq!(local_path("chapter-2"), None);
+        assert_eq!(
+            local_path("chapter-3").as_deref(),
+            Some("Kept/Chapter 1.cbz")
+        );
+
+        let applied: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM schema_migrations WHERE version = 9;",
+                [],
+                |row| row.get(0),
+            )
+            .expect("query migrations");
+        assert_eq!(applied, 1);
+    }
+
     #[test]
     fn removes_user_id_from_existing_json_documents() {
         let conn = Connection::open_in_memory().expect("open memory db");
@@ -724,4 +1211,45 @@ mod tests {
             .expect("query changelog");
         assert_eq!(changelog_has_user_id, 0);
     }
+
+    #[test]
+    fn deletes_bookmarks_with_their_comic_or_chapter() {
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        conn.execute_batch(
+            r#"
+            INSERT INTO comics (id, data) VALUES
+              ('comic-1', json('{"name":"A"}')),
+              ('comic-2', json('{"name":"B"}'));
+            INSERT INTO chapters (id, data) VALUES
+              ('c-1', json('{"comicId":"comic-1"}')),
+              ('c-2', json('{"comicId":"comic-1"}')),
+              ('c-3', json('{"comicId":"comic-2"}'));
+            INSERT INTO bookmarks (id, data) VALUES
+              ('b-1', json('{"chapterId":"c-1","comicId":"comic-1","page":3}')),
+              ('b-2', json('{"chapterId":"c-2","comicId":"comic-1","page":5}')),
+              ('b-3', json('{"chapterId":"c-3","comicId":"comic-2","page":1}'));
+            "#,
+        )
+        .expect("seed bookmarks");
+        let bookmarks = || -> Vec<String> {
+            let mut stmt = conn
+                .prepare("SELECT id FROM bookmarks ORDER BY id;")
+                .expect("prepare");
+            stmt.query_map([], |row| row.get(0))
+                .expect("query bookmarks")
+                .collect::<Result<_, _>>()
+                .expect("read bookmarks")
+        };
+
+        conn.execute("DELETE FROM chapters WHERE id = 'c-1';", [])
+            .expect("delete chapter");
+        assert_eq!(bookmarks(), vec!["b-2", "b-3"]);
+
+        conn.execute("DELETE FROM comics WHERE id = 'comic-2';", [])
+            .expect("delete comic");
+        assert_eq!(bookmarks(), vec!["b-2"]);
+    }
 }
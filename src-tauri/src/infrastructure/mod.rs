@@ -1,6 +1,26 @@
+pub mod archive;
+pub mod archive_health;
+pub mod comic_info;
+mod comic_progress;
+pub mod content_hash;
+pub mod covers;
+pub mod export;
+pub mod image_variants;
+pub mod library_files;
+mod library_filter;
 mod migrations;
-
-use std::{collections::HashSet, fs, path::Path, path::PathBuf};
+pub mod page_metadata;
+mod reading_sessions;
+pub mod storage;
+mod tags;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use rusqlite::{params, Connection, OptionalExtension};
 use sea_query::{Alias, Expr, ExprTrait, Order, Query, SqliteQueryBuilder, Value as SeaValue};
@@ -10,8 +30,11 @@ use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    application::{DocumentStore, LegacyImporter},
-    domain::{AppError, DbRecord, LegacyImportReport, Table},
+    application::{chapter_in_selection, DocumentStore, LegacyImporter},
+    domain::{
+        AppError, ChapterSelection, ComicProgress, ComicTag, DbRecord, DocumentWrite,
+        LegacyImportReport, LibraryFilter, ReadingStats, ReadingStatsRange, Table, TagSummary,
+    },
 };
 use migrations::{import_legacy_database, MigrationRunner, SqliteMigrationRunner};
 
@@ -22,8 +45,13 @@ pub struct SqliteDocumentStore {
 }
 
 impl SqliteDocumentStore {
-    pub fn initialize(base_dir: &Path) -> Result<Self, AppError> {
-        Self::initialize_with_runner(base_dir, Arc::new(SqliteMigrationRunner::new()))
+    /// Opens the database under `base_dir`; migrations that backfill from chapter archives
+    /// look for them in `comics_dir`.
+    pub fn initialize(base_dir: &Path, comics_dir: &Path) -> Result<Self, AppError> {
+        Self::initialize_with_runner(
+            base_dir,
+            Arc::new(SqliteMigrationRunner::with_comics_dir(comics_dir)),
+        )
     }
 
     pub fn initialize_with_runner(
@@ -40,85 +68,13 @@ impl SqliteDocumentStore {
 
 impl DocumentStore for SqliteDocumentStore {
     fn upsert(&self, table: Table, id: Option<String>, data: Value) -> Result<DbRecord, AppError> {
-        if !data.is_object() {
-            return Err(AppError::Validation(
-                "Expected data to be a JSON object".to_string(),
-            ));
-        }
-
-        let conn = open_connection(&self.db_path)?;
-        let id = match (table, id) {
-            // read_progress: keep one row per chapter regardless of legacy ids.
-            (Table::ReadProgress, _) => {
-                let chapter_id = data
-                    .get("chapterId")
-                    .and_then(Value::as_str)
-                    .map(str::trim)
-                    .filter(|value| !value.is_empty())
-                    .map(ToOwned::to_owned)
-                    .ok_or_else(|| {
-                        AppError::Validation(
-                            "read_progress upsert requires a valid data.chapterId".to_string(),
-                        )
-                    })?;
-
-                let existing_id: Option<String> = conn
-                    .query_row(
-                        "SELECT id FROM read_progress
-                         WHERE json_extract(data, '$.chapterId') = ?1
-                         ORDER BY updated_at DESC
-                         LIMIT 1",
-                        params![chapter_id],
-                        |row| row.get(0),
-                    )
-                    .optional()
-                    .map_err(|e| AppError::infrastructure(e.to_string()))?;
-
-                existing_id.unwrap_or(chapter_id)
-            }
-            (_, Some(existing_id)) => existing_id,
-            (_, None) => Uuid::new_v4().to_string(),
-        };
-        let table_name = table.as_str();
-
-        let payload =
-            serde_json::to_string(&data).map_err(|e| AppError::infrastructure(e.to_string()))?;
-
-        if matches!(table, Table::ReadProgress) {
-            let sql = format!(
-                "
-                INSERT INTO {table_name} (id, data, chapter_id, comic_id)
-                VALUES (
-                  ?1,
-                  json(?2),
-                  json_extract(json(?2), '$.chapterId'),
-                  json_extract(json(?2), '$.comicId')
-                )
-                ON CONFLICT(id) DO UPDATE SET
-                  data = json(?2),
-                  chapter_id = json_extract(json(?2), '$.chapterId'),
-                  comic_id = json_extract(json(?2), '$.comicId'),
-                  updated_at = ({timestamp});
-                ",
-                timestamp = TIMESTAMP_SQL
-            );
-
-            conn.execute(&sql, params![id, payload.clone()])
-                .map_err(|e| AppError::infrastructure(e.to_string()))?;
-        } else {
-            let sql = format!(
-                "
-                INSERT INTO {table_name} (id, data)
-                VALUES (?1, json(?2))
-                ON CONFLICT(id) DO UPDATE SET
-                  data = json(?2),
-                  updated_at = ({timestamp});
-                ",
-                timestamp = TIMESTAMP_SQL
-            );
-            conn.execute(&sql, params![id, payload])
-                .map_err(|e| AppError::infrastructure(e.to_string()))?;
-        }
+        let mut conn = open_connection(&self.db_path)?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let id = upsert_document(&tx, table, id, &data)?;
+        tx.commit()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
 
         self.get(table, &id)?.ok_or_else(|| {
             AppError::Infrastructure("Failed to fetch record after upsert".to_string())
@@ -147,6 +103,34 @@ impl DocumentStore for SqliteDocumentStore {
             .map_err(|e| AppError::infrastructure(e.to_string()))
     }
 
+    fn get_many(&self, table: Table, ids: &[String]) -> Result<Vec<DbRecord>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = open_connection(&self.db_path)?;
+        let mut query = Query::select();
+        query
+            .columns([
+                Alias::new("id"),
+                Alias::new("data"),
+                Alias::new("created_at"),
+                Alias::new("updated_at"),
+            ])
+            .from(Alias::new(table.as_str()))
+            .and_where(Expr::col(Alias::new("id")).is_in(ids.iter().cloned()));
+
+        let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
+        let params = values.as_params();
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let rows = stmt
+            .query_map(params.as_slice(), row_to_record)
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::infrastructure(e.to_string()))
+    }
+
     fn list(
         &self,
         table: Table,
@@ -198,6 +182,9 @@ impl DocumentStore for SqliteDocumentStore {
             (Table::Chapters, "comicId" | "$.comicId") => Some("comic_id"),
             (Table::ReadProgress, "chapterId" | "$.chapterId") => Some("chapter_id"),
             (Table::ReadProgress, "comicId" | "$.comicId") => Some("comic_id"),
+            (Table::Bookmarks, "chapterId" | "$.chapterId") => Some("chapter_id"),
+            (Table::Bookmarks, "comicId" | "$.comicId") => Some("comic_id"),
+            (Table::CollectionItems, "collectionId" | "$.collectionId") => Some("collection_id"),
             _ => None,
         };
 
@@ -255,60 +242,124 @@ impl DocumentStore for SqliteDocumentStore {
 
     fn delete(&self, table: Table, id: &str) -> Result<bool, AppError> {
         let conn = open_connection(&self.db_path)?;
-        let mut query = Query::delete();
-        query
-            .from_table(Alias::new(table.as_str()))
-            .and_where(Expr::col(Alias::new("id")).eq(id.to_string()));
+        delete_document(&conn, table, id)
+    }
 
-        let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
-        let params = values.as_params();
-        let affected = conn
-            .execute(&sql, params.as_slice())
+    fn write_batch(&self, writes: &[DocumentWrite]) -> Result<Vec<DbRecord>, AppError> {
+        let mut conn = open_connection(&self.db_path)?;
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let mut upserted = Vec::new();
+        for write in writes {
+            match write {
+                DocumentWrite::Upsert { table, id, data } => {
+                    let id = upsert_document(&tx, *table, id.clone(), data)?;
+                    upserted.push((*table, id));
+                }
+                DocumentWrite::Delete { table, id } => {
+                    delete_document(&tx, *table, id)?;
+                }
+                DocumentWrite::SetField {
+                    table,
+                    id,
+                    field,
+                    value,
+                } => {
+                    set_document_field(&tx, *table, id, field, value)?;
+                }
+            }
+        }
+        tx.commit()
             .map_err(|e| AppError::infrastructure(e.to_string()))?;
-        Ok(affected > 0)
+
+        upserted
+            .into_iter()
+            .filter_map(|(table, id)| self.get(table, &id).transpose())
+            .collect()
+    }
+
+    fn set_json_field(
+        &self,
+        table: Table,
+        id: &str,
+        field: &str,
+        value: &Value,
+    ) -> Result<bool, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        set_document_field(&conn, table, id, field, value)
     }
 
     fn mark_chapters_read_state(
         &self,
         chapter_ids: &[String],
+        selection: Option<&ChapterSelection>,
         read: bool,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
     ) -> Result<(usize, usize), AppError> {
         let mut conn = open_connection(&self.db_path)?;
+
+        // Counting pages opens archives, so it happens before the write transaction, which
+        // then reads the chapters and their progress again.
+        let mut counted_pages = HashMap::new();
+        if read {
+            for chapter_id in chapters_to_mark(&conn, chapter_ids, selection)? {
+                if latest_total_pages(&conn, &chapter_id)?.is_some_and(|total| total > 1) {
+                    continue;
+                }
+                if let Some(chapter) = select_chapter(&conn, &chapter_id)? {
+                    counted_pages.insert(chapter_id, page_count(&chapter));
+                }
+            }
+        }
+
         let tx = conn
             .transaction()
             .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let mut progress = Vec::new();
+        let mut skipped = 0usize;
+        for chapter_id in chapters_to_mark(&tx, chapter_ids, selection)? {
+            let Some(chapter) = select_chapter(&tx, &chapter_id)? else {
+                skipped += 1;
+                continue;
+            };
+            let comic_id = chapter
+                .data
+                .get("comicId")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+
+            // Older marks stored a single page, so those are counted again as well. Pages
+            // are only counted for read marks; unread ones keep the count already known.
+            let total_pages = match latest_total_pages(&tx, &chapter_id)? {
+                Some(total) if total > 1 => Some(total),
+                existing if read => Some(
+                    counted_pages
+                        .get(&chapter_id)
+                        .copied()
+                        .flatten()
+                        .or(existing)
+                        .unwrap_or(1)
+                        .max(1),
+                ),
+                existing => existing.filter(|total| *total > 0),
+            };
+            let mut payload = serde_json::json!({
+                "chapterId": chapter_id,
+                "comicId": comic_id,
+                "page": 0
+            });
+            if let Some(total_pages) = total_pages {
+                payload["totalPages"] = total_pages.into();
+                if read {
+                    payload["page"] = total_pages.into();
+                }
+            }
+            let payload_str = serde_json::to_string(&payload)
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+            progress.push((chapter_id, payload_str));
+        }
 
-        let mut chapter_meta_stmt = tx
-            .prepare(
-                "
-                SELECT json_extract(data, '$.comicId')
-                FROM chapters
-                WHERE id = ?1
-                LIMIT 1;
-                ",
-            )
-            .map_err(|e| AppError::infrastructure(e.to_string()))?;
-        let mut chapter_exists_stmt = tx
-            .prepare(
-                "
-                SELECT 1
-                FROM chapters
-                WHERE id = ?1
-                LIMIT 1;
-                ",
-            )
-            .map_err(|e| AppError::infrastructure(e.to_string()))?;
-        let mut existing_total_pages_stmt = tx
-            .prepare(
-                "
-                SELECT json_extract(data, '$.totalPages')
-                FROM read_progress
-                WHERE json_extract(data, '$.chapterId') = ?1
-                ORDER BY updated_at DESC
-                LIMIT 1;
-                ",
-            )
-            .map_err(|e| AppError::infrastructure(e.to_string()))?;
         let upsert_read_progress_sql = format!(
             "
             INSERT INTO read_progress (id, data, chapter_id, comic_id)
@@ -329,64 +380,155 @@ impl DocumentStore for SqliteDocumentStore {
         let mut upsert_read_progress_stmt = tx
             .prepare(&upsert_read_progress_sql)
             .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        for (chapter_id, payload) in &progress {
+            upsert_read_progress_stmt
+                .execute(params![chapter_id, payload])
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        }
 
-        let mut updated = 0usize;
-        let mut skipped = 0usize;
-        let mut seen = HashSet::new();
+        drop(upsert_read_progress_stmt);
+        tx.commit()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
 
-        for chapter_id in chapter_ids {
-            if !seen.insert(chapter_id) {
-                continue;
-            }
+        Ok((progress.len(), skipped))
+    }
 
-            let exists = chapter_exists_stmt
-                .query_row(params![chapter_id], |row| row.get::<_, i64>(0))
-                .optional()
+    fn repair_progress_page_counts(
+        &self,
+        page_count: &dyn Fn(&DbRecord) -> Option<i64>,
+    ) -> Result<(usize, usize), AppError> {
+        let mut conn = open_connection(&self.db_path)?;
+
+        let stale = {
+            let mut stmt = conn
+                .prepare(
+                    "
+                    SELECT
+                      id,
+                      CAST(COALESCE(json_extract(data, '$.page'), 0) AS INTEGER),
+                      chapter_id
+                    FROM read_progress
+                    WHERE json_extract(data, '$.totalPages') = 1
+                      AND chapter_id IS NOT NULL;
+                    ",
+                )
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                })
                 .map_err(|e| AppError::infrastructure(e.to_string()))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| AppError::infrastructure(e.to_string()))?
+        };
 
-            if exists.is_some() {
-                updated += 1;
-
-                let comic_id: String = chapter_meta_stmt
-                    .query_row(params![chapter_id], |row| row.get::<_, Option<String>>(0))
-                    .optional()
-                    .map_err(|e| AppError::infrastructure(e.to_string()))?
-                    .flatten()
-                    .unwrap_or_default();
-
-                let existing_total_pages = existing_total_pages_stmt
-                    .query_row(params![chapter_id], |row| row.get::<_, Option<i64>>(0))
-                    .optional()
-                    .map_err(|e| AppError::infrastructure(e.to_string()))?
-                    .flatten()
-                    .unwrap_or(1);
-
-                let total_pages = std::cmp::max(existing_total_pages, 1);
-                let page = if read { total_pages } else { 0 };
-                let payload = serde_json::json!({
-                    "chapterId": chapter_id,
-                    "comicId": comic_id,
-                    "totalPages": total_pages,
-                    "page": page
-                });
-                let payload_str = serde_json::to_string(&payload)
-                    .map_err(|e| AppError::infrastructure(e.to_string()))?;
-                upsert_read_progress_stmt
-                    .execute(params![chapter_id, payload_str])
-                    .map_err(|e| AppError::infrastructure(e.to_string()))?;
-            } else {
-                skipped += 1;
-            }
+        // Pages are counted before the write transaction, since that opens archives.
+        let mut repairs = Vec::new();
+        for (progress_id, page, chapter_id) in &stale {
+            let Some(total_pages) = select_chapter(&conn, chapter_id)?
+                .and_then(|chapter| page_count(&chapter))
+                .filter(|count| *count > 1)
+            else {
+                continue;
+            };
+            let page = if *page >= 1 { total_pages } else { 0 };
+            repairs.push((progress_id, total_pages, page));
         }
 
-        drop(upsert_read_progress_stmt);
-        drop(existing_total_pages_stmt);
-        drop(chapter_exists_stmt);
-        drop(chapter_meta_stmt);
+        // The last activity time is left alone: a repair is not reading. Rows changed since
+        // they were read no longer hold a single page and are left as they are.
+        let tx = conn
+            .transaction()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let mut repaired = 0usize;
+        for (progress_id, total_pages, page) in &repairs {
+            repaired += tx
+                .execute(
+                    "
+                    UPDATE read_progress
+                    SET data = json_set(data, '$.totalPages', ?2, '$.page', ?3)
+                    WHERE id = ?1 AND json_extract(data, '$.totalPages') = 1;
+                    ",
+                    params![progress_id, total_pages, page],
+                )
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        }
         tx.commit()
             .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        Ok((stale.len(), repaired))
+    }
+
+    fn reading_stats(&self, range: &ReadingStatsRange) -> Result<ReadingStats, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        reading_sessions::reading_stats(&conn, range)
+    }
+
+    fn comic_progress(&self, comic_id: Option<&str>) -> Result<Vec<ComicProgress>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        comic_progress::comic_progress(&conn, comic_id)
+    }
+
+    fn list_tags(&self) -> Result<Vec<TagSummary>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        tags::list_tags(&conn)
+    }
+
+    fn update_tag(
+        &self,
+        tag_id: &str,
+        name: Option<&str>,
+        aliases: Option<&[String]>,
+    ) -> Result<Option<TagSummary>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        tags::update_tag(&conn, tag_id, name, aliases)
+    }
+
+    fn comic_tags(&self, comic_id: &str) -> Result<Vec<ComicTag>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        tags::comic_tags(&conn, comic_id)
+    }
+
+    fn set_comic_user_tags(&self, comic_id: &str, names: &[String]) -> Result<(), AppError> {
+        let conn = open_connection(&self.db_path)?;
+        tags::set_comic_user_tags(&conn, comic_id, names)
+    }
 
-        Ok((updated, skipped))
+    fn filter_comics(&self, filter: &LibraryFilter) -> Result<Vec<DbRecord>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        library_filter::filter_comics(&conn, filter)
+    }
+
+    fn recently_read_comics(&self, limit: u32) -> Result<Vec<DbRecord>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        comic_progress::recently_read_comics(&conn, limit)
+    }
+
+    fn chapters_stored_in_folders(&self, folders: &[String]) -> Result<Vec<DbRecord>, AppError> {
+        let conn = open_connection(&self.db_path)?;
+        let folders =
+            serde_json::to_string(folders).map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let mut stmt = conn
+            .prepare(
+                "
+                SELECT id, data, created_at, updated_at
+                FROM chapters
+                WHERE substr(
+                  json_extract(data, '$.localPath'),
+                  1,
+                  instr(json_extract(data, '$.localPath') || '/', '/') - 1
+                ) IN (SELECT value FROM json_each(?1));
+                ",
+            )
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![folders], row_to_record)
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::infrastructure(e.to_string()))
     }
 }
 
@@ -414,6 +556,227 @@ fn open_connection(path: &Path) -> Result<Connection, AppError> {
     Ok(conn)
 }
 
+/// Inserts or replaces a document and returns its id. Read progress also extends the
+/// chapter's reading session, so callers run this in a transaction.
+fn upsert_document(
+    conn: &Connection,
+    table: Table,
+    id: Option<String>,
+    data: &Value,
+) -> Result<String, AppError> {
+    if !data.is_object() {
+        return Err(AppError::Validation(
+            "Expected data to be a JSON object".to_string(),
+        ));
+    }
+
+    let id = match (table, id) {
+        // read_progress: keep one row per chapter regardless of legacy ids.
+        (Table::ReadProgress, _) => {
+            let chapter_id = data
+                .get("chapterId")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| {
+                    AppError::Validation(
+                        "read_progress upsert requires a valid data.chapterId".to_string(),
+                    )
+                })?;
+
+            let existing_id: Option<String> = conn
+                .query_row(
+                    "SELECT id FROM read_progress
+                     WHERE json_extract(data, '$.chapterId') = ?1
+                     ORDER BY updated_at DESC
+                     LIMIT 1",
+                    params![chapter_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+            existing_id.unwrap_or(chapter_id)
+        }
+        (_, Some(existing_id)) => existing_id,
+        (_, None) => Uuid::new_v4().to_string(),
+    };
+    let table_name = table.as_str();
+
+    let payload =
+        serde_json::to_string(data).map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    if matches!(table, Table::ReadProgress) {
+        // The session is derived from the page stored before this update, so callers run
+        // this in a transaction.
+        let previous_page: Option<i64> = conn
+            .query_row(
+                "SELECT CAST(json_extract(data, '$.page') AS INTEGER) FROM read_progress WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?
+            .flatten();
+        let sql = format!(
+            "
+            INSERT INTO {table_name} (id, data, chapter_id, comic_id)
+            VALUES (
+              ?1,
+              json(?2),
+              json_extract(json(?2), '$.chapterId'),
+              json_extract(json(?2), '$.comicId')
+            )
+            ON CONFLICT(id) DO UPDATE SET
+              data = json(?2),
+              chapter_id = json_extract(json(?2), '$.chapterId'),
+              comic_id = json_extract(json(?2), '$.comicId'),
+              updated_at = ({timestamp});
+            ",
+            timestamp = TIMESTAMP_SQL
+        );
+
+        conn.execute(&sql, params![id, payload.clone()])
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        reading_sessions::record_progress(conn, data, previous_page, unix_millis_now())?;
+    } else {
+        let sql = format!(
+            "
+            INSERT INTO {table_name} (id, data)
+            VALUES (?1, json(?2))
+            ON CONFLICT(id) DO UPDATE SET
+              data = json(?2),
+              updated_at = ({timestamp});
+            ",
+            timestamp = TIMESTAMP_SQL
+        );
+        conn.execute(&sql, params![id, payload])
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        if matches!(table, Table::Comics) {
+            tags::sync_comic_genres(conn, &id, data)?;
+        }
+    }
+
+    Ok(id)
+}
+
+fn set_document_field(
+    conn: &Connection,
+    table: Table,
+    id: &str,
+    field: &str,
+    value: &Value,
+) -> Result<bool, AppError> {
+    let payload =
+        serde_json::to_string(value).map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let sql = format!(
+        "UPDATE {table_name}
+         SET data = json_set(data, ?2, json(?3)), updated_at = ({timestamp})
+         WHERE id = ?1;",
+        table_name = table.as_str(),
+        timestamp = TIMESTAMP_SQL
+    );
+    let affected = conn
+        .execute(&sql, params![id, format!("$.{field}"), payload])
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    Ok(affected > 0)
+}
+
+fn delete_document(conn: &Connection, table: Table, id: &str) -> Result<bool, AppError> {
+    let mut query = Query::delete();
+    query
+        .from_table(Alias::new(table.as_str()))
+        .and_where(Expr::col(Alias::new("id")).eq(id.to_string()));
+
+    let (sql, values) = query.build_rusqlite(SqliteQueryBuilder);
+    let params = values.as_params();
+    let affected = conn
+        .execute(&sql, params.as_slice())
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    Ok(affected > 0)
+}
+
+fn select_comic_chapters(
+    conn: &Connection,
+    selection: &ChapterSelection,
+) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT id, data FROM chapters WHERE comic_id = ?1;")
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map(params![selection.comic_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let mut selected = Vec::new();
+    for row in rows {
+        let (id, payload) = row.map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let data: Value =
+            serde_json::from_str(&payload).map_err(|e| AppError::infrastructure(e.to_string()))?;
+        if chapter_in_selection(selection, &data) {
+            selected.push(id);
+        }
+    }
+    Ok(selected)
+}
+
+/// The listed chapters followed by the ones `selection` picks, each once.
+fn chapters_to_mark(
+    conn: &Connection,
+    chapter_ids: &[String],
+    selection: Option<&ChapterSelection>,
+) -> Result<Vec<String>, AppError> {
+    let mut chapters = chapter_ids.to_vec();
+    if let Some(selection) = selection {
+        chapters.extend(select_comic_chapters(conn, selection)?);
+    }
+    let mut seen = HashSet::new();
+    chapters.retain(|chapter_id| seen.insert(chapter_id.clone()));
+    Ok(chapters)
+}
+
+/// Page count stored with the chapter's latest progress, if any.
+fn latest_total_pages(conn: &Connection, chapter_id: &str) -> Result<Option<i64>, AppError> {
+    conn.query_row(
+        "
+        SELECT json_extract(data, '$.totalPages')
+        FROM read_progress
+        WHERE chapter_id = ?1
+        ORDER BY updated_at DESC
+        LIMIT 1;
+        ",
+        params![chapter_id],
+        |row| row.get::<_, Option<i64>>(0),
+    )
+    .optional()
+    .map(Option::flatten)
+    .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+fn select_chapter(conn: &Connection, chapter_id: &str) -> Result<Option<DbRecord>, AppError> {
+    conn.query_row(
+        "
+        SELECT id, data, created_at, updated_at
+        FROM chapters
+        WHERE id = ?1
+        LIMIT 1;
+        ",
+        params![chapter_id],
+        row_to_record,
+    )
+    .optional()
+    .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+fn unix_millis_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
+
 fn row_to_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<DbRecord> {
     let payload: String = row.get(1)?;
     let data = serde_json::from_str(&payload).map_err(|e| {
@@ -430,3 +793,172 @@ fn row_to_record(row: &rusqlite::Row<'_>) -> rusqlite::Result<DbRecord> {
         updated_at: row.get(3)?,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::Cell;
+
+    fn store_with_chapters(dir: &Path) -> SqliteDocumentStore {
+        let store = SqliteDocumentStore::initialize(dir, &dir.join("comics")).expect("store");
+        store
+            .upsert(
+                Table::Comics,
+                Some("comic-1".to_string()),
+                json!({ "name": "Pablo" }),
+            )
+            .expect("comic");
+        for number in ["1", "2"] {
+            store
+                .upsert(
+                    Table::Chapters,
+                    Some(format!("c-{number}")),
+                    json!({ "comicId": "comic-1", "number": number }),
+                )
+                .expect("chapter");
+        }
+        store
+    }
+
+    fn progress(store: &SqliteDocumentStore, chapter_id: &str) -> Value {
+        store
+            .get(Table::ReadProgress, chapter_id)
+            .expect("read progress")
+            .expect("progress row")
+            .data
+    }
+
+    #[test]
+    fn counts_pages_only_when_marking_chapters_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_chapters(dir.path());
+        let counted = Cell::new(0);
+        let page_count = |_: &DbRecord| {
+            counted.set(counted.get() + 1);
+            Some(20)
+        };
+
+        let marked = store
+            .mark_chapters_read_state(
+                &["c-1".to_string(), "missing".to_string()],
+                None,
+                true,
+                &page_count,
+            )
+            .expect("mark read");
+        assert_eq!(marked, (1, 1));
+        assert_eq!(counted.get(), 1);
+        assert_eq!(progress(&store, "c-1")["page"], 20);
+        assert_eq!(progress(&store, "c-1")["totalPages"], 20);
+
+        let selection = ChapterSelection {
+            comic_id: "comic-1".to_string(),
+            from_number: None,
+            up_to_number: None,
+            language: None,
+        };
+        let marked = store
+            .mark_chapters_read_state(&[], Some(&selection), false, &page_count)
+            .expect("mark unread");
+        assert_eq!(marked, (2, 0));
+        assert_eq!(counted.get(), 1);
+        assert_eq!(progress(&store, "c-1")["page"], 0);
+        assert_eq!(progress(&store, "c-1")["totalPages"], 20);
+        assert_eq!(progress(&store, "c-2")["page"], 0);
+        assert!(progress(&store, "c-2").get("totalPages").is_none());
+    }
+
+    #[test]
+    fn repairs_progress_stuck_at_one_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_chapters(dir.path());
+        for (chapter_id, page) in [("c-1", 1), ("c-2", 0)] {
+            store
+                .upsert(
+                    Table::ReadProgress,
+                    None,
+                    json!({
+                        "chapterId": chapter_id,
+                        "comicId": "comic-1",
+                        "page": page,
+                        "totalPages": 1
+                    }),
+                )
+                .expect("progress");
+        }
+
+        let repaired = store
+            .repair_progress_page_counts(&|chapter| (chapter.id == "c-1").then_some(18))
+            .expect("repair");
+        assert_eq!(repaired, (2, 1));
+        assert_eq!(progress(&store, "c-1")["page"], 18);
+        assert_eq!(progress(&store, "c-1")["totalPages"], 18);
+        assert_eq!(progress(&store, "c-2")["totalPages"], 1);
+    }
+
+    #[test]
+    fn writes_a_batch_in_one_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_chapters(dir.path());
+
+        let failed = store.write_batch(&[
+            DocumentWrite::Delete {
+                table: Table::Chapters,
+                id: "c-1".to_string(),
+            },
+            DocumentWrite::Upsert {
+                table: Table::ReadProgress,
+                id: None,
+                data: json!({ "comicId": "comic-1" }),
+            },
+        ]);
+        assert!(failed.is_err());
+        assert!(store.get(Table::Chapters, "c-1").unwrap().is_some());
+
+        let written = store
+            .write_batch(&[
+                DocumentWrite::Delete {
+                    table: Table::Chapters,
+                    id: "c-1".to_string(),
+                },
+                DocumentWrite::Upsert {
+                    table: Table::Chapters,
+                    id: Some("c-2".to_string()),
+                    data: json!({ "comicId": "comic-1", "number": "1" }),
+                },
+            ])
+            .expect("batch");
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[0].data["number"], "1");
+        assert!(store.get(Table::Chapters, "c-1").unwrap().is_none());
+    }
+
+    #[test]
+    fn finds_chapters_by_their_stored_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_chapters(dir.path());
+        store
+            .set_json_field(
+                Table::Chapters,
+                "c-1",
+                "localPath",
+                &json!("Old Name/Chapter 1.cbz"),
+            )
+            .unwrap();
+        store
+            .set_json_field(Table::Chapters, "c-2", "localPath", &json!("Old Name (2)"))
+            .unwrap();
+
+        let chapters = store
+            .chapters_stored_in_folders(&["Old Name".to_string()])
+            .unwrap();
+        assert_eq!(
+            chapters
+                .iter()
+                .map(|chapter| chapter.id.as_str())
+                .collect::<Vec<_>>(),
+            ["c-1"]
+        );
+    }
+}
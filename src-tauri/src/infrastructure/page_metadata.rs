@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use imagesize::{Compression, ImageType};
+
+use crate::{
+    domain::AppError,
+    infrastructure::archive::{ArchiveEntry, ArchiveReader},
+};
+
+/// Width-to-height ratio above which a page is most likely a double-page spread.
+pub const SPREAD_ASPECT_RATIO: f64 = 1.2;
+/// Bytes read from the start of a page to find its dimensions. JPEG frame headers can sit
+/// behind large EXIF blocks, so this is generous.
+const PAGE_HEADER_BYTES: u64 = 256 * 1024;
+/// Archives whose page metadata is kept in memory; the least recently used go first.
+const CACHED_ARCHIVES: usize = 256;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageMetadata {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub byte_size: u64,
+    pub mime_type: String,
+    pub is_spread: bool,
+}
+
+impl PageMetadata {
+    /// Reads dimensions and type from the image header. Unreadable headers still yield the
+    /// byte size and a MIME type guessed from the file name.
+    pub fn from_bytes(file_name: &str, bytes: &[u8]) -> Self {
+        Self::from_header(file_name, bytes, bytes.len() as u64)
+    }
+
+    /// Like [`Self::from_bytes`], from the first bytes of an image of `byte_size` bytes.
+    pub fn from_header(file_name: &str, header: &[u8], byte_size: u64) -> Self {
+        let size = imagesize::blob_size(header).ok();
+        let image_type = imagesize::image_type(header).ok();
+        Self::new(
+            file_name,
+            byte_size,
+            size.map(|size| (size.width, size.height)),
+            image_type,
+        )
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, AppError> {
+        let byte_size = fs::metadata(path)
+            .map_err(|e| AppError::infrastructure(format!("Failed to read page metadata: {e}")))?
+            .len();
+        let size = imagesize::size(path).ok();
+        let image_type = File::open(path)
+            .ok()
+            .and_then(|file| imagesize::reader_type(BufReader::new(file)).ok());
+        Ok(Self::new(
+            &path.to_string_lossy(),
+            byte_size,
+            size.map(|size| (size.width, size.height)),
+            image_type,
+        ))
+    }
+
+    fn unknown(file_name: &str, byte_size: u64) -> Self {
+        Self::new(file_name, byte_size, None, None)
+    }
+
+    fn new(
+        file_name: &str,
+        byte_size: u64,
+        dimensions: Option<(usize, usize)>,
+        image_type: Option<ImageType>,
+    ) -> Self {
+        let dimensions = dimensions
+            .and_then(|(width, height)| {
+                Some((u32::try_from(width).ok()?, u32::try_from(height).ok()?))
+            })
+            .filter(|(width, height)| *width > 0 && *height > 0);
+        let mime_type = image_type
+            .and_then(mime_for_image_type)
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                mime_guess::from_path(file_name)
+                    .first_or_octet_stream()
+                    .to_string()
+            });
+
+        Self {
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+            byte_size,
+            mime_type,
+            is_spread: dimensions
+                .map(|(width, height)| width as f64 / height as f64 >= SPREAD_ASPECT_RATIO)
+                .unwrap_or(false),
+        }
+    }
+}
+
+fn mime_for_image_type(image_type: ImageType) -> Option<&'static str> {
+    match image_type {
+        ImageType::Jpeg => Some("image/jpeg"),
+        ImageType::Png => Some("image/png"),
+        ImageType::Gif => Some("image/gif"),
+        ImageType::Webp => Some("image/webp"),
+        ImageType::Bmp => Some("image/bmp"),
+        ImageType::Tiff => Some("image/tiff"),
+        ImageType::Jxl => Some("image/jxl"),
+        ImageType::Heif(Compression::Av1) => Some("image/avif"),
+        ImageType::Heif(_) => Some("image/heif"),
+        _ => None,
+    }
+}
+
+/// Reads metadata for every page of an archive, in the order of `entries`. Only the start of
+/// each page is read.
+pub fn read_archive_page_metadata(
+    reader: &dyn ArchiveReader,
+    entries: &[ArchiveEntry],
+) -> Vec<PageMetadata> {
+    entries
+        .iter()
+        .map(|entry| {
+            let metadata = match reader.file_path(entry) {
+                Some(path) => PageMetadata::from_file(&path).ok(),
+                None => reader
+                    .read_entry_prefix(entry, PAGE_HEADER_BYTES)
+                    .ok()
+                    .map(|header| PageMetadata::from_header(&entry.file_name, &header, entry.size)),
+            };
+            metadata.unwrap_or_else(|| PageMetadata::unknown(&entry.file_name, entry.size))
+        })
+        .collect()
+}
+
+/// Remembers page metadata per archive path. An entry is reused until the archive's
+/// modification time or size changes, and at most [`CACHED_ARCHIVES`] archives are kept.
+#[derive(Clone, Default)]
+pub struct PageMetadataCache {
+    archives: Arc<Mutex<CachedArchives>>,
+}
+
+#[derive(Default)]
+struct CachedArchives {
+    entries: HashMap<PathBuf, CachedArchive>,
+    /// Bumped on every hit or insert; the entry with the lowest stamp is evicted first.
+    clock: u64,
+}
+
+struct CachedArchive {
+    fingerprint: (Option<SystemTime>, u64),
+    pages: Arc<Vec<PageMetadata>>,
+    last_used: u64,
+}
+
+impl PageMetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_read(
+        &self,
+        archive_path: &Path,
+        reader: &dyn ArchiveReader,
+        entries: &[ArchiveEntry],
+    ) -> Arc<Vec<PageMetadata>> {
+        let fingerprint = archive_fingerprint(archive_path);
+        if let Ok(mut archives) = self.archives.lock() {
+            archives.clock += 1;
+            let clock = archives.clock;
+            if let Some(cached) = archives.entries.get_mut(archive_path) {
+                if cached.fingerprint == fingerprint && cached.pages.len() == entries.len() {
+                    cached.last_used = clock;
+                    return cached.pages.clone();
+                }
+            }
+        }
+
+        let pages = Arc::new(read_archive_page_metadata(reader, entries));
+        if let Ok(mut archives) = self.archives.lock() {
+            archives.clock += 1;
+            let last_used = archives.clock;
+            archives.entries.insert(
+                archive_path.to_path_buf(),
+                CachedArchive {
+                    fingerprint,
+                    pages: pages.clone(),
+                    last_used,
+                },
+            );
+            while archives.entries.len() > CACHED_ARCHIVES {
+                let Some(oldest) = archives
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.last_used)
+                    .map(|(path, _)| path.clone())
+                else {
+                    break;
+                };
+                archives.entries.remove(&oldest);
+            }
+        }
+        pages
+    }
+}
+
+fn archive_fingerprint(path: &Path) -> (Option<SystemTime>, u64) {
+    fs::metadata(path)
+        .map(|metadata| (metadata.modified().ok(), metadata.len()))
+        .unwrap_or((None, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageFormat, RgbImage};
+    use std::io::Cursor;
+
+    fn sample_png(width: u32, height: u32) -> Vec<u8> {
+        let mut output = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut output, ImageFormat::Png)
+            .expect("encode sample");
+        output.into_inner()
+    }
+
+    #[test]
+    fn reads_dimensions_and_flags_spreads() {
+        let single = PageMetadata::from_bytes("001.jpg", &sample_png(800, 1200));
+        assert_eq!(single.width, Some(800));
+        assert_eq!(single.height, Some(1200));
+        assert_eq!(single.mime_type, "image/png");
+        assert!(!single.is_spread);
+
+        let spread = PageMetadata::from_bytes("002.png", &sample_png(1600, 1200));
+        assert!(spread.is_spread);
+    }
+
+    #[test]
+    fn reads_dimensions_from_a_header_prefix() {
+        let page = sample_png(1600, 1200);
+        let metadata = PageMetadata::from_header("001.png", &page[..64], 90_000);
+        assert_eq!(metadata.width, Some(1600));
+        assert_eq!(metadata.byte_size, 90_000);
+        assert!(metadata.is_spread);
+    }
+
+    #[test]
+    fn falls_back_to_file_name_for_unreadable_headers() {
+        let metadata = PageMetadata::from_bytes("003.webp", b"not an image");
+        assert_eq!(metadata.width, None);
+        assert_eq!(metadata.byte_size, 12);
+        assert_eq!(metadata.mime_type, "image/webp");
+        assert!(!metadata.is_spread);
+    }
+}
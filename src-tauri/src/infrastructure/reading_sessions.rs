@@ -0,0 +1,399 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::tags::GENRE_SOURCE;
+use crate::{
+    application::page_number,
+    domain::{
+        AppError, ReadingComicStat, ReadingDay, ReadingGenreStat, ReadingStats, ReadingStatsRange,
+    },
+};
+
+/// Progress updates closer together than this extend the same session.
+const SESSION_IDLE_GAP_MS: i64 = 30 * 60 * 1000;
+/// Optional read_progress field naming the device the progress was made on.
+const DEVICE_FIELD: &str = "device";
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// Extends the chapter's open session on the same device with a new progress value, or
+/// opens a session when the reader has been idle. `previous_page` is the page stored before
+/// this update; without one, the update only opens the chapter and reading starts counting
+/// from the first page turn.
+pub(super) fn record_progress(
+    conn: &Connection,
+    progress: &Value,
+    previous_page: Option<i64>,
+    now: i64,
+) -> Result<(), AppError> {
+    let (Some(chapter_id), Some(page)) = (
+        progress.get("chapterId").and_then(Value::as_str),
+        page_number(progress.get("page")),
+    ) else {
+        return Ok(());
+    };
+    let device = progress
+        .get(DEVICE_FIELD)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let previous_page = previous_page.unwrap_or(page);
+    let advanced = (page - previous_page).max(0);
+    let completed = page_number(progress.get("totalPages")).is_some_and(|total| page >= total);
+
+    let open: Option<(String, String)> = conn
+        .query_row(
+            "
+            SELECT id, data
+            FROM reading_sessions
+            WHERE chapter_id = ?1
+              AND ended_at >= ?2
+              AND COALESCE(json_extract(data, '$.device'), '') = ?3
+            ORDER BY ended_at DESC
+            LIMIT 1;
+            ",
+            params![
+                chapter_id,
+                now - SESSION_IDLE_GAP_MS,
+                device.unwrap_or_default()
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let (id, session) = match open {
+        Some((id, payload)) => {
+            let mut session: Value = serde_json::from_str(&payload)
+                .map_err(|e| AppError::infrastructure(e.to_string()))?;
+            let pages_read = page_number(session.get("pagesRead")).unwrap_or(0) + advanced;
+            let completed = completed || session["completed"].as_bool().unwrap_or(false);
+            session["endPage"] = json!(page);
+            session["endedAt"] = json!(now);
+            session["pagesRead"] = json!(pages_read);
+            session["completed"] = json!(completed);
+            (id, session)
+        }
+        // Reopening a chapter without turning a page is not reading.
+        None if page == previous_page => return Ok(()),
+        None => {
+            let comic_id = match progress.get("comicId").and_then(Value::as_str) {
+                Some(comic_id) if !comic_id.is_empty() => Some(comic_id.to_string()),
+                _ => conn
+                    .query_row(
+                        "SELECT comic_id FROM chapters WHERE id = ?1 LIMIT 1;",
+                        params![chapter_id],
+                        |row| row.get::<_, Option<String>>(0),
+                    )
+                    .optional()
+                    .map_err(|e| AppError::infrastructure(e.to_string()))?
+                    .flatten(),
+            };
+            let session = json!({
+                "chapterId": chapter_id,
+                "comicId": comic_id,
+                "device": device,
+                "startPage": previous_page,
+                "endPage": page,
+                "pagesRead": advanced,
+                "completed": completed,
+                "startedAt": now,
+                "endedAt": now
+            });
+            (Uuid::new_v4().to_string(), session)
+        }
+    };
+
+    let payload =
+        serde_json::to_string(&session).map_err(|e| AppError::infrastructure(e.to_string()))?;
+    conn.execute(
+        "
+        INSERT INTO reading_sessions (id, data)
+        VALUES (?1, json(?2))
+        ON CONFLICT(id) DO UPDATE SET
+          data = json(?2),
+          updated_at = (strftime('%Y-%m-%dT%H:%M:%fZ','now'));
+        ",
+        params![id, payload],
+    )
+    .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    Ok(())
+}
+
+/// Pages, finished chapters and time read per local day, with streaks and the comics and
+/// genres read the most over the range.
+pub(super) fn reading_stats(
+    conn: &Connection,
+    range: &ReadingStatsRange,
+) -> Result<ReadingStats, AppError> {
+    for date in [&range.from, &range.to].into_iter().flatten() {
+        if !is_iso_date(date) {
+            return Err(AppError::Validation(format!(
+                "Expected a YYYY-MM-DD date, got {date}"
+            )));
+        }
+    }
+
+    let offset = format!("{:+} minutes", range.utc_offset_minutes);
+    let (from, to, to_day): (String, String, i64) = conn
+        .query_row(
+            "
+            SELECT
+              COALESCE(?1, date('now', ?3, ?4)),
+              COALESCE(?2, date('now', ?3)),
+              CAST(julianday(COALESCE(?2, date('now', ?3))) AS INTEGER);
+            ",
+            params![
+                range.from,
+                range.to,
+                offset,
+                format!("-{} days", DEFAULT_RANGE_DAYS - 1)
+            ],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    if from > to {
+        return Err(AppError::Validation(format!(
+            "The range starts after it ends: {from} > {to}"
+        )));
+    }
+
+    let mut day_stmt = conn
+        .prepare(
+            "
+            SELECT
+              date(started_at / 1000, 'unixepoch', ?1) AS day,
+              CAST(julianday(date(started_at / 1000, 'unixepoch', ?1)) AS INTEGER),
+              CAST(TOTAL(json_extract(data, '$.pagesRead')) AS INTEGER),
+              COUNT(DISTINCT CASE WHEN json_extract(data, '$.completed') THEN chapter_id END),
+              CAST(TOTAL(ended_at - started_at) / 1000 AS INTEGER)
+            FROM reading_sessions
+            WHERE day BETWEEN ?2 AND ?3
+            GROUP BY day
+            ORDER BY day;
+            ",
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = day_stmt
+        .query_map(params![offset, from, to], |row| {
+            Ok((
+                row.get::<_, i64>(1)?,
+                ReadingDay {
+                    date: row.get(0)?,
+                    pages_read: row.get(2)?,
+                    chapters_read: row.get(3)?,
+                    seconds_read: row.get(4)?,
+                },
+            ))
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let mut day_numbers = Vec::new();
+    let mut days = Vec::new();
+    for row in rows {
+        let (day_number, day) = row.map_err(|e| AppError::infrastructure(e.to_string()))?;
+        day_numbers.push(day_number);
+        days.push(day);
+    }
+    let (current_streak, longest_streak) = streaks(&day_numbers, to_day);
+
+    let chapters_read: i64 = conn
+        .query_row(
+            "
+            SELECT COUNT(DISTINCT chapter_id)
+            FROM reading_sessions
+            WHERE json_extract(data, '$.completed')
+              AND date(started_at / 1000, 'unixepoch', ?1) BETWEEN ?2 AND ?3;
+            ",
+            params![offset, from, to],
+            |row| row.get(0),
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let mut comic_stmt = conn
+        .prepare(
+            "
+            SELECT
+              s.comic_id,
+              json_extract(c.data, '$.name'),
+              CAST(TOTAL(json_extract(s.data, '$.pagesRead')) AS INTEGER) AS pages,
+              COUNT(DISTINCT CASE WHEN json_extract(s.data, '$.completed') THEN s.chapter_id END),
+              CAST(TOTAL(s.ended_at - s.started_at) / 1000 AS INTEGER) AS seconds
+            FROM reading_sessions s
+            LEFT JOIN comics c ON c.id = s.comic_id
+            WHERE s.comic_id IS NOT NULL
+              AND date(s.started_at / 1000, 'unixepoch', ?1) BETWEEN ?2 AND ?3
+            GROUP BY s.comic_id
+            ORDER BY seconds DESC, pages DESC;
+            ",
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = comic_stmt
+        .query_map(params![offset, from, to], |row| {
+            Ok(ReadingComicStat {
+                comic_id: row.get(0)?,
+                name: row.get(1)?,
+                pages_read: row.get(2)?,
+                chapters_read: row.get(3)?,
+                seconds_read: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let mut top_comics = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    top_comics.truncate(range.top_limit);
+
+    // Genres are the tags the comics' genre links point at, like the library's tag filters.
+    let mut genre_stmt = conn
+        .prepare(
+            "
+            WITH comic_reading AS (
+              SELECT
+                comic_id,
+                CAST(TOTAL(json_extract(data, '$.pagesRead')) AS INTEGER) AS pages,
+                CAST(TOTAL(ended_at - started_at) / 1000 AS INTEGER) AS seconds
+              FROM reading_sessions
+              WHERE comic_id IS NOT NULL
+                AND date(started_at / 1000, 'unixepoch', ?1) BETWEEN ?2 AND ?3
+              GROUP BY comic_id
+            )
+            SELECT
+              COALESCE(json_extract(t.data, '$.name'), t.id) AS genre,
+              SUM(r.pages) AS pages,
+              SUM(r.seconds) AS seconds,
+              COUNT(DISTINCT r.comic_id)
+            FROM comic_reading r
+            JOIN comic_tags ct ON ct.comic_id = r.comic_id AND ct.source = ?4
+            JOIN tags t ON t.id = ct.tag_id
+            GROUP BY t.id
+            ORDER BY seconds DESC, pages DESC, genre
+            LIMIT ?5;
+            ",
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = genre_stmt
+        .query_map(
+            params![offset, from, to, GENRE_SOURCE, range.top_limit as i64],
+            |row| {
+                Ok(ReadingGenreStat {
+                    genre: row.get(0)?,
+                    pages_read: row.get(1)?,
+                    seconds_read: row.get(2)?,
+                    comic_count: row.get(3)?,
+                })
+            },
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let top_genres = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    Ok(ReadingStats {
+        from,
+        to,
+        pages_read: days.iter().map(|day| day.pages_read).sum(),
+        chapters_read,
+        seconds_read: days.iter().map(|day| day.seconds_read).sum(),
+        current_streak,
+        longest_streak,
+        days,
+        top_comics,
+        top_genres,
+    })
+}
+
+/// Current and longest runs of consecutive days in `days` (sorted day numbers). The current
+/// run may end on `today` or the day before.
+fn streaks(days: &[i64], today: i64) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous = None;
+    for &day in days {
+        run = if previous == Some(day - 1) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+    let current = match previous {
+        Some(last) if last >= today - 1 => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+fn is_iso_date(value: &str) -> bool {
+    let bytes = value.as_bytes();
+    bytes.len() == 10
+        && bytes.iter().enumerate().all(|(index, byte)| match index {
+            4 | 7 => *byte == b'-',
+            _ => byte.is_ascii_digit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::{
+        migrations::{MigrationRunner, SqliteMigrationRunner},
+        tags::sync_comic_genres,
+    };
+
+    #[test]
+    fn groups_progress_into_sessions_and_days() {
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        conn.execute(
+            r#"INSERT INTO comics (id, data) VALUES ('comic-1', json('{"name":"Pablo","genres":"Action, Drama, acción"}'))"#,
+            [],
+        )
+        .expect("insert comic");
+        sync_comic_genres(
+            &conn,
+            "comic-1",
+            &json!({ "genres": "Action, Drama, acción" }),
+        )
+        .expect("link genres");
+
+        // 2026-03-01T10:00:00Z, then five minutes later, then the next day.
+        let start = 1_772_359_200_000;
+        let progress = |page: i64| json!({ "chapterId": "c-1", "comicId": "comic-1", "page": page, "totalPages": 20 });
+        let sessions = || -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM reading_sessions;", [], |row| {
+                row.get(0)
+            })
+            .expect("count sessions")
+        };
+        // Opening the chapter is not reading; the first page turn is.
+        record_progress(&conn, &progress(1), None, start).expect("open chapter");
+        assert_eq!(sessions(), 0);
+        record_progress(&conn, &progress(5), Some(1), start).expect("first update");
+        record_progress(&conn, &progress(12), Some(5), start + 300_000).expect("second update");
+        record_progress(&conn, &progress(20), Some(12), start + 86_400_000).expect("next day");
+        assert_eq!(sessions(), 2);
+
+        let stats = reading_stats(
+            &conn,
+            &ReadingStatsRange {
+                from: Some("2026-03-01".to_string()),
+                to: Some("2026-03-03".to_string()),
+                utc_offset_minutes: 0,
+                top_limit: 10,
+            },
+        )
+        .expect("stats");
+        assert_eq!(stats.pages_read, 19);
+        assert_eq!(stats.chapters_read, 1);
+        assert_eq!(stats.seconds_read, 300);
+        assert_eq!(stats.days.len(), 2);
+        assert_eq!(stats.days[0].pages_read, 11);
+        assert_eq!((stats.current_streak, stats.longest_streak), (2, 2));
+        assert_eq!(stats.top_comics[0].name.as_deref(), Some("Pablo"));
+        assert_eq!(stats.top_genres.len(), 2);
+        assert_eq!(stats.top_genres[0].comic_count, 1);
+    }
+}
@@ -0,0 +1,72 @@
+use std::{fs, path::Path};
+
+use crate::domain::AppError;
+
+/// Size in bytes of a file, or of every file below a folder. Symlinks are counted as links,
+/// never followed, so nothing outside the folder is measured.
+pub fn disk_usage(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| disk_usage(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Moves a file or folder to the system trash, where the user can still restore it.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn move_to_trash(path: &Path) -> Result<(), AppError> {
+    trash::delete(path).map_err(|e| AppError::infrastructure(format!("Failed to trash: {e}")))
+}
+
+#[cfg(any(target_os = "android", target_os = "ios"))]
+pub fn move_to_trash(path: &Path) -> Result<(), AppError> {
+    Err(AppError::Validation(format!(
+        "There is no trash on this platform to move {} to",
+        path.display()
+    )))
+}
+
+/// Permanently removes a file or a whole folder.
+pub fn delete_path(path: &Path) -> Result<(), AppError> {
+    let result = if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| AppError::infrastructure(format!("Failed to delete: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("Pablo").join("Chapter 1")).unwrap();
+        fs::write(root.join("Pablo").join("cover.jpg"), [0u8; 10]).unwrap();
+        fs::write(
+            root.join("Pablo").join("Chapter 1").join("001.jpg"),
+            [0u8; 32],
+        )
+        .unwrap();
+
+        assert_eq!(disk_usage(root), 42);
+        assert_eq!(disk_usage(&root.join("Pablo").join("cover.jpg")), 10);
+        assert_eq!(disk_usage(&root.join("missing")), 0);
+
+        delete_path(&root.join("Pablo")).unwrap();
+        assert_eq!(disk_usage(root), 0);
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+
+use crate::{
+    application::{canonical_tag, genre_names, tag_slug, CanonicalTag},
+    domain::{AppError, ComicTag, TagSummary},
+};
+
+/// Links a comic got from its `genres` field.
+pub(super) const GENRE_SOURCE: &str = "genre";
+/// Links added by hand.
+const USER_SOURCE: &str = "user";
+
+/// Points a comic's genre links at the tags its current `genres` value resolves to, creating
+/// tags that do not exist yet. Tags added by hand are left alone.
+pub(super) fn sync_comic_genres(
+    conn: &Connection,
+    comic_id: &str,
+    comic: &Value,
+) -> Result<(), AppError> {
+    let aliases = tag_aliases(conn)?;
+    let tags = genre_names(comic.get("genres").unwrap_or(&Value::Null))
+        .iter()
+        .filter_map(|name| canonical_tag(name, &aliases))
+        .collect::<Vec<_>>();
+    replace_comic_tags(conn, comic_id, &tags, GENRE_SOURCE)
+}
+
+/// Builds genre links for every comic already in the library.
+pub(super) fn backfill_genre_tags(conn: &Connection) -> Result<(), AppError> {
+    let comics = {
+        let mut stmt = conn
+            .prepare("SELECT id, data FROM comics;")
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    for (comic_id, data) in comics {
+        let comic = serde_json::from_str::<Value>(&data).unwrap_or(Value::Null);
+        sync_comic_genres(&tx, &comic_id, &comic)?;
+    }
+    tx.commit()
+        .map_err(|e| AppError::infrastructure(e.to_string()))
+}
+
+/// Replaces the tags added to a comic by hand.
+pub(super) fn set_comic_user_tags(
+    conn: &Connection,
+    comic_id: &str,
+    names: &[String],
+) -> Result<(), AppError> {
+    let aliases = tag_aliases(conn)?;
+    let tags = names
+        .iter()
+        .filter_map(|name| canonical_tag(name, &aliases))
+        .collect::<Vec<_>>();
+    replace_comic_tags(conn, comic_id, &tags, USER_SOURCE)
+}
+
+/// Every tag with the number of comics carrying it, most used first.
+pub(super) fn list_tags(conn: &Connection) -> Result<Vec<TagSummary>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "
+            SELECT t.id, t.data, COUNT(DISTINCT ct.comic_id)
+            FROM tags t
+            LEFT JOIN comic_tags ct ON ct.tag_id = t.id
+            GROUP BY t.id
+            ORDER BY COUNT(DISTINCT ct.comic_id) DESC, t.id;
+            ",
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let mut tags = Vec::new();
+    for row in rows {
+        let (id, data, comic_count) = row.map_err(|e| AppError::infrastructure(e.to_string()))?;
+        tags.push(tag_summary(id, &data, comic_count));
+    }
+    Ok(tags)
+}
+
+/// Renames a tag and replaces its aliases. Other tags an alias names are merged into this
+/// one: their comics move over and they are deleted.
+pub(super) fn update_tag(
+    conn: &Connection,
+    tag_id: &str,
+    name: Option<&str>,
+    aliases: Option<&[String]>,
+) -> Result<Option<TagSummary>, AppError> {
+    let exists = conn
+        .query_row("SELECT 1 FROM tags WHERE id = ?1;", params![tag_id], |_| {
+            Ok(())
+        })
+        .optional()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?
+        .is_some();
+    if !exists {
+        return Ok(None);
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    if let Some(name) = name.map(str::trim).filter(|name| !name.is_empty()) {
+        tx.execute(
+            "UPDATE tags SET data = json_set(data, '$.name', ?2) WHERE id = ?1;",
+            params![tag_id, name],
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    }
+    if let Some(aliases) = aliases {
+        let aliases = aliases
+            .iter()
+            .map(|alias| alias.trim().to_string())
+            .filter(|alias| !tag_slug(alias).is_empty() && tag_slug(alias) != tag_id)
+            .collect::<Vec<_>>();
+        for alias in &aliases {
+            merge_tag(&tx, &tag_slug(alias), tag_id)?;
+        }
+        tx.execute(
+            "UPDATE tags SET data = json_set(data, '$.aliases', json(?2)) WHERE id = ?1;",
+            params![tag_id, Value::from(aliases).to_string()],
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    }
+    tx.commit()
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    Ok(list_tags(conn)?.into_iter().find(|tag| tag.id == tag_id))
+}
+
+pub(super) fn comic_tags(conn: &Connection, comic_id: &str) -> Result<Vec<ComicTag>, AppError> {
+    let mut stmt = conn
+        .prepare(
+            "
+            SELECT t.id, t.data, group_concat(DISTINCT ct.source)
+            FROM comic_tags ct
+            JOIN tags t ON t.id = ct.tag_id
+            WHERE ct.comic_id = ?1
+            GROUP BY t.id
+            ORDER BY json_extract(t.data, '$.name') COLLATE NOCASE;
+            ",
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map(params![comic_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let mut tags = Vec::new();
+    for row in rows {
+        let (id, data, sources) = row.map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let summary = tag_summary(id, &data, 0);
+        let mut sources = sources
+            .unwrap_or_default()
+            .split(',')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        sources.sort();
+        tags.push(ComicTag {
+            id: summary.id,
+            name: summary.name,
+            kind: summary.kind,
+            sources,
+        });
+    }
+    Ok(tags)
+}
+
+fn replace_comic_tags(
+    conn: &Connection,
+    comic_id: &str,
+    tags: &[CanonicalTag],
+    source: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM comic_tags WHERE comic_id = ?1 AND source = ?2;",
+        params![comic_id, source],
+    )
+    .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    for tag in tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (id, data) VALUES (?1, json(?2));",
+            params![
+                tag.id,
+                json!({ "name": tag.name, "kind": source, "aliases": [] }).to_string()
+            ],
+        )
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        link_comic(conn, comic_id, &tag.id, source)?;
+    }
+    Ok(())
+}
+
+fn link_comic(
+    conn: &Connection,
+    comic_id: &str,
+    tag_id: &str,
+    source: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO comic_tags (id, data) VALUES (?1, json(?2));",
+        params![
+            format!("{source}:{tag_id}@{comic_id}"),
+            json!({ "comicId": comic_id, "tagId": tag_id, "source": source }).to_string()
+        ],
+    )
+    .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    Ok(())
+}
+
+/// Moves the comics of tag `from` to tag `into` and deletes `from`, with its own aliases
+/// carried over.
+fn merge_tag(conn: &Connection, from: &str, into: &str) -> Result<(), AppError> {
+    let links = {
+        let mut stmt = conn
+            .prepare("SELECT comic_id, source FROM comic_tags WHERE tag_id = ?1;")
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let rows = stmt
+            .query_map(params![from], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| AppError::infrastructure(e.to_string()))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::infrastructure(e.to_string()))?
+    };
+    for (comic_id, source) in links {
+        link_comic(conn, &comic_id, into, &source)?;
+    }
+    conn.execute("DELETE FROM tags WHERE id = ?1;", params![from])
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    Ok(())
+}
+
+/// Slugs of every stored tag name and alias, mapped to the tag they belong to.
+pub(super) fn tag_aliases(conn: &Connection) -> Result<HashMap<String, String>, AppError> {
+    let mut stmt = conn
+        .prepare("SELECT id, data FROM tags;")
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| AppError::infrastructure(e.to_string()))?;
+
+    let mut aliases = HashMap::new();
+    for row in rows {
+        let (id, data) = row.map_err(|e| AppError::infrastructure(e.to_string()))?;
+        let summary = tag_summary(id, &data, 0);
+        for name in summary.aliases.iter().chain([&summary.name]) {
+            aliases.insert(tag_slug(name), summary.id.clone());
+        }
+    }
+    Ok(aliases)
+}
+
+fn tag_summary(id: String, data: &str, comic_count: i64) -> TagSummary {
+    let data = serde_json::from_str::<Value>(data).unwrap_or(Value::Null);
+    let text = |key: &str| data.get(key).and_then(Value::as_str).map(str::to_string);
+    TagSummary {
+        name: text("name").unwrap_or_else(|| id.clone()),
+        kind: text("kind").unwrap_or_else(|| GENRE_SOURCE.to_string()),
+        aliases: data
+            .get("aliases")
+            .and_then(Value::as_array)
+            .map(|aliases| {
+                aliases
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        id,
+        comic_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{DbRecord, LibraryFilter},
+        infrastructure::{
+            library_filter::filter_comics,
+            migrations::{MigrationRunner, SqliteMigrationRunner},
+        },
+    };
+
+    fn ids(comics: Vec<DbRecord>) -> Vec<String> {
+        comics.into_iter().map(|comic| comic.id).collect()
+    }
+
+    #[test]
+    fn filters_comics_by_normalized_tags() {
+        let conn = Connection::open_in_memory().expect("open memory db");
+        SqliteMigrationRunner::new()
+            .run(&conn)
+            .expect("run migrations");
+        for (id, genres) in [
+            ("comic-1", json!("Ação, Comédia")),
+            ("comic-2", json!(["Action", "Terror"])),
+            ("comic-3", json!(r#"["Comedy"]"#)),
+        ] {
+            conn.execute(
+                "INSERT INTO comics (id, data) VALUES (?1, json(?2));",
+                params![id, json!({ "name": id, "genres": genres }).to_string()],
+            )
+            .expect("insert comic");
+        }
+        backfill_genre_tags(&conn).expect("backfill");
+
+        let filter = |include: &[&str], exclude: &[&str]| {
+            let filter = LibraryFilter {
+                include_tags: include.iter().map(|tag| tag.to_string()).collect(),
+                exclude_tags: exclude.iter().map(|tag| tag.to_string()).collect(),
+                ..LibraryFilter::default()
+            };
+            ids(filter_comics(&conn, &filter).expect("filter"))
+        };
+        assert_eq!(filter(&["acción"], &[]), ["comic-1", "comic-2"]);
+        assert_eq!(filter(&["action", "comedy"], &[]), ["comic-1"]);
+        assert_eq!(filter(&["action"], &["horror"]), ["comic-1"]);
+
+        set_comic_user_tags(&conn, "comic-3", &["Favorites".to_string()]).expect("user tags");
+        update_tag(&conn, "favorites", None, Some(&["comedy".to_string()])).expect("merge");
+        assert_eq!(filter(&["favorites"], &[]), ["comic-1", "comic-3"]);
+        assert_eq!(
+            comic_tags(&conn, "comic-3").expect("comic tags")[0].sources,
+            ["genre", "user"]
+        );
+    }
+}
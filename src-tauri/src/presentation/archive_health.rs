@@ -0,0 +1,112 @@
+use std::{collections::HashMap, path::Path};
+
+use super::{
+    chapter_display_name, chapter_value_as_string,
+    dto::{ArchiveHealthItem, ArchiveVerifyResponse},
+    list_all_records, locate_chapter_archive,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, ArchiveHealth, ArchiveHealthStatus, DbRecord},
+    infrastructure::{
+        archive_health::{is_current, verify_archive},
+        library_files::{to_local_path, LOCAL_PATH_FIELD},
+    },
+};
+
+/// Chapter field holding the last [`ArchiveHealth`] recorded for its archive.
+const ARCHIVE_HEALTH_FIELD: &str = "archiveHealth";
+
+/// Verifies the local archive of every chapter and records the result on the chapter.
+/// Archives unchanged since their last check are skipped unless `force` is set.
+pub fn verify_archives(
+    service: &DocumentService,
+    comics_dir: &Path,
+    force: bool,
+) -> Result<ArchiveVerifyResponse, AppError> {
+    let comic_names = list_all_records(service, "comics")?
+        .into_iter()
+        .filter_map(|comic| {
+            let name = chapter_value_as_string(&comic, "name")?;
+            Some((comic.id, name))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut report = ArchiveVerifyResponse::default();
+    for chapter in list_all_records(service, "chapters")? {
+        let Some(comic_id) = chapter_value_as_string(&chapter, "comicId") else {
+            continue;
+        };
+        let comic_name = comic_names
+            .get(&comic_id)
+            .cloned()
+            .unwrap_or_else(|| comic_id.clone());
+        let Some(path) = locate_chapter_archive(comics_dir, &chapter, &comic_id, &comic_name)
+        else {
+            continue;
+        };
+
+        let unchanged = stored_health(&chapter).is_some_and(|health| is_current(&health, &path));
+        if !force && unchanged {
+            report.skipped += 1;
+            continue;
+        }
+
+        // Verification takes long enough for the chapter to change meanwhile, so only the
+        // health field is written.
+        let health = verify_archive(&path);
+        let value = serde_json::to_value(&health)
+            .map_err(|e| AppError::infrastructure(format!("Failed to encode health: {e}")))?;
+        service.set_json_field("chapters", &chapter.id, ARCHIVE_HEALTH_FIELD, &value)?;
+
+        report.checked += 1;
+        if health.status != ArchiveHealthStatus::Ok {
+            report.broken.push(health_item(
+                &chapter,
+                comic_id,
+                to_local_path(comics_dir, &path),
+                health,
+            ));
+        }
+    }
+
+    Ok(report)
+}
+
+/// Chapters whose last verification found corrupt pages or an unreadable archive.
+pub fn broken_chapters(service: &DocumentService) -> Result<Vec<ArchiveHealthItem>, AppError> {
+    Ok(list_all_records(service, "chapters")?
+        .into_iter()
+        .filter_map(|chapter| {
+            let health = stored_health(&chapter)?;
+            if health.status == ArchiveHealthStatus::Ok {
+                return None;
+            }
+            let comic_id = chapter_value_as_string(&chapter, "comicId").unwrap_or_default();
+            let local_path = chapter_value_as_string(&chapter, LOCAL_PATH_FIELD);
+            Some(health_item(&chapter, comic_id, local_path, health))
+        })
+        .collect())
+}
+
+fn stored_health(chapter: &DbRecord) -> Option<ArchiveHealth> {
+    chapter
+        .data
+        .get(ARCHIVE_HEALTH_FIELD)
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+}
+
+fn health_item(
+    chapter: &DbRecord,
+    comic_id: String,
+    local_path: Option<String>,
+    health: ArchiveHealth,
+) -> ArchiveHealthItem {
+    ArchiveHealthItem {
+        chapter_id: chapter.id.clone(),
+        comic_id,
+        chapter_name: chapter_display_name(chapter),
+        local_path,
+        health,
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::{
+    chapter_number, chapter_value_as_string,
+    dto::{Bookmark, BookmarkRegion, CreateBookmarkBody},
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord},
+};
+
+const MAX_NOTE_CHARS: usize = 1000;
+const DEEP_LINK_PREFIX: &str = "comic-universe://reader";
+
+/// The chapters the reader lists for a work: canonical chapters, the variants sources
+/// provide, and the mappings between them.
+struct WorkChapters {
+    work_id: String,
+    canonicals: Vec<DbRecord>,
+    variants: Vec<DbRecord>,
+    mappings: Vec<DbRecord>,
+}
+
+impl WorkChapters {
+    /// The work a comic was linked to. `None` when it has none, or the work is gone.
+    fn of_comic(service: &DocumentService, comic_id: &str) -> Result<Option<Self>, AppError> {
+        let Some(work_id) = service
+            .get("comics", comic_id)?
+            .and_then(|comic| chapter_value_as_string(&comic, "workId"))
+        else {
+            return Ok(None);
+        };
+        if service.get("works", &work_id)?.is_none() {
+            return Ok(None);
+        }
+        let by_work = |table: &str| {
+            service.find_by_json_field(
+                table,
+                "workId",
+                Value::String(work_id.clone()),
+                Some(u32::MAX),
+            )
+        };
+        Ok(Some(Self {
+            canonicals: by_work("canonical_chapters")?,
+            variants: by_work("chapter_variants")?,
+            mappings: by_work("chapter_mappings")?,
+            work_id,
+        }))
+    }
+
+    /// The id the reader knows `chapter` by: the variant from the same source page, else
+    /// the canonical chapter, else a variant, with the same number. Mapped variants are
+    /// listed under their canonical chapter, so its id is used for them.
+    fn reader_chapter_id(&self, chapter: &DbRecord) -> Option<String> {
+        let listed_id = |variant: &DbRecord| {
+            self.mappings
+                .iter()
+                .filter(|mapping| {
+                    chapter_value_as_string(mapping, "variantChapterId").as_deref()
+                        == Some(variant.id.as_str())
+                })
+                .filter_map(|mapping| chapter_value_as_string(mapping, "canonicalChapterId"))
+                .find(|id| self.canonicals.iter().any(|canonical| canonical.id == *id))
+                .unwrap_or_else(|| variant.id.clone())
+        };
+
+        if let Some(site_id) = chapter_value_as_string(chapter, "siteId") {
+            if let Some(variant) = self.variants.iter().find(|variant| {
+                chapter_value_as_string(variant, "siteId").as_deref() == Some(site_id.as_str())
+            }) {
+                return Some(listed_id(variant));
+            }
+        }
+        let number = chapter_number(chapter)?;
+        if let Some(canonical) = self
+            .canonicals
+            .iter()
+            .find(|canonical| chapter_number(canonical) == Some(number))
+        {
+            return Some(canonical.id.clone());
+        }
+        self.variants
+            .iter()
+            .find(|variant| chapter_number(variant) == Some(number))
+            .map(listed_id)
+    }
+}
+
+/// Where the reader opens bookmarked chapters, as a work id and a chapter id of that work.
+/// Each comic's work is loaded once.
+struct ReaderLocations<'a> {
+    service: &'a DocumentService,
+    works: HashMap<String, Option<WorkChapters>>,
+}
+
+impl<'a> ReaderLocations<'a> {
+    fn new(service: &'a DocumentService) -> Self {
+        Self {
+            service,
+            works: HashMap::new(),
+        }
+    }
+
+    fn locate(&mut self, chapter: &DbRecord) -> Result<Option<(String, String)>, AppError> {
+        let Some(comic_id) = chapter_value_as_string(chapter, "comicId") else {
+            return Ok(None);
+        };
+        if !self.works.contains_key(&comic_id) {
+            let work = WorkChapters::of_comic(self.service, &comic_id)?;
+            self.works.insert(comic_id.clone(), work);
+        }
+        Ok(self.works[&comic_id].as_ref().and_then(|work| {
+            let chapter_id = work.reader_chapter_id(chapter)?;
+            Some((work.work_id.clone(), chapter_id))
+        }))
+    }
+}
+
+/// Saves a bookmark on a page of `chapter`, which must exist.
+pub fn create_bookmark(
+    service: &DocumentService,
+    chapter: &DbRecord,
+    body: CreateBookmarkBody,
+) -> Result<Bookmark, AppError> {
+    if body.page < 1 {
+        return Err(AppError::Validation(
+            "page must be 1 or greater".to_string(),
+        ));
+    }
+    let note = body
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note
+        .as_ref()
+        .is_some_and(|note| note.chars().count() > MAX_NOTE_CHARS)
+    {
+        return Err(AppError::Validation(format!(
+            "note cannot be longer than {MAX_NOTE_CHARS} characters"
+        )));
+    }
+    if let Some(region) = &body.region {
+        validate_region(region)?;
+    }
+    let comic_id = chapter_value_as_string(chapter, "comicId")
+        .ok_or_else(|| AppError::Validation("Chapter has no comicId".to_string()))?;
+
+    let record = service.upsert(
+        "bookmarks",
+        None,
+        json!({
+            "chapterId": chapter.id,
+            "comicId": comic_id,
+            "page": body.page,
+            "note": note,
+            "region": body.region,
+        }),
+    )?;
+    let location = ReaderLocations::new(service).locate(chapter)?;
+    bookmark_from_record(&record, location.as_ref())
+        .ok_or_else(|| AppError::infrastructure("Saved bookmark could not be read back"))
+}
+
+/// Bookmarks of a comic or of a chapter, by page and then oldest first.
+pub fn list_bookmarks(
+    service: &DocumentService,
+    field: &str,
+    id: &str,
+) -> Result<Vec<Bookmark>, AppError> {
+    let records = service.find_by_json_field(
+        "bookmarks",
+        field,
+        Value::String(id.to_string()),
+        Some(u32::MAX),
+    )?;
+    let chapter_ids = records
+        .iter()
+        .filter_map(|record| chapter_value_as_string(record, "chapterId"))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let mut locations = ReaderLocations::new(service);
+    let mut located = HashMap::new();
+    for chapter in service.get_many("chapters", &chapter_ids)? {
+        if let Some(location) = locations.locate(&chapter)? {
+            located.insert(chapter.id, location);
+        }
+    }
+
+    let mut bookmarks = records
+        .iter()
+        .filter_map(|record| {
+            let location = chapter_value_as_string(record, "chapterId")
+                .and_then(|chapter_id| located.get(&chapter_id));
+            bookmark_from_record(record, location)
+        })
+        .collect::<Vec<_>>();
+    bookmarks.sort_by(|left, right| {
+        (&left.chapter_id, left.page, &left.created_at).cmp(&(
+            &right.chapter_id,
+            right.page,
+            &right.created_at,
+        ))
+    });
+    Ok(bookmarks)
+}
+
+/// The bookmark stored in `record`, linked to the reader at `location` when its chapter
+/// is part of a work.
+fn bookmark_from_record(
+    record: &DbRecord,
+    location: Option<&(String, String)>,
+) -> Option<Bookmark> {
+    let chapter_id = chapter_value_as_string(record, "chapterId")?;
+    let comic_id = chapter_value_as_string(record, "comicId")?;
+    let page = record.data.get("page").and_then(Value::as_i64)?;
+    Some(Bookmark {
+        deep_link: location.map(|(work_id, reader_chapter_id)| {
+            reader_deep_link(work_id, reader_chapter_id, page, &record.id)
+        }),
+        id: record.id.clone(),
+        note: chapter_value_as_string(record, "note"),
+        region: record
+            .data
+            .get("region")
+            .and_then(|region| serde_json::from_value(region.clone()).ok()),
+        created_at: record.created_at.clone(),
+        chapter_id,
+        comic_id,
+        page,
+    })
+}
+
+fn validate_region(region: &BookmarkRegion) -> Result<(), AppError> {
+    let values = [region.x, region.y, region.width, region.height];
+    let valid = values
+        .iter()
+        .all(|value| value.is_finite() && (0.0..=1.0).contains(value))
+        && region.width > 0.0
+        && region.height > 0.0
+        && region.x + region.width <= 1.0
+        && region.y + region.height <= 1.0;
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::Validation(
+            "region must lie within the page, as fractions between 0 and 1".to_string(),
+        ))
+    }
+}
+
+/// `comic-universe://reader/{workId}/{chapterId}?page=N&bookmark=ID`, which the app opens
+/// in the reader at that page. `chapterId` is a canonical chapter or chapter variant id.
+fn reader_deep_link(work_id: &str, chapter_id: &str, page: i64, bookmark_id: &str) -> String {
+    format!(
+        "{DEEP_LINK_PREFIX}/{}/{}?page={page}&bookmark={}",
+        percent_encode(work_id),
+        percent_encode(chapter_id),
+        percent_encode(bookmark_id)
+    )
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::infrastructure::SqliteDocumentStore;
+
+    /// The work and chapter ids the reader route gets from a bookmark's link.
+    fn open_reader_link(link: &str) -> (String, String) {
+        let decode = |segment: &str| {
+            let mut bytes = Vec::new();
+            let mut rest = segment.as_bytes();
+            while let Some((&byte, tail)) = rest.split_first() {
+                if byte == b'%' {
+                    let hex = std::str::from_utf8(&tail[..2]).expect("hex digits");
+                    bytes.push(u8::from_str_radix(hex, 16).expect("escaped byte"));
+                    rest = &tail[2..];
+                } else {
+                    bytes.push(byte);
+                    rest = tail;
+                }
+            }
+            String::from_utf8(bytes).expect("utf-8 segment")
+        };
+        let path = link
+            .strip_prefix(&format!("{DEEP_LINK_PREFIX}/"))
+            .and_then(|rest| rest.split('?').next())
+            .expect("reader link");
+        let (work_id, chapter_id) = path.split_once('/').expect("two segments");
+        (decode(work_id), decode(chapter_id))
+    }
+
+    #[test]
+    fn reads_bookmarks_with_encoded_deep_links() {
+        let record = DbRecord {
+            id: "b-1".to_string(),
+            data: json!({
+                "chapterId": "chapter:mangadex/42",
+                "comicId": "comic-1",
+                "page": 7,
+                "note": "  the big reveal ",
+                "region": { "x": 0.5, "y": 0.0, "width": 0.5, "height": 1.0 }
+            }),
+            created_at: "2026-03-01T10:00:00.000Z".to_string(),
+            updated_at: "2026-03-01T10:00:00.000Z".to_string(),
+        };
+        let location = (
+            "work-1".to_string(),
+            "chapter-variant:work-1:mangadex/42".to_string(),
+        );
+
+        let bookmark = bookmark_from_record(&record, Some(&location)).expect("bookmark");
+        assert_eq!(bookmark.note.as_deref(), Some("the big reveal"));
+        assert_eq!(
+            bookmark.deep_link.as_deref(),
+            Some("comic-universe://reader/work-1/chapter-variant%3Awork-1%3Amangadex%2F42?page=7&bookmark=b-1")
+        );
+        assert!(validate_region(bookmark.region.as_ref().unwrap()).is_ok());
+        assert!(validate_region(&BookmarkRegion {
+            x: 0.6,
+            y: 0.0,
+            width: 0.5,
+            height: 1.0,
+        })
+        .is_err());
+        assert_eq!(
+            bookmark_from_record(&record, None)
+                .expect("bookmark")
+                .deep_link,
+            None
+        );
+    }
+
+    #[test]
+    fn opens_bookmarks_at_the_chapter_of_their_work() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let store =
+            SqliteDocumentStore::initialize(dir.path(), &dir.path().join("comics")).expect("store");
+        let service = DocumentService::new(Arc::new(store));
+        let seed = [
+            ("works", "work-1", json!({ "title": "Pablo" })),
+            (
+                "comics",
+                "comic-1",
+                json!({ "name": "Pablo", "workId": "work-1" }),
+            ),
+            ("comics", "comic-2", json!({ "name": "Loose" })),
+            (
+                "chapters",
+                "c-1",
+                json!({ "comicId": "comic-1", "number": "1" }),
+            ),
+            (
+                "chapters",
+                "c-2",
+                json!({ "comicId": "comic-1", "number": "2", "siteId": "s-2" }),
+            ),
+            (
+                "chapters",
+                "c-3",
+                json!({ "comicId": "comic-1", "number": "3" }),
+            ),
+            (
+                "chapters",
+                "c-4",
+                json!({ "comicId": "comic-2", "number": "1" }),
+            ),
+            (
+                "canonical_chapters",
+                "canonical:1",
+                json!({ "workId": "work-1", "number": "1" }),
+            ),
+            (
+                "canonical_chapters",
+                "canonical:2",
+                json!({ "workId": "work-1", "number": "2" }),
+            ),
+            (
+                "chapter_variants",
+                "chapter-variant:work-1:mangadex:s-2",
+                json!({ "workId": "work-1", "number": "2.5", "siteId": "s-2" }),
+            ),
+            (
+                "chapter_variants",
+                "chapter-variant:work-1:mangadex:s-3",
+                json!({ "workId": "work-1", "number": "3", "siteId": "s-3" }),
+            ),
+            (
+                "chapter_mappings",
+                "mapping-2",
+                json!({
+                    "workId": "work-1",
+                    "canonicalChapterId": "canonical:2",
+                    "variantChapterId": "chapter-variant:work-1:mangadex:s-2"
+                }),
+            ),
+        ];
+        for (table, id, data) in seed {
+            service
+                .upsert(table, Some(id.to_string()), data)
+                .expect("seed record");
+        }
+        let bookmark = |chapter_id: &str| {
+            let chapter = service
+                .get("chapters", chapter_id)
+                .expect("get chapter")
+                .expect("chapter");
+            let body = serde_json::from_value(json!({ "chapterId": chapter_id, "page": 4 }))
+                .expect("bookmark body");
+            create_bookmark(&service, &chapter, body).expect("create bookmark")
+        };
+
+        let created = ["c-1", "c-2", "c-3", "c-4"].map(bookmark);
+        let opened = |bookmark: &Bookmark| bookmark.deep_link.as_deref().map(open_reader_link);
+        let expected =
+            |work_id: &str, chapter_id: &str| Some((work_id.to_string(), chapter_id.to_string()));
+        // Same number; the variant from the same source page, listed under its canonical
+        // chapter; an unmapped variant; a comic that is not part of a work.
+        assert_eq!(opened(&created[0]), expected("work-1", "canonical:1"));
+        assert_eq!(opened(&created[1]), expected("work-1", "canonical:2"));
+        assert_eq!(
+            opened(&created[2]),
+            expected("work-1", "chapter-variant:work-1:mangadex:s-3")
+        );
+        assert_eq!(opened(&created[3]), None);
+        assert!(created[0]
+            .deep_link
+            .as_deref()
+            .is_some_and(|link| link.ends_with(&format!("?page=4&bookmark={}", created[0].id))));
+
+        let listed = list_bookmarks(&service, "comicId", "comic-1").expect("list bookmarks");
+        assert_eq!(
+            listed
+                .iter()
+                .map(|bookmark| bookmark.deep_link.clone())
+                .collect::<Vec<_>>(),
+            created[..3]
+                .iter()
+                .map(|bookmark| bookmark.deep_link.clone())
+                .collect::<Vec<_>>()
+        );
+    }
+}
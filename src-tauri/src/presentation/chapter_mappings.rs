@@ -0,0 +1,166 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::{
+    chapter_display_name, chapter_value_as_string, compare_chapter_order,
+    dto::{RemapReviewItem, RemapWorkResponse},
+    stable_import_id,
+};
+use crate::{
+    application::{plan_chapter_mappings, DocumentService, MappingTarget},
+    domain::{AppError, DbRecord, DocumentWrite},
+};
+
+/// Mappings set by hand; remapping never replaces them.
+const MANUAL_STRATEGY: &str = "manual";
+
+/// Maps every chapter variant of a work to a canonical chapter, creating canonical chapters
+/// for variants that match none. Automatic mappings are replaced; variants mapped by hand
+/// are left alone. All writes land in one transaction. `None` when the work does not exist.
+pub fn remap_work(
+    service: &DocumentService,
+    work_id: &str,
+) -> Result<Option<RemapWorkResponse>, AppError> {
+    if service.get("works", work_id)?.is_none() {
+        return Ok(None);
+    }
+    let by_work = |table: &str| {
+        service.find_by_json_field(
+            table,
+            "workId",
+            Value::String(work_id.to_string()),
+            Some(u32::MAX),
+        )
+    };
+    let mut canonicals = by_work("canonical_chapters")?;
+    canonicals.sort_by(compare_chapter_order);
+    let mut variants = by_work("chapter_variants")?;
+    variants.sort_by(compare_chapter_order);
+    let mappings = by_work("chapter_mappings")?;
+
+    let manual = mappings
+        .iter()
+        .filter(|mapping| {
+            chapter_value_as_string(mapping, "strategy").as_deref() == Some(MANUAL_STRATEGY)
+        })
+        .filter_map(|mapping| chapter_value_as_string(mapping, "variantChapterId"))
+        .collect::<HashSet<_>>();
+    let mut automatic = HashMap::<String, Vec<&DbRecord>>::new();
+    for mapping in &mappings {
+        if let Some(variant_id) = chapter_value_as_string(mapping, "variantChapterId") {
+            if !manual.contains(&variant_id) {
+                automatic.entry(variant_id).or_default().push(mapping);
+            }
+        }
+    }
+    variants.retain(|variant| !manual.contains(&variant.id));
+
+    let plan = plan_chapter_mappings(&canonicals, &variants);
+
+    let mut used_ids = canonicals
+        .iter()
+        .map(|canonical| canonical.id.clone())
+        .collect::<HashSet<_>>();
+    let mut created_ids = Vec::new();
+    let mut writes = Vec::new();
+    for chapter in &plan.new_chapters {
+        let base = stable_import_id("canonical-chapter", &[work_id, &chapter.number]);
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while !used_ids.insert(id.clone()) {
+            id = format!("{base}:{suffix}");
+            suffix += 1;
+        }
+        writes.push(DocumentWrite::upsert(
+            "canonical_chapters",
+            Some(id.clone()),
+            json!({
+                "workId": work_id,
+                "number": chapter.number,
+                "name": chapter.name,
+                "raw": { "generatedFromVariants": true },
+            }),
+        )?);
+        created_ids.push(id);
+    }
+
+    let mut to_review = Vec::new();
+    for chapter_match in &plan.matches {
+        let canonical_id = match &chapter_match.target {
+            MappingTarget::Existing(id) => id.clone(),
+            MappingTarget::New(index) => created_ids[*index].clone(),
+        };
+        let previous = automatic
+            .remove(&chapter_match.variant_id)
+            .unwrap_or_default();
+        // The (canonical, variant) pair is unique, so an existing row for it is reused.
+        let mut mapping_id = format!(
+            "chapter-mapping:{canonical_id}:{}",
+            chapter_match.variant_id
+        );
+        for mapping in previous {
+            if chapter_value_as_string(mapping, "canonicalChapterId").as_deref()
+                == Some(canonical_id.as_str())
+            {
+                mapping_id = mapping.id.clone();
+            } else {
+                writes.push(DocumentWrite::delete("chapter_mappings", &mapping.id)?);
+            }
+        }
+        writes.push(DocumentWrite::upsert(
+            "chapter_mappings",
+            Some(mapping_id.clone()),
+            json!({
+                "workId": work_id,
+                "canonicalChapterId": canonical_id,
+                "variantChapterId": chapter_match.variant_id,
+                "strategy": chapter_match.strategy,
+                "confidence": chapter_match.confidence,
+                "needsReview": chapter_match.needs_review,
+            }),
+        )?);
+
+        if chapter_match.needs_review {
+            to_review.push((chapter_match, mapping_id, canonical_id));
+        }
+    }
+
+    // Canonical chapters are written first, so they lead the written records.
+    let written = service.write_batch(&writes)?;
+    let canonical_by_id = canonicals
+        .iter()
+        .chain(&written[..created_ids.len()])
+        .map(|canonical| (canonical.id.as_str(), canonical))
+        .collect::<HashMap<_, _>>();
+    let variant_by_id = variants
+        .iter()
+        .map(|variant| (variant.id.as_str(), variant))
+        .collect::<HashMap<_, _>>();
+    let review = to_review
+        .into_iter()
+        .map(
+            |(chapter_match, mapping_id, canonical_id)| RemapReviewItem {
+                mapping_id,
+                variant_label: variant_by_id
+                    .get(chapter_match.variant_id.as_str())
+                    .map(|variant| chapter_display_name(variant)),
+                canonical_label: canonical_by_id
+                    .get(canonical_id.as_str())
+                    .map(|canonical| chapter_display_name(canonical)),
+                variant_chapter_id: chapter_match.variant_id.clone(),
+                canonical_chapter_id: canonical_id,
+                confidence: chapter_match.confidence,
+                strategy: chapter_match.strategy.to_string(),
+            },
+        )
+        .collect();
+
+    Ok(Some(RemapWorkResponse {
+        work_id: work_id.to_string(),
+        mapped: plan.matches.len(),
+        manual: manual.len(),
+        created_chapter_ids: created_ids,
+        review,
+    }))
+}
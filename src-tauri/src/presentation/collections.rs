@@ -0,0 +1,421 @@
+use std::collections::{HashMap, HashSet};
+
+use serde_json::{json, Value};
+
+use super::{
+    chapter_display_name, chapter_value_as_string,
+    dto::{
+        AddCollectionItemsBody, CollectionBody, CollectionDetail, CollectionItem,
+        CollectionItemRef, CollectionSummary, ReorderCollectionItemsBody,
+    },
+    list_all_records,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord, DocumentWrite},
+};
+
+const COLLECTION_KINDS: [&str; 2] = ["collection", "readingList"];
+const READING_LIST_KIND: &str = "readingList";
+const ITEM_TYPES: [&str; 3] = ["comic", "work", "chapter"];
+
+/// Every collection with its item count, by name.
+pub fn list_collections(service: &DocumentService) -> Result<Vec<CollectionSummary>, AppError> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for item in list_all_records(service, "collection_items")? {
+        if let Some(collection_id) = chapter_value_as_string(&item, "collectionId") {
+            *counts.entry(collection_id).or_default() += 1;
+        }
+    }
+
+    let mut collections = list_all_records(service, "collections")?
+        .iter()
+        .map(|record| summary(record, counts.get(&record.id).copied().unwrap_or(0)))
+        .collect::<Vec<_>>();
+    collections.sort_by_key(|collection| collection.name.to_lowercase());
+    Ok(collections)
+}
+
+pub fn create_collection(
+    service: &DocumentService,
+    body: CollectionBody,
+) -> Result<CollectionDetail, AppError> {
+    let name = body
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| AppError::Validation("name is required".to_string()))?;
+    let kind = validate_kind(body.kind.as_deref().unwrap_or(COLLECTION_KINDS[0]))?;
+
+    let record = service.upsert(
+        "collections",
+        None,
+        json!({
+            "name": name,
+            "description": body.description,
+            "cover": body.cover,
+            "kind": kind,
+        }),
+    )?;
+    detail(service, &record, Vec::new())
+}
+
+/// Changes the fields given in `body`. A collection only becomes a reading list when it
+/// holds nothing but chapters.
+pub fn update_collection(
+    service: &DocumentService,
+    collection_id: &str,
+    body: CollectionBody,
+) -> Result<Option<CollectionDetail>, AppError> {
+    let Some(mut collection) = service.get("collections", collection_id)? else {
+        return Ok(None);
+    };
+    let items = ordered_items(service, collection_id)?;
+
+    if let Some(name) = body.name {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("name cannot be empty".to_string()));
+        }
+        collection.data["name"] = Value::String(name.to_string());
+    }
+    if let Some(kind) = body.kind {
+        let kind = validate_kind(&kind)?;
+        if kind == READING_LIST_KIND && items.iter().any(|item| item_type(item) != "chapter") {
+            return Err(AppError::Validation(
+                "Only collections of chapters can become reading lists".to_string(),
+            ));
+        }
+        collection.data["kind"] = Value::String(kind.to_string());
+    }
+    if let Some(description) = body.description {
+        collection.data["description"] = Value::String(description);
+    }
+    if let Some(cover) = body.cover {
+        collection.data["cover"] = Value::String(cover);
+    }
+
+    let record = service.upsert("collections", Some(collection.id), collection.data)?;
+    detail(service, &record, items).map(Some)
+}
+
+pub fn collection_detail(
+    service: &DocumentService,
+    collection_id: &str,
+) -> Result<Option<CollectionDetail>, AppError> {
+    let Some(collection) = service.get("collections", collection_id)? else {
+        return Ok(None);
+    };
+    let items = ordered_items(service, collection_id)?;
+    detail(service, &collection, items).map(Some)
+}
+
+/// Inserts references at `position`, or at the end. References already in the collection
+/// are skipped; ones to missing comics, works or chapters are rejected.
+pub fn add_items(
+    service: &DocumentService,
+    collection_id: &str,
+    body: AddCollectionItemsBody,
+) -> Result<Option<CollectionDetail>, AppError> {
+    let Some(collection) = service.get("collections", collection_id)? else {
+        return Ok(None);
+    };
+    let reading_list =
+        chapter_value_as_string(&collection, "kind").as_deref() == Some(READING_LIST_KIND);
+    let items = ordered_items(service, collection_id)?;
+    let mut present = items
+        .iter()
+        .map(|item| (item_type(item), item_ref_id(item)))
+        .collect::<HashSet<_>>();
+
+    let mut comic_ids = Vec::new();
+    for reference in &body.items {
+        if !ITEM_TYPES.contains(&reference.item_type.as_str()) {
+            return Err(AppError::Validation(format!(
+                "Unsupported item type: {}",
+                reference.item_type
+            )));
+        }
+        if reading_list && reference.item_type != "chapter" {
+            return Err(AppError::Validation(
+                "Reading lists only hold chapters".to_string(),
+            ));
+        }
+        comic_ids.push(referenced_comic_id(service, reference)?);
+    }
+
+    let position = body.position.unwrap_or(items.len()).min(items.len());
+    let mut added = Vec::new();
+    for (reference, comic_id) in body.items.into_iter().zip(comic_ids) {
+        if !present.insert((reference.item_type.clone(), reference.id.clone())) {
+            continue;
+        }
+        added.push(DocumentWrite::upsert(
+            "collection_items",
+            None,
+            json!({
+                "collectionId": collection_id,
+                "itemType": reference.item_type,
+                "itemId": reference.id,
+                "comicId": comic_id,
+                "position": position + added.len(),
+            }),
+        )?);
+    }
+
+    let mut writes = renumber(&items[..position], 0)?;
+    writes.extend(renumber(&items[position..], position + added.len())?);
+    writes.extend(added);
+    service.write_batch(&writes)?;
+    let items = ordered_items(service, collection_id)?;
+    detail(service, &collection, items).map(Some)
+}
+
+/// Removes one item. `None` when the collection or the item does not exist.
+pub fn remove_item(
+    service: &DocumentService,
+    collection_id: &str,
+    item_id: &str,
+) -> Result<Option<CollectionDetail>, AppError> {
+    let Some(collection) = service.get("collections", collection_id)? else {
+        return Ok(None);
+    };
+    let mut items = ordered_items(service, collection_id)?;
+    let Some(index) = items.iter().position(|item| item.id == item_id) else {
+        return Ok(None);
+    };
+
+    items.remove(index);
+    let mut writes = vec![DocumentWrite::delete("collection_items", item_id)?];
+    writes.extend(renumber(&items, 0)?);
+    service.write_batch(&writes)?;
+    let items = ordered_items(service, collection_id)?;
+    detail(service, &collection, items).map(Some)
+}
+
+pub fn reorder_items(
+    service: &DocumentService,
+    collection_id: &str,
+    body: ReorderCollectionItemsBody,
+) -> Result<Option<CollectionDetail>, AppError> {
+    let Some(collection) = service.get("collections", collection_id)? else {
+        return Ok(None);
+    };
+    let items = apply_order(ordered_items(service, collection_id)?, &body.item_ids)?;
+    service.write_batch(&renumber(&items, 0)?)?;
+    let items = ordered_items(service, collection_id)?;
+    detail(service, &collection, items).map(Some)
+}
+
+fn apply_order(items: Vec<DbRecord>, item_ids: &[String]) -> Result<Vec<DbRecord>, AppError> {
+    let mut remaining = items.into_iter().map(Some).collect::<Vec<_>>();
+    let mut ordered = Vec::with_capacity(remaining.len());
+    for item_id in item_ids {
+        let Some(slot) = remaining
+            .iter_mut()
+            .find(|slot| slot.as_ref().is_some_and(|item| &item.id == item_id))
+        else {
+            if ordered.iter().any(|item: &DbRecord| &item.id == item_id) {
+                continue;
+            }
+            return Err(AppError::Validation(format!(
+                "Item {item_id} is not in this collection"
+            )));
+        };
+        ordered.extend(slot.take());
+    }
+    ordered.extend(remaining.into_iter().flatten());
+    Ok(ordered)
+}
+
+fn ordered_items(
+    service: &DocumentService,
+    collection_id: &str,
+) -> Result<Vec<DbRecord>, AppError> {
+    let mut items = service.find_by_json_field(
+        "collection_items",
+        "collectionId",
+        Value::String(collection_id.to_string()),
+        Some(u32::MAX),
+    )?;
+    items.sort_by(|left, right| {
+        (position(left), &left.created_at).cmp(&(position(right), &right.created_at))
+    });
+    Ok(items)
+}
+
+/// Writes that store `first` plus each item's index as its position, for the items that
+/// moved.
+fn renumber(items: &[DbRecord], first: usize) -> Result<Vec<DocumentWrite>, AppError> {
+    items
+        .iter()
+        .enumerate()
+        .filter(|(index, item)| position(item) != first + index)
+        .map(|(index, item)| {
+            let mut data = item.data.clone();
+            data["position"] = json!(first + index);
+            DocumentWrite::upsert("collection_items", Some(item.id.clone()), data)
+        })
+        .collect()
+}
+
+/// The comic a reference belongs to, which removes the item when that comic is deleted.
+fn referenced_comic_id(
+    service: &DocumentService,
+    reference: &CollectionItemRef,
+) -> Result<Option<String>, AppError> {
+    let table = match reference.item_type.as_str() {
+        "comic" => "comics",
+        "work" => "works",
+        _ => "chapters",
+    };
+    let record = service.get(table, &reference.id)?.ok_or_else(|| {
+        AppError::Validation(format!(
+            "{} {} not found",
+            reference.item_type, reference.id
+        ))
+    })?;
+    Ok(match reference.item_type.as_str() {
+        "comic" => Some(record.id),
+        "chapter" => chapter_value_as_string(&record, "comicId"),
+        _ => None,
+    })
+}
+
+/// Resolves the titles of the items with one query per referenced table.
+fn detail(
+    service: &DocumentService,
+    collection: &DbRecord,
+    items: Vec<DbRecord>,
+) -> Result<CollectionDetail, AppError> {
+    let referenced = |wanted: &str| {
+        items
+            .iter()
+            .filter(|item| item_type(item) == wanted)
+            .map(item_ref_id)
+            .collect::<Vec<_>>()
+    };
+    let by_id = |records: Vec<DbRecord>| {
+        records
+            .into_iter()
+            .map(|record| (record.id.clone(), record))
+            .collect::<HashMap<_, _>>()
+    };
+    let chapters = by_id(service.get_many("chapters", &referenced("chapter"))?);
+    let works = by_id(service.get_many("works", &referenced("work"))?);
+    let mut comic_ids = referenced("comic");
+    comic_ids.extend(
+        items
+            .iter()
+            .filter(|item| item_type(item) == "chapter")
+            .filter_map(|item| chapter_value_as_string(item, "comicId")),
+    );
+    let comics = by_id(service.get_many("comics", &comic_ids)?);
+    let comic_name = |comic_id: &str| {
+        comics
+            .get(comic_id)
+            .and_then(|comic| chapter_value_as_string(comic, "name"))
+    };
+
+    let items = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let item_type = item_type(item);
+            let item_id = item_ref_id(item);
+            let comic_id = chapter_value_as_string(item, "comicId");
+            let (title, comic_name) = match item_type.as_str() {
+                "comic" => (comic_name(&item_id), None),
+                "work" => (
+                    works.get(&item_id).and_then(|work| {
+                        chapter_value_as_string(work, "title")
+                            .or_else(|| chapter_value_as_string(work, "name"))
+                    }),
+                    None,
+                ),
+                _ => (
+                    chapters.get(&item_id).map(chapter_display_name),
+                    comic_id.as_deref().and_then(comic_name),
+                ),
+            };
+            CollectionItem {
+                id: item.id.clone(),
+                item_type,
+                item_id,
+                comic_id,
+                title,
+                comic_name,
+                position: index,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(CollectionDetail {
+        collection: summary(collection, items.len()),
+        items,
+    })
+}
+
+fn summary(record: &DbRecord, item_count: usize) -> CollectionSummary {
+    CollectionSummary {
+        id: record.id.clone(),
+        name: chapter_value_as_string(record, "name").unwrap_or_else(|| record.id.clone()),
+        description: chapter_value_as_string(record, "description"),
+        cover: chapter_value_as_string(record, "cover"),
+        kind: chapter_value_as_string(record, "kind")
+            .unwrap_or_else(|| COLLECTION_KINDS[0].to_string()),
+        item_count,
+        created_at: record.created_at.clone(),
+        updated_at: record.updated_at.clone(),
+    }
+}
+
+fn validate_kind(kind: &str) -> Result<&'static str, AppError> {
+    COLLECTION_KINDS
+        .iter()
+        .find(|candidate| **candidate == kind)
+        .copied()
+        .ok_or_else(|| AppError::Validation(format!("Unsupported collection kind: {kind}")))
+}
+
+fn item_type(item: &DbRecord) -> String {
+    chapter_value_as_string(item, "itemType").unwrap_or_default()
+}
+
+fn item_ref_id(item: &DbRecord) -> String {
+    chapter_value_as_string(item, "itemId").unwrap_or_default()
+}
+
+fn position(item: &DbRecord) -> usize {
+    item.data
+        .get("position")
+        .and_then(Value::as_u64)
+        .map_or(usize::MAX, |position| position as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str) -> DbRecord {
+        DbRecord {
+            id: id.to_string(),
+            data: json!({ "collectionId": "list-1", "itemType": "chapter", "itemId": id }),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn moves_listed_items_first_and_keeps_the_rest_in_order() {
+        let items = ["a", "b", "c", "d"].map(item).to_vec();
+        let ids = |items: Vec<DbRecord>| items.into_iter().map(|item| item.id).collect::<Vec<_>>();
+
+        let reordered =
+            apply_order(items.clone(), &["c".to_string(), "a".to_string()]).expect("reorder");
+        assert_eq!(ids(reordered), ["c", "a", "b", "d"]);
+
+        assert!(apply_order(items, &["missing".to_string()]).is_err());
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use utoipa::ToSchema;
 
+use crate::domain::{ArchiveHealth, ComicProgress, ComicTag, DbRecord, TagSummary};
+
 #[derive(Clone, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ApiEndpointPayload {
@@ -31,6 +33,28 @@ pub struct ListQuery {
     pub offset: Option<u32>,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStatsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub utc_offset_minutes: Option<i32>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ContinueReadingQuery {
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct ImageVariantQuery {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    pub fit: Option<String>,
+    pub format: Option<String>,
+}
+
 #[derive(Serialize, ToSchema)]
 pub struct HealthResponse {
     pub status: String,
@@ -52,6 +76,12 @@ pub struct ChapterPage {
     pub index: usize,
     pub file_name: String,
     pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub byte_size: Option<u64>,
+    pub mime_type: Option<String>,
+    /// Wide page that most likely spans two pages of the printed book.
+    pub is_spread: bool,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -82,8 +112,18 @@ pub struct MigrateLegacyResponse {
 #[derive(Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MarkChaptersBody {
+    #[serde(default)]
     pub chapter_ids: Vec<String>,
     pub read: bool,
+    /// Also marks this comic's chapters, narrowed by the fields below; all of them when none
+    /// is set.
+    pub comic_id: Option<String>,
+    /// Lowest chapter number to mark, inclusive.
+    pub from_number: Option<f64>,
+    /// Highest chapter number to mark, inclusive.
+    pub up_to_number: Option<f64>,
+    /// Only chapters available in this language code.
+    pub language: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -93,6 +133,320 @@ pub struct MarkChaptersResponse {
     pub skipped: usize,
 }
 
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RepairProgressResponse {
+    /// Progress rows that recorded a single page.
+    pub checked: usize,
+    /// Rows whose real page count was found and written.
+    pub repaired: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueReadingItem {
+    pub comic_id: String,
+    pub comic_name: Option<String>,
+    pub chapter_id: String,
+    pub chapter_name: String,
+    pub chapter_number: Option<String>,
+    /// Page to reopen the chapter at; `0` when it has not been started.
+    pub resume_page: i64,
+    pub total_pages: Option<i64>,
+    /// `inProgress` or `unread`.
+    pub status: String,
+    /// Last progress update in the comic, if any.
+    pub last_read_at: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NextChapterResponse {
+    pub comic_id: String,
+    /// `null` when every chapter of the comic is read.
+    pub next: Option<ContinueReadingItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ContinueReadingResponse {
+    pub items: Vec<ContinueReadingItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryProgressResponse {
+    pub comics: Vec<ComicProgress>,
+}
+
+/// Part of a page, as fractions of its width and height measured from the top-left corner.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBookmarkBody {
+    pub chapter_id: String,
+    /// Page number, starting at 1 like saved progress.
+    pub page: i64,
+    pub note: Option<String>,
+    pub region: Option<BookmarkRegion>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub id: String,
+    pub chapter_id: String,
+    pub comic_id: String,
+    pub page: i64,
+    pub note: Option<String>,
+    pub region: Option<BookmarkRegion>,
+    pub created_at: String,
+    /// `comic-universe://` link that opens the reader at the bookmarked page. `None` when
+    /// the chapter's comic is not part of a work the reader can list.
+    pub deep_link: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkListResponse {
+    pub items: Vec<Bookmark>,
+}
+
+/// Fields of a collection. `name` is required when creating one; omitted fields are left
+/// unchanged on update.
+#[derive(Default, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBody {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub cover: Option<String>,
+    /// `collection` (default) or `readingList`, which only holds chapters.
+    pub kind: Option<String>,
+}
+
+#[derive(Clone, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItemRef {
+    /// `comic`, `work` or `chapter`.
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub id: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AddCollectionItemsBody {
+    pub items: Vec<CollectionItemRef>,
+    /// Where to insert the items; appended at the end by default.
+    pub position: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderCollectionItemsBody {
+    /// Collection item ids in their new order. Items left out keep their relative order
+    /// after the listed ones.
+    pub item_ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionSummary {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub cover: Option<String>,
+    pub kind: String,
+    pub item_count: usize,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionItem {
+    /// Id of the collection item, used to remove or reorder it.
+    pub id: String,
+    #[serde(rename = "type")]
+    pub item_type: String,
+    pub item_id: String,
+    /// Comic of a comic or chapter item. Works span several comics, so work items omit it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comic_id: Option<String>,
+    /// Comic name, work title, or chapter name.
+    pub title: Option<String>,
+    /// Name of the comic a chapter belongs to.
+    pub comic_name: Option<String>,
+    pub position: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionDetail {
+    pub collection: CollectionSummary,
+    pub items: Vec<CollectionItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionListResponse {
+    pub items: Vec<CollectionSummary>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TagListResponse {
+    pub items: Vec<TagSummary>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTagBody {
+    pub name: Option<String>,
+    /// Replaces the tag's aliases. Existing tags named by an alias are merged into this one.
+    pub aliases: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicTagsBody {
+    /// Tag names added by hand; genre tags are not affected.
+    pub tags: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicTagListResponse {
+    pub comic_id: String,
+    pub items: Vec<ComicTag>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryComicsQuery {
+    pub tags: Option<String>,
+    pub exclude_tags: Option<String>,
+    pub favorite: Option<bool>,
+    pub min_rating: Option<i64>,
+    pub sort: Option<String>,
+}
+
+/// Personal rating, favorite flag and review. Fields left out are kept; `rating`, `stars`
+/// and `review` set to `null` are cleared.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalRatingBody {
+    /// Rating from 0 to 10.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<i64>)]
+    pub rating: Option<Option<i64>>,
+    /// Rating as 0 to 5 stars in half steps, stored as `rating`. Must agree with `rating`
+    /// when both are given.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<f64>)]
+    pub stars: Option<Option<f64>>,
+    pub favorite: Option<bool>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub review: Option<Option<String>>,
+}
+
+/// Tells a field sent as `null`, `Some(None)`, apart from one left out, `None`.
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalRating {
+    /// `comic` or `work`.
+    pub item_type: String,
+    pub item_id: String,
+    pub rating: Option<i64>,
+    pub stars: Option<f64>,
+    pub favorite: bool,
+    pub review: Option<String>,
+    pub updated_at: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalRatingListResponse {
+    pub items: Vec<PersonalRating>,
+}
+
+/// A rating from a ratings export. Other fields of the export, like `stars`, are ignored.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedPersonalRating {
+    /// `comic` or `work`.
+    pub item_type: String,
+    pub item_id: String,
+    pub rating: Option<i64>,
+    pub favorite: Option<bool>,
+    pub review: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPersonalRatingsBody {
+    pub items: Vec<ImportedPersonalRating>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPersonalRatingsResponse {
+    pub restored: usize,
+    /// Ratings of items missing from this library, already rated, or out of range.
+    pub skipped: usize,
+}
+
+/// A mapping the engine was unsure of, for someone to confirm or correct.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapReviewItem {
+    pub mapping_id: String,
+    pub variant_chapter_id: String,
+    pub variant_label: Option<String>,
+    pub canonical_chapter_id: String,
+    pub canonical_label: Option<String>,
+    /// 0 to 1.
+    pub confidence: f64,
+    pub strategy: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemapWorkResponse {
+    pub work_id: String,
+    /// Variants given an automatic mapping.
+    pub mapped: usize,
+    /// Variants skipped because they were mapped by hand.
+    pub manual: usize,
+    /// Canonical chapters created for variants that matched none.
+    pub created_chapter_ids: Vec<String>,
+    /// Low-confidence mappings, flagged with `needsReview` in their data.
+    pub review: Vec<RemapReviewItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryComicsResponse {
+    pub items: Vec<DbRecord>,
+}
+
 #[derive(Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ImportComicBody {
@@ -116,3 +470,196 @@ pub struct ImportComicResponse {
     pub chapters_imported: usize,
     pub chapters_skipped: usize,
 }
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanItem {
+    /// `comic` or `chapter`.
+    pub kind: String,
+    /// Path relative to the comics directory.
+    pub path: String,
+    pub record_id: Option<String>,
+    pub reason: Option<String>,
+    pub candidate_ids: Vec<String>,
+}
+
+#[derive(Serialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanResponse {
+    pub scanned_archives: usize,
+    pub created: Vec<LibraryScanItem>,
+    pub matched: Vec<LibraryScanItem>,
+    pub ambiguous: Vec<LibraryScanItem>,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterAvailability {
+    pub chapter_id: String,
+    pub has_offline: bool,
+}
+
+#[derive(Clone, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineChangedEvent {
+    pub comic_id: String,
+    pub chapters: Vec<ChapterAvailability>,
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRenameBody {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRenameItem {
+    pub chapter_id: String,
+    pub from: String,
+    pub to: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRenameResponse {
+    pub dry_run: bool,
+    pub renamed: Vec<LibraryRenameItem>,
+    pub skipped: Vec<LibraryRenameItem>,
+}
+
+#[derive(Deserialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveVerifyBody {
+    /// Also re-read archives that have not changed since their last check.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveHealthItem {
+    pub chapter_id: String,
+    pub comic_id: String,
+    pub chapter_name: String,
+    pub local_path: Option<String>,
+    pub health: ArchiveHealth,
+}
+
+#[derive(Serialize, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveVerifyResponse {
+    pub checked: usize,
+    pub skipped: usize,
+    pub broken: Vec<ArchiveHealthItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveHealthResponse {
+    pub chapters: Vec<ArchiveHealthItem>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterStorageUsage {
+    pub chapter_id: String,
+    pub name: String,
+    pub local_path: Option<String>,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ComicStorageUsage {
+    pub comic_id: String,
+    pub name: String,
+    /// Comic folders, chapter archives kept elsewhere, and covers.
+    pub total_bytes: u64,
+    pub covers_bytes: u64,
+    pub chapters: Vec<ChapterStorageUsage>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsageResponse {
+    /// Everything under the comics and covers directories, orphans included.
+    pub total_bytes: u64,
+    pub comics: Vec<ComicStorageUsage>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanEntry {
+    /// `comics` or `covers`: the directory `path` is relative to.
+    pub location: String,
+    pub path: String,
+    /// `file` or `folder`.
+    pub kind: String,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanListResponse {
+    pub total_bytes: u64,
+    pub orphans: Vec<OrphanEntry>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanTarget {
+    pub location: String,
+    pub path: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanupBody {
+    /// `trash` (default) or `delete`. Deleting needs `only` or `confirm`.
+    pub mode: Option<String>,
+    /// Only reports what would be removed unless set to `false`.
+    #[serde(default = "default_dry_run")]
+    #[schema(default = true)]
+    pub dry_run: bool,
+    /// Confirms deleting every current orphan when `only` is omitted.
+    #[serde(default)]
+    pub confirm: bool,
+    /// Orphans to remove; all current orphans when omitted. Paths that are not orphans
+    /// are ignored.
+    pub only: Option<Vec<OrphanTarget>>,
+}
+
+impl Default for OrphanCleanupBody {
+    fn default() -> Self {
+        Self {
+            mode: None,
+            dry_run: default_dry_run(),
+            confirm: false,
+            only: None,
+        }
+    }
+}
+
+fn default_dry_run() -> bool {
+    true
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanupFailure {
+    pub entry: OrphanEntry,
+    pub error: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanCleanupResponse {
+    pub dry_run: bool,
+    pub mode: String,
+    pub removed: Vec<OrphanEntry>,
+    pub failed: Vec<OrphanCleanupFailure>,
+    pub freed_bytes: u64,
+}
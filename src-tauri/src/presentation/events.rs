@@ -0,0 +1,44 @@
+use tokio::sync::broadcast;
+
+use super::dto::OfflineChangedEvent;
+
+/// Events a subscriber may fall behind by before it starts missing them.
+const EVENT_BUFFER: usize = 64;
+
+/// Library changes pushed to REST clients over `GET /api/events`.
+#[derive(Clone, Debug)]
+pub enum LibraryEvent {
+    OfflineChanged(OfflineChangedEvent),
+}
+
+impl LibraryEvent {
+    /// Server-sent event name, shared with the matching Tauri event.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OfflineChanged(_) => "offline-changed",
+        }
+    }
+}
+
+/// Fan-out of library change notifications. Publishing without subscribers is a no-op.
+#[derive(Clone)]
+pub struct LibraryEvents {
+    sender: broadcast::Sender<LibraryEvent>,
+}
+
+impl Default for LibraryEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+}
+
+impl LibraryEvents {
+    pub fn publish(&self, event: LibraryEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub(super) fn subscribe(&self) -> broadcast::Receiver<LibraryEvent> {
+        self.sender.subscribe()
+    }
+}
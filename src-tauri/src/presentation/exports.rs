@@ -0,0 +1,148 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use axum::{
+    body::{Body, Bytes},
+    http::{header, StatusCode},
+    response::Response,
+};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use super::{chapter_display_name, chapter_external_pages, resolve_external_page_file_path};
+use crate::{
+    application::export_comic_info,
+    domain::{AppError, DbRecord},
+    infrastructure::{
+        archive::open_archive,
+        comic_info::read_comic_info,
+        export::{ExportChapter, ExportPages, PageSource},
+        library_files::legacy_sanitize_segment,
+    },
+};
+
+/// Bytes buffered before a chunk is handed to the response body.
+const EXPORT_CHUNK_BYTES: usize = 256 * 1024;
+/// Chunks queued ahead of a slow client before the writer waits for it.
+const EXPORT_QUEUED_CHUNKS: usize = 8;
+
+pub(super) type ExportWriter = BufWriter<ChannelWriter>;
+
+/// Where a chapter's pages come from: its local archive, or else the files and URLs listed
+/// in its `pages`. `None` when it has neither.
+pub(super) fn export_pages(
+    comics_dir: &Path,
+    chapter: &DbRecord,
+    archive_path: Option<PathBuf>,
+) -> Option<ExportPages> {
+    if let Some(path) = archive_path {
+        return Some(ExportPages::Archive(path));
+    }
+
+    let sources = chapter_external_pages(chapter)
+        .into_iter()
+        .map(|page| {
+            if page.source.starts_with("http://") || page.source.starts_with("https://") {
+                Some(PageSource::Url(page.source))
+            } else {
+                resolve_external_page_file_path(comics_dir, &page.source).map(PageSource::File)
+            }
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (!sources.is_empty()).then_some(ExportPages::Sources(sources))
+}
+
+/// Pairs the pages with the ComicInfo.xml to write next to them. Reads the archive's own
+/// ComicInfo.xml, so it belongs on a blocking thread.
+pub(super) fn export_chapter(
+    comic: &DbRecord,
+    chapter: &DbRecord,
+    pages: ExportPages,
+) -> ExportChapter {
+    let archived = match &pages {
+        // A malformed ComicInfo.xml only costs the fields the records do not have.
+        ExportPages::Archive(path) => open_archive(path)
+            .and_then(|archive| read_comic_info(archive.as_ref()))
+            .ok()
+            .flatten(),
+        ExportPages::Sources(_) => None,
+    };
+    ExportChapter {
+        name: chapter_display_name(chapter),
+        info: export_comic_info(comic, chapter, archived),
+        pages,
+    }
+}
+
+/// Streams the ZIP that `write` produces on a blocking thread as a download. A failure after
+/// the first bytes aborts the body, so a truncated download never looks complete.
+pub(super) fn stream_zip_download(
+    file_name: &str,
+    content_type: &str,
+    write: impl FnOnce(ExportWriter) -> Result<ExportWriter, AppError> + Send + 'static,
+) -> Result<Response, (StatusCode, String)> {
+    let (sender, receiver) = mpsc::channel(EXPORT_QUEUED_CHUNKS);
+    let error_sender = sender.clone();
+    tokio::task::spawn_blocking(move || {
+        let writer = BufWriter::with_capacity(EXPORT_CHUNK_BYTES, ChannelWriter { sender });
+        let result = write(writer).and_then(|mut writer| {
+            writer
+                .flush()
+                .map_err(|e| AppError::infrastructure(format!("Failed to send export: {e}")))
+        });
+        if let Err(error) = result {
+            eprintln!("Export failed: {error}");
+            let _ = error_sender.blocking_send(Err(io::Error::other(error.to_string())));
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CONTENT_DISPOSITION, content_disposition(file_name))
+        .body(Body::from_stream(ReceiverStream::new(receiver)))
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to build export response: {error}"),
+            )
+        })
+}
+
+/// `attachment` with an ASCII fallback name plus the exact name for clients that read
+/// `filename*` (RFC 6266).
+fn content_disposition(file_name: &str) -> String {
+    let encoded = file_name
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect::<String>();
+    format!(
+        "attachment; filename=\"{}\"; filename*=UTF-8''{encoded}",
+        legacy_sanitize_segment(file_name)
+    )
+}
+
+/// Hands written bytes to the response body, waiting while the client falls behind.
+pub(super) struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Export download closed"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
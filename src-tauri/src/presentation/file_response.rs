@@ -0,0 +1,508 @@
+use std::{
+    fs::{self, Metadata},
+    io::SeekFrom,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, Method, StatusCode},
+    response::Response,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use super::internal_error;
+use crate::infrastructure::{
+    archive::{ArchiveEntry, ArchiveReader},
+    content_hash::stable_hash,
+};
+
+const CACHE_CONTROL_VALUE: &str = "public, max-age=300";
+/// Largest archive entry served. Archive readers hand out whole entries, so an entry is held
+/// in memory while it is sent; pages are far smaller than this.
+const MAX_ARCHIVE_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Validators sent with every image response so clients can revalidate with
+/// `If-None-Match` / `If-Modified-Since` instead of downloading again.
+pub struct CacheValidators {
+    etag: String,
+    last_modified: Option<SystemTime>,
+}
+
+impl CacheValidators {
+    pub fn for_file(path: &Path, metadata: &Metadata) -> Self {
+        let last_modified = metadata.modified().ok();
+        Self {
+            etag: strong_etag(&[
+                &path.display().to_string(),
+                &modified_nanos(last_modified).to_string(),
+                &metadata.len().to_string(),
+            ]),
+            last_modified,
+        }
+    }
+
+    pub fn for_archive_entry(archive_path: &Path, entry: &ArchiveEntry) -> Self {
+        let last_modified = fs::metadata(archive_path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let content_key = entry
+            .crc32
+            .map(|crc| format!("crc:{crc:08x}"))
+            .unwrap_or_else(|| format!("size:{}", entry.size));
+        Self {
+            etag: strong_etag(&[
+                &archive_path.display().to_string(),
+                &modified_nanos(last_modified).to_string(),
+                &entry.file_name,
+                &content_key,
+            ]),
+            last_modified,
+        }
+    }
+
+    /// Validators for a derived rendition (e.g. a resized page): they change whenever the
+    /// source or the rendition options change.
+    pub fn for_variant(&self, variant_key: &str) -> Self {
+        Self {
+            etag: strong_etag(&[&self.etag, variant_key]),
+            last_modified: self.last_modified,
+        }
+    }
+
+    pub fn etag(&self) -> &str {
+        &self.etag
+    }
+
+    fn apply(&self, builder: axum::http::response::Builder) -> axum::http::response::Builder {
+        let builder = builder
+            .header(header::ETAG, &self.etag)
+            .header(header::CACHE_CONTROL, CACHE_CONTROL_VALUE);
+        match self.last_modified {
+            Some(modified) => {
+                builder.header(header::LAST_MODIFIED, httpdate::fmt_http_date(modified))
+            }
+            None => builder,
+        }
+    }
+
+    pub fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = header_str(headers, header::IF_NONE_MATCH) {
+            return if_none_match
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag.trim_start_matches("W/") == self.etag);
+        }
+
+        match (
+            header_str(headers, header::IF_MODIFIED_SINCE)
+                .and_then(|value| httpdate::parse_http_date(value).ok()),
+            self.last_modified,
+        ) {
+            (Some(since), Some(modified)) => truncate_to_seconds(modified) <= since,
+            _ => false,
+        }
+    }
+
+    /// `If-Range` only allows a partial response while the representation is unchanged.
+    fn allows_range(&self, headers: &HeaderMap) -> bool {
+        let Some(if_range) = header_str(headers, header::IF_RANGE) else {
+            return true;
+        };
+
+        if if_range.starts_with('"') {
+            return if_range == self.etag;
+        }
+
+        match (httpdate::parse_http_date(if_range).ok(), self.last_modified) {
+            (Some(date), Some(modified)) => truncate_to_seconds(modified) == date,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ByteRange {
+    Full,
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Streams a file from disk, honoring conditional requests, `HEAD` and single byte ranges.
+pub async fn serve_file(
+    method: &Method,
+    headers: &HeaderMap,
+    path: &Path,
+    content_type: &str,
+) -> Result<Response, (StatusCode, String)> {
+    let (file, metadata) = open_file(path).await?;
+    let validators = CacheValidators::for_file(path, &metadata);
+    stream_file(
+        method,
+        headers,
+        file,
+        metadata.len(),
+        &validators,
+        content_type,
+    )
+    .await
+}
+
+/// Like [`serve_file`], but with caller-supplied validators. Used for cached renditions whose
+/// identity comes from their source rather than from the cache file itself.
+pub async fn serve_file_with_validators(
+    method: &Method,
+    headers: &HeaderMap,
+    path: &Path,
+    validators: &CacheValidators,
+    content_type: &str,
+) -> Result<Response, (StatusCode, String)> {
+    let (file, metadata) = open_file(path).await?;
+    stream_file(
+        method,
+        headers,
+        file,
+        metadata.len(),
+        validators,
+        content_type,
+    )
+    .await
+}
+
+async fn open_file(path: &Path) -> Result<(tokio::fs::File, Metadata), (StatusCode, String)> {
+    let file = tokio::fs::File::open(path).await.map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to open file: {error}"),
+        )
+    })?;
+    let metadata = file.metadata().await.map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read file metadata: {error}"),
+        )
+    })?;
+    Ok((file, metadata))
+}
+
+async fn stream_file(
+    method: &Method,
+    headers: &HeaderMap,
+    mut file: tokio::fs::File,
+    total_len: u64,
+    validators: &CacheValidators,
+    content_type: &str,
+) -> Result<Response, (StatusCode, String)> {
+    if validators.is_not_modified(headers) {
+        return not_modified(validators);
+    }
+
+    let range = requested_range(headers, validators, total_len);
+    let (builder, start, len) = match range {
+        ByteRange::Full => (
+            response_builder(StatusCode::OK, validators, content_type),
+            0,
+            total_len,
+        ),
+        ByteRange::Partial { start, end } => (
+            response_builder(StatusCode::PARTIAL_CONTENT, validators, content_type).header(
+                header::CONTENT_RANGE,
+                format!("bytes {start}-{end}/{total_len}"),
+            ),
+            start,
+            end - start + 1,
+        ),
+        ByteRange::Unsatisfiable => return range_not_satisfiable(validators, total_len),
+    };
+    let builder = builder.header(header::CONTENT_LENGTH, len);
+
+    if method == Method::HEAD {
+        return finish(builder, Body::empty());
+    }
+
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await.map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to seek file: {error}"),
+            )
+        })?;
+    }
+
+    finish(
+        builder,
+        Body::from_stream(ReaderStream::new(file.take(len))),
+    )
+}
+
+/// Serves a single archive entry. The entry is only decoded when the client actually needs
+/// the bytes, so revalidation and `HEAD` stay cheap. Unlike loose files the entry is read
+/// into memory, so entries over [`MAX_ARCHIVE_ENTRY_BYTES`] are refused.
+pub fn serve_archive_entry(
+    method: &Method,
+    headers: &HeaderMap,
+    archive_path: &Path,
+    archive: &dyn ArchiveReader,
+    entry: &ArchiveEntry,
+    content_type: &str,
+) -> Result<Response, (StatusCode, String)> {
+    let validators = CacheValidators::for_archive_entry(archive_path, entry);
+    if validators.is_not_modified(headers) {
+        return not_modified(&validators);
+    }
+
+    if method == Method::HEAD
+        && requested_range(headers, &validators, entry.size) == ByteRange::Full
+    {
+        return finish(
+            response_builder(StatusCode::OK, &validators, content_type)
+                .header(header::CONTENT_LENGTH, entry.size),
+            Body::empty(),
+        );
+    }
+
+    let too_large = || {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Archive entry is larger than {MAX_ARCHIVE_ENTRY_BYTES} bytes"),
+        )
+    };
+    if entry.size > MAX_ARCHIVE_ENTRY_BYTES {
+        return Err(too_large());
+    }
+    // The stored size may be wrong, so the read itself is bounded too.
+    let bytes = archive
+        .read_entry_prefix(entry, MAX_ARCHIVE_ENTRY_BYTES + 1)
+        .map_err(internal_error)?;
+    let total_len = bytes.len() as u64;
+    if total_len > MAX_ARCHIVE_ENTRY_BYTES {
+        return Err(too_large());
+    }
+    let (builder, body) = match requested_range(headers, &validators, total_len) {
+        ByteRange::Full => (
+            response_builder(StatusCode::OK, &validators, content_type),
+            bytes,
+        ),
+        ByteRange::Partial { start, end } => (
+            response_builder(StatusCode::PARTIAL_CONTENT, &validators, content_type).header(
+                header::CONTENT_RANGE,
+                format!("bytes {start}-{end}/{total_len}"),
+            ),
+            bytes[start as usize..=end as usize].to_vec(),
+        ),
+        ByteRange::Unsatisfiable => return range_not_satisfiable(&validators, total_len),
+    };
+    let builder = builder.header(header::CONTENT_LENGTH, body.len());
+
+    if method == Method::HEAD {
+        return finish(builder, Body::empty());
+    }
+
+    finish(builder, Body::from(body))
+}
+
+fn requested_range(headers: &HeaderMap, validators: &CacheValidators, total_len: u64) -> ByteRange {
+    let Some(raw) = header_str(headers, header::RANGE) else {
+        return ByteRange::Full;
+    };
+    if !validators.allows_range(headers) {
+        return ByteRange::Full;
+    }
+
+    parse_range(raw, total_len)
+}
+
+/// Parses a single `bytes=` range. Multiple ranges are answered with the full body,
+/// which RFC 9110 allows.
+fn parse_range(raw: &str, total_len: u64) -> ByteRange {
+    let Some(spec) = raw.trim().strip_prefix("bytes=") else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') || total_len == 0 {
+        return ByteRange::Full;
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        let Ok(suffix) = end.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        if suffix == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        return ByteRange::Partial {
+            start: total_len.saturating_sub(suffix),
+            end: total_len - 1,
+        };
+    }
+
+    let Ok(start) = start.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    if start >= total_len {
+        return ByteRange::Unsatisfiable;
+    }
+
+    let end = if end.is_empty() {
+        total_len - 1
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end.min(total_len - 1),
+            _ => return ByteRange::Full,
+        }
+    };
+
+    ByteRange::Partial { start, end }
+}
+
+fn response_builder(
+    status: StatusCode,
+    validators: &CacheValidators,
+    content_type: &str,
+) -> axum::http::response::Builder {
+    validators
+        .apply(Response::builder().status(status))
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+}
+
+pub fn not_modified(validators: &CacheValidators) -> Result<Response, (StatusCode, String)> {
+    finish(
+        validators.apply(Response::builder().status(StatusCode::NOT_MODIFIED)),
+        Body::empty(),
+    )
+}
+
+fn range_not_satisfiable(
+    validators: &CacheValidators,
+    total_len: u64,
+) -> Result<Response, (StatusCode, String)> {
+    finish(
+        validators
+            .apply(Response::builder().status(StatusCode::RANGE_NOT_SATISFIABLE))
+            .header(header::CONTENT_RANGE, format!("bytes */{total_len}")),
+        Body::empty(),
+    )
+}
+
+fn finish(
+    builder: axum::http::response::Builder,
+    body: Body,
+) -> Result<Response, (StatusCode, String)> {
+    builder.body(body).map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to build response: {error}"),
+        )
+    })
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn strong_etag(parts: &[&str]) -> String {
+    format!("\"{}\"", stable_hash(parts))
+}
+
+fn modified_nanos(modified: Option<SystemTime>) -> u128 {
+    modified
+        .and_then(|value| value.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0)
+}
+
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| UNIX_EPOCH + Duration::from_secs(duration.as_secs()))
+        .unwrap_or(time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::archive::DirectoryReader;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=500-5000", 1000),
+            ByteRange::Partial {
+                start: 500,
+                end: 999
+            }
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), ByteRange::Full);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn matches_if_none_match_against_strong_etag() {
+        let validators = CacheValidators {
+            etag: strong_etag(&["chapter.cbz", "1", "crc:0000abcd"]),
+            last_modified: None,
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_str(&format!("\"other\", {}", validators.etag)).expect("header"),
+        );
+        assert!(validators.is_not_modified(&headers));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!validators.is_not_modified(&headers));
+    }
+
+    #[test]
+    fn refuses_archive_entries_over_the_size_bound() {
+        let temp = tempfile::tempdir().expect("create temp root");
+        let archive = DirectoryReader::new(temp.path());
+        let entry = ArchiveEntry {
+            archive_index: 0,
+            file_name: "001.jpg".to_string(),
+            size: MAX_ARCHIVE_ENTRY_BYTES + 1,
+            crc32: None,
+        };
+
+        let error = serve_archive_entry(
+            &Method::GET,
+            &HeaderMap::new(),
+            temp.path(),
+            &archive,
+            &entry,
+            "image/jpeg",
+        )
+        .expect_err("entry over the bound");
+        assert_eq!(error.0, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
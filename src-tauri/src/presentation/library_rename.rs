@@ -0,0 +1,226 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use super::{
+    chapter_display_name, chapter_value_as_string,
+    dto::{LibraryRenameItem, LibraryRenameResponse},
+    list_all_records, locate_chapter_archive,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord},
+    infrastructure::library_files::{
+        chapter_file_patterns, legacy_sanitize_segment, sanitize_segment, to_local_path,
+        LOCAL_PATH_FIELD,
+    },
+};
+
+/// Moves chapter archives saved under the legacy ASCII naming scheme to the names the
+/// current scheme gives them, and points each chapter's `localPath` at the new location.
+/// Existing files are never overwritten.
+pub fn rename_legacy_files(
+    service: &DocumentService,
+    comics_dir: &Path,
+    dry_run: bool,
+) -> Result<LibraryRenameResponse, AppError> {
+    let comic_names = list_all_records(service, "comics")?
+        .into_iter()
+        .filter_map(|comic| {
+            let name = chapter_value_as_string(&comic, "name")?;
+            Some((comic.id, name))
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut report = LibraryRenameResponse {
+        dry_run,
+        ..Default::default()
+    };
+    for chapter in list_all_records(service, "chapters")? {
+        let Some(comic_id) = chapter_value_as_string(&chapter, "comicId") else {
+            continue;
+        };
+        let comic_name = comic_names
+            .get(&comic_id)
+            .cloned()
+            .unwrap_or_else(|| comic_id.clone());
+        let Some(current) = locate_chapter_archive(comics_dir, &chapter, &comic_id, &comic_name)
+        else {
+            continue;
+        };
+        let Some(target) =
+            modern_archive_path(comics_dir, &current, &comic_id, &comic_name, &chapter)
+        else {
+            continue;
+        };
+
+        let item = |reason: Option<String>| LibraryRenameItem {
+            chapter_id: chapter.id.clone(),
+            from: to_local_path(comics_dir, &current).unwrap_or_default(),
+            to: to_local_path(comics_dir, &target).unwrap_or_default(),
+            reason,
+        };
+        if target.exists() {
+            report.skipped.push(item(Some(
+                "A file already exists under the new name".to_string(),
+            )));
+            continue;
+        }
+        if dry_run {
+            report.renamed.push(item(None));
+            continue;
+        }
+
+        match move_archive(&current, &target) {
+            Ok(()) => {
+                record_local_path(service, comics_dir, &chapter, &target)?;
+                report.renamed.push(item(None));
+            }
+            Err(error) => report.skipped.push(item(Some(error))),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Where the current scheme would put `current` if it was saved under a legacy name. The
+/// folder and the file stem are migrated separately, so `{name} (2)` folders and custom
+/// file names that the legacy scheme did not produce are left alone.
+fn modern_archive_path(
+    comics_dir: &Path,
+    current: &Path,
+    comic_id: &str,
+    comic_name: &str,
+    chapter: &DbRecord,
+) -> Option<PathBuf> {
+    let folder_path = current.parent()?;
+    if folder_path.parent()? != comics_dir {
+        return None;
+    }
+
+    let folder = folder_path.file_name()?.to_str()?;
+    let stem = if current.is_dir() {
+        current.file_name()?.to_str()?
+    } else {
+        current.file_stem()?.to_str()?
+    };
+    let extension = current
+        .is_file()
+        .then(|| current.extension().and_then(|value| value.to_str()))
+        .flatten();
+
+    let target_folder = modern_segment(folder, &[comic_name, comic_id]);
+    let patterns = chapter_file_patterns(
+        &chapter_display_name(chapter),
+        chapter_value_as_string(chapter, "number").as_deref(),
+    );
+    let target_stem = modern_segment(
+        stem,
+        &patterns.iter().map(String::as_str).collect::<Vec<_>>(),
+    );
+    if target_folder == folder && target_stem == stem {
+        return None;
+    }
+
+    let file_name = match extension {
+        Some(extension) => format!("{target_stem}.{extension}"),
+        None => target_stem,
+    };
+    Some(comics_dir.join(target_folder).join(file_name))
+}
+
+/// The current-scheme name for `segment` when it is the legacy form of one of `sources`
+/// and no source already produces it under the current scheme.
+fn modern_segment(segment: &str, sources: &[&str]) -> String {
+    if sources
+        .iter()
+        .any(|source| sanitize_segment(source) == segment)
+    {
+        return segment.to_string();
+    }
+
+    sources
+        .iter()
+        .find(|source| legacy_sanitize_segment(source) == segment)
+        .map(|source| sanitize_segment(source))
+        .unwrap_or_else(|| segment.to_string())
+}
+
+fn move_archive(current: &Path, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {e}"))?;
+    }
+    fs::rename(current, target).map_err(|e| format!("Failed to rename: {e}"))?;
+
+    // Drop the legacy folder once its last chapter has moved; covers and other files keep
+    // it in place, where lookups still find them.
+    if let Some(parent) = current.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
+
+fn record_local_path(
+    service: &DocumentService,
+    comics_dir: &Path,
+    chapter: &DbRecord,
+    path: &Path,
+) -> Result<(), AppError> {
+    let Some(local_path) = to_local_path(comics_dir, path) else {
+        return Ok(());
+    };
+
+    let mut data = chapter.data.clone();
+    if let Value::Object(ref mut map) = data {
+        map.insert(LOCAL_PATH_FIELD.to_string(), Value::String(local_path));
+    }
+    service.upsert("chapters", Some(chapter.id.clone()), data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renames_only_legacy_segments() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        let legacy = root.join("Caf_").join("Cap_tulo 1.cbz");
+        let custom = root.join("Caf_ (2)").join("Cap_tulo 1.cbz");
+        for path in [&legacy, &custom] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+        let chapter = DbRecord {
+            id: "chapter:1".to_string(),
+            data: json!({ "comicId": "comic:1", "name": "Capítulo 1", "number": "1" }),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+
+        assert_eq!(
+            modern_archive_path(root, &legacy, "comic:1", "Café", &chapter),
+            Some(root.join("Café").join("Capítulo 1.cbz"))
+        );
+        assert_eq!(
+            modern_archive_path(root, &custom, "comic:1", "Café", &chapter),
+            Some(root.join("Caf_ (2)").join("Capítulo 1.cbz"))
+        );
+        assert_eq!(
+            modern_archive_path(
+                root,
+                &root.join("Café").join("Capítulo 1.cbz"),
+                "comic:1",
+                "Café",
+                &chapter
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::{json, Value};
+
+use super::{
+    chapter_display_name, chapter_value_as_string,
+    dto::{LibraryScanItem, LibraryScanResponse},
+    list_all_records, ratings, record_extracted_cover, stable_import_id,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord, DocumentWrite},
+    infrastructure::{
+        archive::open_archive,
+        comic_info::read_comic_info,
+        export::PERSONAL_RATING_FILE_NAME,
+        library_files::{
+            chapter_file_candidates, comic_dir_matches, list_library, to_local_path,
+            LibraryChapterFile, LOCAL_PATH_FIELD,
+        },
+    },
+};
+
+const LOCAL_SOURCE_TAG: &str = "local";
+
+/// Walks the comics directory and creates `local` comics and chapters for archives that no
+/// record resolves to yet. Existing records are only ever marked offline, never renamed.
+/// Personal ratings saved by a comic export are restored for comics without one, and comics
+/// without a cover get one extracted from their first local chapter.
+pub fn scan_library(
+    service: &DocumentService,
+    comics_dir: &Path,
+    covers_dir: &Path,
+) -> Result<LibraryScanResponse, AppError> {
+    let listing = list_library(comics_dir, &[comics_dir.join("covers")]);
+    let comics = list_all_records(service, "comics")?;
+    let mut chapters_by_comic: HashMap<String, Vec<DbRecord>> = HashMap::new();
+    for chapter in list_all_records(service, "chapters")? {
+        if let Some(comic_id) = chapter_value_as_string(&chapter, "comicId") {
+            chapters_by_comic.entry(comic_id).or_default().push(chapter);
+        }
+    }
+
+    let mut report = LibraryScanResponse::default();
+    for path in &listing.loose_archives {
+        report.scanned_archives += 1;
+        report.ambiguous.push(scan_item(
+            "chapter",
+            comics_dir,
+            path,
+            None,
+            Some("Archive is not inside a comic folder"),
+            Vec::new(),
+        ));
+    }
+
+    for folder in &listing.folders {
+        report.scanned_archives += folder.chapters.len();
+        let matches = comics
+            .iter()
+            .filter(|comic| comic_matches_folder(comic, &folder.name))
+            .collect::<Vec<_>>();
+        let comic_id = match matches.as_slice() {
+            [] => {
+                let comic_id = stable_import_id("comic", &[LOCAL_SOURCE_TAG, &folder.name]);
+                service.upsert(
+                    "comics",
+                    Some(comic_id.clone()),
+                    json!({
+                        "name": folder.name,
+                        "sourceTag": LOCAL_SOURCE_TAG,
+                        "hasOffline": true,
+                        "offline": 1,
+                    }),
+                )?;
+                report.created.push(scan_item(
+                    "comic",
+                    comics_dir,
+                    &folder.path,
+                    Some(&comic_id),
+                    None,
+                    Vec::new(),
+                ));
+                comic_id
+            }
+            [comic] => {
+                report.matched.push(scan_item(
+                    "comic",
+                    comics_dir,
+                    &folder.path,
+                    Some(&comic.id),
+                    None,
+                    Vec::new(),
+                ));
+                comic.id.clone()
+            }
+            _ => {
+                report.ambiguous.push(scan_item(
+                    "comic",
+                    comics_dir,
+                    &folder.path,
+                    None,
+                    Some("Several comics resolve to this folder"),
+                    matches.iter().map(|comic| comic.id.clone()).collect(),
+                ));
+                continue;
+            }
+        };
+
+        let personal_rating = folder.path.join(PERSONAL_RATING_FILE_NAME);
+        if personal_rating.is_file() {
+            ratings::import_personal_rating(service, &comic_id, &personal_rating)?;
+        }
+
+        let existing = chapters_by_comic
+            .get(&comic_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for file in &folder.chapters {
+            scan_chapter(service, comics_dir, &comic_id, existing, file, &mut report)?;
+        }
+        record_extracted_cover(service, comics_dir, covers_dir, &comic_id)?;
+    }
+
+    Ok(report)
+}
+
+fn scan_chapter(
+    service: &DocumentService,
+    comics_dir: &Path,
+    comic_id: &str,
+    existing: &[DbRecord],
+    file: &LibraryChapterFile,
+    report: &mut LibraryScanResponse,
+) -> Result<(), AppError> {
+    let by_file_name = existing
+        .iter()
+        .filter(|chapter| {
+            chapter_file_candidates(
+                &chapter_display_name(chapter),
+                chapter_value_as_string(chapter, "number").as_deref(),
+            )
+            .contains(&file.stem)
+        })
+        .collect::<Vec<_>>();
+
+    match by_file_name.as_slice() {
+        [chapter] => {
+            mark_chapter_offline(service, chapter, to_local_path(comics_dir, &file.path))?;
+            merge_comic_info(service, &chapter.id, &file.path);
+            report.matched.push(scan_item(
+                "chapter",
+                comics_dir,
+                &file.path,
+                Some(&chapter.id),
+                None,
+                Vec::new(),
+            ));
+            return Ok(());
+        }
+        [_, _, ..] => {
+            report.ambiguous.push(scan_item(
+                "chapter",
+                comics_dir,
+                &file.path,
+                None,
+                Some("Several chapters resolve to this archive"),
+                by_file_name
+                    .iter()
+                    .map(|chapter| chapter.id.clone())
+                    .collect(),
+            ));
+            return Ok(());
+        }
+        [] => {}
+    }
+
+    // A chapter with the same number probably is this archive under another file name;
+    // creating a second one would duplicate it in the library.
+    let same_number = file
+        .parsed
+        .number
+        .as_deref()
+        .map(|number| {
+            existing
+                .iter()
+                .filter(|chapter| {
+                    chapter_value_as_string(chapter, "number")
+                        .is_some_and(|value| chapter_numbers_equal(&value, number))
+                })
+                .map(|chapter| chapter.id.clone())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if !same_number.is_empty() {
+        report.ambiguous.push(scan_item(
+            "chapter",
+            comics_dir,
+            &file.path,
+            None,
+            Some("A chapter with the same number exists under a different file name"),
+            same_number,
+        ));
+        return Ok(());
+    }
+
+    let chapter_id = stable_import_id("chapter", &[comic_id, &file.stem]);
+    let volume = file.parsed.volume.as_deref().map(|volume| {
+        volume
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::from(volume))
+    });
+    service.upsert(
+        "chapters",
+        Some(chapter_id.clone()),
+        json!({
+            "comicId": comic_id,
+            "name": file.stem,
+            "number": file.parsed.number.clone().unwrap_or_default(),
+            "volume": volume,
+            "title": file.parsed.title,
+            "pages": [],
+            "sourceTag": LOCAL_SOURCE_TAG,
+            "hasOffline": true,
+            "offline": 1,
+            LOCAL_PATH_FIELD: to_local_path(comics_dir, &file.path),
+        }),
+    )?;
+    merge_comic_info(service, &chapter_id, &file.path);
+    report.created.push(scan_item(
+        "chapter",
+        comics_dir,
+        &file.path,
+        Some(&chapter_id),
+        None,
+        Vec::new(),
+    ));
+    Ok(())
+}
+
+fn comic_matches_folder(comic: &DbRecord, folder_name: &str) -> bool {
+    let comic_name = chapter_value_as_string(comic, "name").unwrap_or_else(|| comic.id.clone());
+    comic_dir_matches(&comic.id, &comic_name, folder_name)
+}
+
+fn chapter_numbers_equal(left: &str, right: &str) -> bool {
+    match (left.trim().parse::<f64>(), right.trim().parse::<f64>()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left.trim().eq_ignore_ascii_case(right.trim()),
+    }
+}
+
+pub(super) fn mark_chapter_offline(
+    service: &DocumentService,
+    chapter: &DbRecord,
+    local_path: Option<String>,
+) -> Result<(), AppError> {
+    let has_offline = chapter
+        .data
+        .get("hasOffline")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let offline = chapter
+        .data
+        .get("offline")
+        .and_then(Value::as_i64)
+        .unwrap_or(0)
+        != 0;
+    let stored_path = chapter_value_as_string(chapter, LOCAL_PATH_FIELD);
+    if has_offline && offline && (local_path.is_none() || local_path == stored_path) {
+        return Ok(());
+    }
+
+    // Only the availability fields are written, so edits made since the scan read the
+    // chapter are kept.
+    let mut writes = vec![
+        DocumentWrite::set_field("chapters", &chapter.id, "hasOffline", Value::Bool(true))?,
+        DocumentWrite::set_field("chapters", &chapter.id, "offline", Value::from(1))?,
+    ];
+    if let Some(local_path) = local_path {
+        writes.push(DocumentWrite::set_field(
+            "chapters",
+            &chapter.id,
+            LOCAL_PATH_FIELD,
+            Value::String(local_path),
+        )?);
+    }
+    service.write_batch(&writes)?;
+    Ok(())
+}
+
+/// Merges the ComicInfo.xml of a chapter archive into the chapter and its comic when the
+/// archive is scanned or uploaded. Best effort: unreadable archives are still reported as
+/// scanned.
+pub(super) fn merge_comic_info(service: &DocumentService, chapter_id: &str, path: &Path) {
+    let result = open_archive(path)
+        .and_then(|archive| read_comic_info(archive.as_ref()))
+        .and_then(|info| match info {
+            Some(info) => service.apply_comic_info(chapter_id, &info),
+            None => Ok(false),
+        });
+    if let Err(error) = result {
+        eprintln!("Failed to merge ComicInfo.xml for chapter {chapter_id}: {error}");
+    }
+}
+
+fn scan_item(
+    kind: &str,
+    comics_dir: &Path,
+    path: &Path,
+    record_id: Option<&str>,
+    reason: Option<&str>,
+    candidate_ids: Vec<String>,
+) -> LibraryScanItem {
+    LibraryScanItem {
+        kind: kind.to_string(),
+        path: path
+            .strip_prefix(comics_dir)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/"),
+        record_id: record_id.map(str::to_string),
+        reason: reason.map(str::to_string),
+        candidate_ids,
+    }
+}
@@ -1,9 +1,22 @@
+mod archive_health;
+mod bookmarks;
+mod chapter_mappings;
+mod collections;
 mod dto;
+mod events;
+mod exports;
+mod file_response;
+mod library_rename;
+mod library_scan;
+mod ratings;
+mod reading;
+mod storage;
+mod uploads;
 
 use std::{
-    collections::HashSet,
-    fs::{self, File},
-    io::{ErrorKind, Read},
+    convert::Infallible,
+    fs,
+    io::ErrorKind,
     net::TcpListener,
     path::{Path as FsPath, PathBuf},
     sync::Mutex,
@@ -11,30 +24,75 @@ use std::{
 use serde_json::{Map, Value};
 
 use axum::{
-    extract::{Path, Query, State},
-    http::{header, StatusCode},
-    response::{IntoResponse, Redirect, Response},
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{HeaderMap, Method, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Redirect, Response,
+    },
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use mime_guess::from_path;
 use tauri::async_runtime;
 use tokio::sync::oneshot;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
 use tower_http::cors::CorsLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
-use zip::ZipArchive;
+use uuid::Uuid;
 
 use crate::{
     application::{AdminService, DocumentService},
-    domain::{AppError, DbRecord},
+    domain::{
+        AppError, ArchiveHealth, ArchiveHealthStatus, ChapterSelection, ComicInfo, ComicInfoPage,
+        ComicProgress, ComicTag, DbRecord, LibraryFilter, LibrarySort, ReadingComicStat,
+        ReadingDay, ReadingGenreStat, ReadingStats, ReadingStatsRange, TagSummary,
+    },
+    infrastructure::{
+        archive::{is_image_file, list_image_entries, natural_cmp, open_archive},
+        comic_info::read_comic_info,
+        covers::{extract_archive_cover, extracted_cover_path, save_extracted_cover},
+        export::{write_chapter_cbz, write_comic_zip},
+        image_variants::{render_variant, ImageVariantCache, ImageVariantSpec},
+        library_files::{
+            canonical_chapter_archive_path, comic_dir_candidates, guess_chapter_archive,
+            legacy_sanitize_segment, resolve_local_path, LOCAL_PATH_FIELD,
+        },
+        page_metadata::{PageMetadata, PageMetadataCache},
+    },
 };
-pub use dto::ApiEndpointPayload;
 use dto::{
-    ChapterPage, ChapterPagesResponse, DeleteResponse, ErrorResponse, FindBody, HealthResponse,
-    ImportComicBody, ImportComicResponse, ListQuery, MarkChaptersBody, MarkChaptersResponse,
-    MigrateLegacyBody, MigrateLegacyResponse, UpsertBody,
+    AddCollectionItemsBody, ArchiveHealthItem, ArchiveHealthResponse, ArchiveVerifyBody,
+    ArchiveVerifyResponse, Bookmark, BookmarkListResponse, BookmarkRegion, ChapterPage,
+    ChapterPagesResponse, ChapterStorageUsage, CollectionBody, CollectionDetail, CollectionItem,
+    CollectionItemRef, CollectionListResponse, CollectionSummary, ComicStorageUsage,
+    ComicTagListResponse, ComicTagsBody, ContinueReadingItem, ContinueReadingQuery,
+    ContinueReadingResponse, CreateBookmarkBody, DeleteResponse, ErrorResponse, FindBody,
+    HealthResponse, ImageVariantQuery, ImportComicBody, ImportComicResponse,
+    ImportPersonalRatingsBody, ImportPersonalRatingsResponse, ImportedPersonalRating,
+    LibraryComicsQuery, LibraryComicsResponse, LibraryProgressResponse, LibraryRenameBody,
+    LibraryRenameItem, LibraryRenameResponse, LibraryScanItem, LibraryScanResponse, ListQuery,
+    MarkChaptersBody, MarkChaptersResponse, MigrateLegacyBody, MigrateLegacyResponse,
+    NextChapterResponse, OrphanCleanupBody, OrphanCleanupFailure, OrphanCleanupResponse,
+    OrphanEntry, OrphanListResponse, OrphanTarget, PersonalRating, PersonalRatingBody,
+    PersonalRatingListResponse, ReadingStatsQuery, RemapReviewItem, RemapWorkResponse,
+    ReorderCollectionItemsBody, RepairProgressResponse, StorageUsageResponse, TagListResponse,
+    UpdateTagBody, UpsertBody,
+};
+pub use dto::{ApiEndpointPayload, ChapterAvailability, OfflineChangedEvent};
+pub use events::{LibraryEvent, LibraryEvents};
+use file_response::{
+    not_modified, serve_archive_entry, serve_file, serve_file_with_validators, CacheValidators,
 };
+use uploads::{body_limit, CUSTOM_COVER_FIELD, MAX_ARCHIVE_UPLOAD_BYTES, MAX_COVER_UPLOAD_BYTES};
+
+const LIST_PAGE_SIZE: u32 = 500;
+const DEFAULT_STATS_TOP_LIMIT: usize = 10;
+const DEFAULT_CONTINUE_READING_LIMIT: usize = 20;
+const MAX_CONTINUE_READING_LIMIT: usize = 100;
+/// Comic field naming the cover extracted into the covers directory.
+const LOCAL_COVER_FIELD: &str = "localCover";
 
 #[derive(Clone)]
 struct RestState {
@@ -42,18 +100,17 @@ struct RestState {
     admin_service: AdminService,
     admin_enabled: bool,
     comics_dir: PathBuf,
-}
-
-#[derive(Clone)]
-struct CbzPageEntry {
-    archive_index: usize,
-    file_name: String,
+    covers_dir: PathBuf,
+    image_cache: ImageVariantCache,
+    page_metadata: PageMetadataCache,
+    events: LibraryEvents,
 }
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         health,
+        stream_events,
         upsert_record,
         get_record,
         list_records,
@@ -61,14 +118,60 @@ struct CbzPageEntry {
         delete_record,
         list_chapter_pages,
         get_chapter_page,
+        get_chapter_comic_info,
+        export_chapter,
         get_comic_cover,
+        upload_comic_cover,
+        upload_chapter_archive,
+        export_comic,
         mark_chapters_read_state,
+        repair_progress_page_counts,
+        get_next_chapter,
+        get_continue_reading,
+        get_library_progress,
+        get_comic_progress,
+        create_bookmark,
+        list_comic_bookmarks,
+        list_chapter_bookmarks,
+        delete_bookmark,
+        list_collections,
+        create_collection,
+        get_collection,
+        update_collection,
+        delete_collection,
+        add_collection_items,
+        remove_collection_item,
+        reorder_collection_items,
+        list_tags,
+        update_tag,
+        get_comic_tags,
+        set_comic_tags,
+        list_library_comics,
+        get_comic_rating,
+        set_comic_rating,
+        get_work_rating,
+        set_work_rating,
+        export_ratings,
+        import_ratings,
+        remap_work,
+        get_reading_stats,
         import_comic,
-        migrate_legacy
+        scan_library,
+        rename_legacy_files,
+        get_storage_usage,
+        list_storage_orphans,
+        clean_storage_orphans,
+        migrate_legacy,
+        verify_archives,
+        get_archives_health
     ),
     components(
         schemas(
             DbRecord,
+            ChapterAvailability,
+            OfflineChangedEvent,
+            ComicInfo,
+            ComicInfoPage,
             UpsertBody,
             FindBody,
             HealthResponse,
@@ -78,10 +181,67 @@ struct CbzPageEntry {
             ChapterPagesResponse,
             MarkChaptersBody,
             MarkChaptersResponse,
+            RepairProgressResponse,
+            ContinueReadingItem,
+            NextChapterResponse,
+            ContinueReadingResponse,
+            ComicProgress,
+            LibraryProgressResponse,
+            BookmarkRegion,
+            CreateBookmarkBody,
+            Bookmark,
+            BookmarkListResponse,
+            CollectionBody,
+            CollectionItemRef,
+            AddCollectionItemsBody,
+            ReorderCollectionItemsBody,
+            CollectionSummary,
+            CollectionItem,
+            CollectionDetail,
+            CollectionListResponse,
+            TagSummary,
+            TagListResponse,
+            UpdateTagBody,
+            ComicTag,
+            ComicTagsBody,
+            ComicTagListResponse,
+            LibraryComicsResponse,
+            PersonalRatingBody,
+            PersonalRating,
+            PersonalRatingListResponse,
+            ImportedPersonalRating,
+            ImportPersonalRatingsBody,
+            ImportPersonalRatingsResponse,
+            RemapReviewItem,
+            RemapWorkResponse,
+            ReadingStats,
+            ReadingDay,
+            ReadingComicStat,
+            ReadingGenreStat,
             ImportComicBody,
             ImportComicResponse,
+            LibraryScanItem,
+            LibraryScanResponse,
+            LibraryRenameBody,
+            LibraryRenameItem,
+            LibraryRenameResponse,
+            ChapterStorageUsage,
+            ComicStorageUsage,
+            StorageUsageResponse,
+            OrphanEntry,
+            OrphanListResponse,
+            OrphanTarget,
+            OrphanCleanupBody,
+            OrphanCleanupFailure,
+            OrphanCleanupResponse,
             MigrateLegacyBody,
-            MigrateLegacyResponse
+            MigrateLegacyResponse,
+            ArchiveHealth,
+            ArchiveHealthStatus,
+            ArchiveHealthItem,
+            ArchiveHealthResponse,
+            ArchiveVerifyBody,
+            ArchiveVerifyResponse
         )
     ),
     tags(
@@ -93,6 +253,7 @@ struct ApiDoc;
 pub struct RestApiState {
     shutdown: Mutex<Option<oneshot::Sender<()>>>,
     endpoint: ApiEndpointPayload,
+    events: LibraryEvents,
 }
 
 impl RestApiState {
@@ -100,6 +261,10 @@ impl RestApiState {
         self.endpoint.clone()
     }
 
+    pub fn events(&self) -> LibraryEvents {
+        self.events.clone()
+    }
+
     pub fn stop(&self) {
         if let Ok(mut guard) = self.shutdown.lock() {
             if let Some(tx) = guard.take() {
@@ -113,6 +278,8 @@ pub fn start_rest_api(
     service: DocumentService,
     admin_service: AdminService,
     comics_dir: PathBuf,
+    covers_dir: PathBuf,
+    image_cache_dir: PathBuf,
 ) -> Result<RestApiState, String> {
     let preferred_port = std::env::var("REST_API_PORT")
         .ok()
@@ -132,11 +299,16 @@ pub fn start_rest_api(
         .map_err(|e| format!("Failed to configure REST API listener: {e}"))?;
 
     let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let events = LibraryEvents::default();
     let app = build_router(RestState {
         service,
         admin_service,
         admin_enabled: admin_endpoints_enabled(),
         comics_dir,
+        covers_dir,
+        image_cache: ImageVariantCache::new(image_cache_dir, image_cache_max_bytes()),
+        page_metadata: PageMetadataCache::new(),
+        events: events.clone(),
     });
 
     async_runtime::spawn(async move {
@@ -160,6 +332,7 @@ pub fn start_rest_api(
     Ok(RestApiState {
         shutdown: Mutex::new(Some(shutdown_tx)),
         endpoint,
+        events,
     })
 }
 
@@ -185,6 +358,7 @@ fn bind_listener(preferred_port: u16) -> Result<(TcpListener, u16), String> {
 fn build_router(state: RestState) -> Router {
     let mut router = Router::new()
         .route("/api/health", get(health))
+        .route("/api/events", get(stream_events))
         .route("/api/db/{table}", get(list_records).post(upsert_record))
         .route(
             "/api/db/{table}/{id}",
@@ -196,14 +370,103 @@ fn build_router(state: RestState) -> Router {
             "/api/chapters/{chapter_id}/pages/{page_index}",
             get(get_chapter_page),
         )
-        .route("/api/comics/{comic_id}/cover", get(get_comic_cover))
+        .route(
+            "/api/chapters/{chapter_id}/comicinfo",
+            get(get_chapter_comic_info),
+        )
+        .route("/api/chapters/{chapter_id}/export.cbz", get(export_chapter))
+        .route(
+            "/api/comics/{comic_id}/cover",
+            get(get_comic_cover)
+                .put(upload_comic_cover)
+                .layer(DefaultBodyLimit::max(body_limit(MAX_COVER_UPLOAD_BYTES))),
+        )
+        .route(
+            "/api/chapters/{chapter_id}/archive",
+            put(upload_chapter_archive)
+                .layer(DefaultBodyLimit::max(body_limit(MAX_ARCHIVE_UPLOAD_BYTES))),
+        )
+        .route("/api/comics/{comic_id}/export.zip", get(export_comic))
         .route("/api/chapters/mark", post(mark_chapters_read_state))
+        .route(
+            "/api/library/repair-progress",
+            post(repair_progress_page_counts),
+        )
+        .route("/api/comics/{comic_id}/next", get(get_next_chapter))
+        .route("/api/library/continue-reading", get(get_continue_reading))
+        .route("/api/library/progress", get(get_library_progress))
+        .route("/api/comics/{comic_id}/progress", get(get_comic_progress))
+        .route("/api/bookmarks", post(create_bookmark))
+        .route("/api/bookmarks/{bookmark_id}", delete(delete_bookmark))
+        .route(
+            "/api/comics/{comic_id}/bookmarks",
+            get(list_comic_bookmarks),
+        )
+        .route(
+            "/api/chapters/{chapter_id}/bookmarks",
+            get(list_chapter_bookmarks),
+        )
+        .route(
+            "/api/collections",
+            get(list_collections).post(create_collection),
+        )
+        .route(
+            "/api/collections/{collection_id}",
+            get(get_collection)
+                .put(update_collection)
+                .delete(delete_collection),
+        )
+        .route(
+            "/api/collections/{collection_id}/items",
+            post(add_collection_items),
+        )
+        .route(
+            "/api/collections/{collection_id}/items/order",
+            put(reorder_collection_items),
+        )
+        .route(
+            "/api/collections/{collection_id}/items/{item_id}",
+            delete(remove_collection_item),
+        )
+        .route("/api/tags", get(list_tags))
+        .route("/api/tags/{tag_id}", put(update_tag))
+        .route(
+            "/api/comics/{comic_id}/tags",
+            get(get_comic_tags).put(set_comic_tags),
+        )
+        .route("/api/library/comics", get(list_library_comics))
+        .route(
+            "/api/comics/{comic_id}/rating",
+            get(get_comic_rating).put(set_comic_rating),
+        )
+        .route(
+            "/api/works/{work_id}/rating",
+            get(get_work_rating).put(set_work_rating),
+        )
+        .route("/api/ratings/export", get(export_ratings))
+        .route("/api/ratings/import", post(import_ratings))
+        .route("/api/works/{work_id}/remap", post(remap_work))
+        .route("/api/stats/reading", get(get_reading_stats))
         .route("/api/import/comic", post(import_comic))
+        .route("/api/library/scan", post(scan_library))
+        .route("/api/storage/usage", get(get_storage_usage))
+        .route("/api/storage/orphans", get(list_storage_orphans))
         .merge(SwaggerUi::new("/swagger").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .layer(CorsLayer::permissive());
 
     if state.admin_enabled {
-        router = router.route("/api/admin/migrate-legacy", post(migrate_legacy));
+        router = router
+            .route("/api/admin/migrate-legacy", post(migrate_legacy))
+            .route(
+                "/api/admin/library/rename-legacy",
+                post(rename_legacy_files),
+            )
+            .route(
+                "/api/admin/storage/orphans/cleanup",
+                post(clean_storage_orphans),
+            )
+            .route("/api/admin/archives/verify", post(verify_archives))
+            .route("/api/admin/archives/health", get(get_archives_health));
     }
 
     router.with_state(state)
@@ -223,6 +486,29 @@ async fn health() -> impl IntoResponse {
     })
 }
 
+/// Server-sent library change notifications. The event name says which payload follows;
+/// slow clients silently skip the events they fell behind on.
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "db",
+    responses(
+        (status = 200, description = "Event stream; `offline-changed` events carry this payload", body = OfflineChangedEvent, content_type = "text/event-stream")
+    )
+)]
+async fn stream_events(
+    State(state): State<RestState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = BroadcastStream::new(state.events.subscribe()).filter_map(|event| {
+        let event = event.ok()?;
+        let data = match &event {
+            LibraryEvent::OfflineChanged(payload) => Event::default().json_data(payload),
+        };
+        data.ok().map(|data| Ok(data.event(event.name())))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
 #[utoipa::path(
     post,
     path = "/api/db/{table}",
@@ -360,7 +646,7 @@ async fn delete_record(
     ),
     responses(
         (status = 200, description = "Chapter page URLs", body = ChapterPagesResponse),
-        (status = 404, description = "Chapter or CBZ not found", body = ErrorResponse),
+        (status = 404, description = "Chapter or archive not found", body = ErrorResponse),
         (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
@@ -368,9 +654,94 @@ async fn list_chapter_pages(
     State(state): State<RestState>,
     Path(chapter_id): Path<String>,
 ) -> Result<Json<ChapterPagesResponse>, (StatusCode, String)> {
+    let ChapterSource {
+        chapter,
+        comic_id,
+        comic_name,
+        chapter_name,
+        archive_path: cbz_path,
+        ..
+    } = resolve_chapter_source(&state, &chapter_id)?;
+
+    // Reading page headers touches every entry of the archive.
+    let pages = {
+        let chapter_id = chapter_id.clone();
+        tokio::task::spawn_blocking(move || {
+            read_chapter_pages(&state, &chapter_id, &chapter, cbz_path.as_deref())
+        })
+        .await
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Page listing task failed: {error}"),
+            )
+        })??
+    };
+
+    Ok(Json(ChapterPagesResponse {
+        chapter_id,
+        comic_id,
+        comic_name,
+        chapter_name,
+        page_count: pages.len(),
+        pages,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/chapters/{chapter_id}/comicinfo",
+    tag = "db",
+    params(
+        ("chapter_id" = String, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "ComicInfo.xml metadata of the chapter archive", body = ComicInfo),
+        (status = 404, description = "Chapter, archive or ComicInfo.xml not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_chapter_comic_info(
+    State(state): State<RestState>,
+    Path(chapter_id): Path<String>,
+) -> Result<Json<ComicInfo>, (StatusCode, String)> {
+    let archive_path = resolve_chapter_source(&state, &chapter_id)?
+        .archive_path
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Chapter has no local archive".to_string(),
+            )
+        })?;
+    let archive = open_archive(&archive_path).map_err(internal_error)?;
+    let info = read_comic_info(archive.as_ref())
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Chapter archive has no ComicInfo.xml".to_string(),
+            )
+        })?;
+    Ok(Json(info))
+}
+
+/// Chapter record together with its comic and the local archive it resolves to, if any.
+struct ChapterSource {
+    chapter: DbRecord,
+    comic: DbRecord,
+    comic_id: String,
+    comic_name: String,
+    chapter_name: String,
+    archive_path: Option<PathBuf>,
+}
+
+fn resolve_chapter_source(
+    state: &RestState,
+    chapter_id: &str,
+) -> Result<ChapterSource, (StatusCode, String)> {
     let chapter = state
         .service
-        .get("chapters", &chapter_id)
+        .get("chapters", chapter_id)
         .map_err(internal_error)?
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Chapter not found".to_string()))?;
 
@@ -381,7 +752,6 @@ async fn list_chapter_pages(
         )
     })?;
     let chapter_name = chapter_display_name(&chapter);
-    let chapter_number = chapter_value_as_string(&chapter, "number");
 
     let comic = state
         .service
@@ -390,57 +760,209 @@ async fn list_chapter_pages(
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
     let comic_name = chapter_value_as_string(&comic, "name").unwrap_or_else(|| comic_id.clone());
 
-    let cbz_path = resolve_chapter_cbz_path(
-        &state.comics_dir,
-        &comic_id,
-        &comic_name,
-        &chapter_name,
-        chapter_number.as_deref(),
-    );
+    let archive_path = locate_chapter_archive(&state.comics_dir, &chapter, &comic_id, &comic_name);
 
-    let pages = if let Some(cbz_path) = cbz_path {
-        let entries = list_image_entries(&cbz_path).map_err(internal_error)?;
-        if entries.is_empty() {
-            return Err((StatusCode::NOT_FOUND, "CBZ has no image pages".to_string()));
-        }
+    Ok(ChapterSource {
+        chapter,
+        comic,
+        comic_id,
+        comic_name,
+        chapter_name,
+        archive_path,
+    })
+}
 
-        entries
-            .iter()
-            .enumerate()
-            .map(|(index, entry)| ChapterPage {
-                index,
-                file_name: entry.file_name.clone(),
-                url: format!("/api/chapters/{chapter_id}/pages/{index}"),
-            })
-            .collect::<Vec<_>>()
-    } else {
-        let external_pages = chapter_external_pages(&chapter);
-        if external_pages.is_empty() {
+#[utoipa::path(
+    get,
+    path = "/api/chapters/{chapter_id}/export.cbz",
+    tag = "db",
+    params(
+        ("chapter_id" = String, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "CBZ with the chapter's pages and a generated ComicInfo.xml", content_type = "application/vnd.comicbook+zip"),
+        (status = 404, description = "Chapter has no local archive or resolvable pages", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn export_chapter(
+    State(state): State<RestState>,
+    Path(chapter_id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let ChapterSource {
+        chapter,
+        comic,
+        chapter_name,
+        archive_path,
+        ..
+    } = resolve_chapter_source(&state, &chapter_id)?;
+    let pages =
+        exports::export_pages(&state.comics_dir, &chapter, archive_path).ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Chapter has no local archive or resolvable pages".to_string(),
+            )
+        })?;
+
+    exports::stream_zip_download(
+        &format!("{chapter_name}.cbz"),
+        "application/vnd.comicbook+zip",
+        move |writer| {
+            let chapter = exports::export_chapter(&comic, &chapter, pages);
+            write_chapter_cbz(writer, &chapter)
+        },
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/export.zip",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "ZIP with one CBZ per exportable chapter, in reading order", content_type = "application/zip"),
+        (status = 404, description = "Comic not found or without exportable chapters", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn export_comic(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Response, (StatusCode, String)> {
+    let comic = state
+        .service
+        .get("comics", &comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+    let comic_name = chapter_value_as_string(&comic, "name").unwrap_or_else(|| comic_id.clone());
+    let personal_rating =
+        ratings::export_personal_rating(&state.service, &comic_id).map_err(internal_error)?;
+
+    let mut chapters = state
+        .service
+        .find_by_json_field(
+            "chapters",
+            "comicId",
+            Value::String(comic_id.clone()),
+            Some(u32::MAX),
+        )
+        .map_err(internal_error)?;
+    chapters.sort_by(compare_chapter_order);
+    // Chapters that were never downloaded and list no pages have nothing to export.
+    let exportable = chapters
+        .into_iter()
+        .filter_map(|chapter| {
+            let archive_path =
+                locate_chapter_archive(&state.comics_dir, &chapter, &comic_id, &comic_name);
+            let pages = exports::export_pages(&state.comics_dir, &chapter, archive_path)?;
+            Some((chapter, pages))
+        })
+        .collect::<Vec<_>>();
+    if exportable.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Comic has no chapters with a local archive or resolvable pages".to_string(),
+        ));
+    }
+
+    exports::stream_zip_download(
+        &format!("{comic_name}.zip"),
+        "application/zip",
+        move |writer| {
+            let chapters = exportable
+                .into_iter()
+                .map(|(chapter, pages)| exports::export_chapter(&comic, &chapter, pages));
+            write_comic_zip(writer, &comic_name, personal_rating.as_ref(), chapters)
+        },
+    )
+}
+
+/// The stored `localPath` when its file still exists, otherwise the first archive found
+/// under the names the chapter may have been saved with.
+fn locate_chapter_archive(
+    comics_dir: &FsPath,
+    chapter: &DbRecord,
+    comic_id: &str,
+    comic_name: &str,
+) -> Option<PathBuf> {
+    chapter_value_as_string(chapter, LOCAL_PATH_FIELD)
+        .and_then(|local_path| resolve_local_path(comics_dir, &local_path))
+        .or_else(|| {
+            guess_chapter_archive(
+                &comic_dir_candidates(comics_dir, comic_id, comic_name),
+                &chapter_display_name(chapter),
+                chapter_value_as_string(chapter, "number").as_deref(),
+            )
+        })
+}
+
+/// Pages of a chapter with their metadata, from its archive or its external pages list.
+fn read_chapter_pages(
+    state: &RestState,
+    chapter_id: &str,
+    chapter: &DbRecord,
+    archive_path: Option<&FsPath>,
+) -> Result<Vec<ChapterPage>, (StatusCode, String)> {
+    if let Some(archive_path) = archive_path {
+        let archive = open_archive(archive_path).map_err(internal_error)?;
+        let entries = list_image_entries(archive.as_ref()).map_err(internal_error)?;
+        if entries.is_empty() {
             return Err((
                 StatusCode::NOT_FOUND,
-                "Chapter has no local CBZ and no pages array in database".to_string(),
+                "Chapter archive has no image pages".to_string(),
             ));
         }
 
-        external_pages
+        let metadata = state
+            .page_metadata
+            .get_or_read(archive_path, archive.as_ref(), &entries);
+        return Ok(entries
             .iter()
+            .zip(metadata.iter())
             .enumerate()
-            .map(|(index, entry)| ChapterPage {
-                index,
-                file_name: entry.file_name.clone(),
-                url: format!("/api/chapters/{chapter_id}/pages/{index}"),
+            .map(|(index, (entry, metadata))| {
+                chapter_page(chapter_id, index, &entry.file_name, Some(metadata))
             })
-            .collect::<Vec<_>>()
-    };
+            .collect());
+    }
 
-    Ok(Json(ChapterPagesResponse {
-        chapter_id,
-        comic_id,
-        comic_name,
-        chapter_name,
-        page_count: pages.len(),
-        pages,
-    }))
+    let external_pages = chapter_external_pages(chapter);
+    if external_pages.is_empty() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Chapter has no local archive and no pages array in database".to_string(),
+        ));
+    }
+
+    Ok(external_pages
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let metadata = resolve_external_page_file_path(&state.comics_dir, &entry.source)
+                .and_then(|path| PageMetadata::from_file(&path).ok());
+            chapter_page(chapter_id, index, &entry.file_name, metadata.as_ref())
+        })
+        .collect())
+}
+
+fn chapter_page(
+    chapter_id: &str,
+    index: usize,
+    file_name: &str,
+    metadata: Option<&PageMetadata>,
+) -> ChapterPage {
+    ChapterPage {
+        index,
+        file_name: file_name.to_string(),
+        url: format!("/api/chapters/{chapter_id}/pages/{index}"),
+        width: metadata.and_then(|metadata| metadata.width),
+        height: metadata.and_then(|metadata| metadata.height),
+        byte_size: metadata.map(|metadata| metadata.byte_size),
+        mime_type: metadata.map(|metadata| metadata.mime_type.clone()),
+        is_spread: metadata.is_some_and(|metadata| metadata.is_spread),
+    }
 }
 
 #[utoipa::path(
@@ -449,50 +971,38 @@ async fn list_chapter_pages(
     tag = "db",
     params(
         ("chapter_id" = String, Path, description = "Chapter id"),
-        ("page_index" = usize, Path, description = "Page index (0-based)")
+        ("page_index" = usize, Path, description = "Page index (0-based)"),
+        ("w" = Option<u32>, Query, description = "Resize to at most this width"),
+        ("h" = Option<u32>, Query, description = "Resize to at most this height"),
+        ("fit" = Option<String>, Query, description = "contain (default), cover or fill"),
+        ("format" = Option<String>, Query, description = "jpeg (default) or webp")
     ),
     responses(
         (status = 200, description = "Image bytes"),
+        (status = 206, description = "Requested byte range of the image"),
+        (status = 304, description = "Image unchanged since the cached copy"),
         (status = 404, description = "Page not found", body = ErrorResponse),
+        (status = 416, description = "Requested range not satisfiable"),
         (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
 async fn get_chapter_page(
     State(state): State<RestState>,
     Path((chapter_id, page_index)): Path<(String, usize)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let chapter = state
-        .service
-        .get("chapters", &chapter_id)
-        .map_err(internal_error)?
-        .ok_or_else(|| (StatusCode::NOT_FOUND, "Chapter not found".to_string()))?;
-
-    let comic_id = chapter_value_as_string(&chapter, "comicId").ok_or_else(|| {
-        (
-            StatusCode::BAD_REQUEST,
-            "Chapter has no comicId".to_string(),
-        )
-    })?;
-    let chapter_name = chapter_display_name(&chapter);
-    let chapter_number = chapter_value_as_string(&chapter, "number");
-
-    let comic = state
-        .service
-        .get("comics", &comic_id)
-        .map_err(internal_error)?
-        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
-    let comic_name = chapter_value_as_string(&comic, "name").unwrap_or_else(|| comic_id.clone());
-
-    let cbz_path = resolve_chapter_cbz_path(
-        &state.comics_dir,
-        &comic_id,
-        &comic_name,
-        &chapter_name,
-        chapter_number.as_deref(),
-    );
+    Query(variant): Query<ImageVariantQuery>,
+    method: Method,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, String)> {
+    let variant = image_variant_spec(&variant)?;
+    let ChapterSource {
+        chapter,
+        archive_path: cbz_path,
+        ..
+    } = resolve_chapter_source(&state, &chapter_id)?;
 
     if let Some(cbz_path) = cbz_path {
-        let entries = list_image_entries(&cbz_path).map_err(internal_error)?;
+        let archive = open_archive(&cbz_path).map_err(internal_error)?;
+        let entries = list_image_entries(archive.as_ref()).map_err(internal_error)?;
         let page = entries.get(page_index).ok_or_else(|| {
             (
                 StatusCode::NOT_FOUND,
@@ -500,17 +1010,35 @@ async fn get_chapter_page(
             )
         })?;
 
-        let bytes = read_entry_bytes(&cbz_path, page.archive_index).map_err(internal_error)?;
         let content_type = from_path(&page.file_name).first_or_octet_stream();
+        if let Some(file_path) = archive.file_path(page) {
+            return serve_local_image(
+                &state,
+                &method,
+                &headers,
+                &file_path,
+                content_type.as_ref(),
+                variant,
+            )
+            .await;
+        }
 
-        return Ok((
-            [
-                (header::CONTENT_TYPE, content_type.to_string()),
-                (header::CACHE_CONTROL, "public, max-age=300".to_string()),
-            ],
-            bytes,
-        )
-            .into_response());
+        if let Some(spec) = variant {
+            let source = CacheValidators::for_archive_entry(&cbz_path, page);
+            return serve_image_variant(&state, &method, &headers, &source, spec, || {
+                archive.read_entry(page)
+            })
+            .await;
+        }
+
+        return serve_archive_entry(
+            &method,
+            &headers,
+            &cbz_path,
+            archive.as_ref(),
+            page,
+            content_type.as_ref(),
+        );
     }
 
     let external_pages = chapter_external_pages(&chapter);
@@ -533,22 +1061,16 @@ async fn get_chapter_page(
             )
         })?;
 
-    let bytes = fs::read(&file_path).map_err(|error| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to read chapter page file: {error}"),
-        )
-    })?;
     let content_type = from_path(&file_path).first_or_octet_stream();
-
-    Ok((
-        [
-            (header::CONTENT_TYPE, content_type.to_string()),
-            (header::CACHE_CONTROL, "public, max-age=300".to_string()),
-        ],
-        bytes,
+    serve_local_image(
+        &state,
+        &method,
+        &headers,
+        &file_path,
+        content_type.as_ref(),
+        variant,
     )
-        .into_response())
+    .await
 }
 
 #[utoipa::path(
@@ -556,19 +1078,30 @@ async fn get_chapter_page(
     path = "/api/comics/{comic_id}/cover",
     tag = "db",
     params(
-        ("comic_id" = String, Path, description = "Comic id")
+        ("comic_id" = String, Path, description = "Comic id"),
+        ("w" = Option<u32>, Query, description = "Resize to at most this width"),
+        ("h" = Option<u32>, Query, description = "Resize to at most this height"),
+        ("fit" = Option<String>, Query, description = "contain (default), cover or fill"),
+        ("format" = Option<String>, Query, description = "jpeg (default) or webp")
     ),
     responses(
         (status = 200, description = "Cover image bytes"),
+        (status = 206, description = "Requested byte range of the cover"),
         (status = 302, description = "Redirect to remote cover URL"),
+        (status = 304, description = "Cover unchanged since the cached copy"),
         (status = 404, description = "Cover not found", body = ErrorResponse),
+        (status = 416, description = "Requested range not satisfiable"),
         (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
 async fn get_comic_cover(
     State(state): State<RestState>,
     Path(comic_id): Path<String>,
+    Query(variant): Query<ImageVariantQuery>,
+    method: Method,
+    headers: HeaderMap,
 ) -> Result<Response, (StatusCode, String)> {
+    let variant = image_variant_spec(&variant)?;
     let comic = state
         .service
         .get("comics", &comic_id)
@@ -576,63 +1109,1280 @@ async fn get_comic_cover(
         .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
 
     let comic_name = chapter_value_as_string(&comic, "name").unwrap_or_else(|| comic_id.clone());
-    let path = if let Some(cover_ref) = chapter_value_as_string(&comic, "coverUrl")
-        .or_else(|| chapter_value_as_string(&comic, "cover"))
-        .or_else(|| chapter_value_as_string(&comic, "image"))
+    let path = match resolve_comic_cover(&state.comics_dir, &state.covers_dir, &comic, &comic_name)
     {
-        let trimmed = cover_ref.trim();
-        if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
-            return Ok(Redirect::temporary(trimmed).into_response());
-        }
-
-        resolve_comic_cover_path(&state.comics_dir, &comic_id, &comic_name, trimmed)
-    } else {
-        find_local_cover_in_comic_dir(&state.comics_dir, &comic_id, &comic_name)
-    }
-    .ok_or_else(|| (StatusCode::NOT_FOUND, "Cover image not found".to_string()))?;
-    let bytes = fs::read(&path).map_err(|error| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to read cover image: {error}"),
-        )
-    })?;
+        Some(ComicCover::Remote(url)) => return Ok(Redirect::temporary(&url).into_response()),
+        Some(ComicCover::Local(path)) => path,
+        None => extracted_comic_cover(&state, comic, comic_name)
+            .await?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Cover image not found".to_string()))?,
+    };
     let content_type = from_path(&path).first_or_octet_stream();
+    serve_local_image(
+        &state,
+        &method,
+        &headers,
+        &path,
+        content_type.as_ref(),
+        variant,
+    )
+    .await
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/comics/{comic_id}/cover",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    request_body(
+        content_type = "multipart/form-data",
+        description = "BMP, GIF, JPEG, PNG or WebP image sent as the `file` part, up to 20 MiB"
+    ),
+    responses(
+        (status = 200, description = "Comic with its custom cover recorded", body = DbRecord),
+        (status = 400, description = "Missing or empty `file` part", body = ErrorResponse),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 413, description = "Image too large", body = ErrorResponse),
+        (status = 415, description = "Not a supported image", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn upload_comic_cover(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<DbRecord>, (StatusCode, String)> {
+    let comic = state
+        .service
+        .get("comics", &comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+
+    let file = uploads::file_part(&mut multipart).await?;
+    let bytes = uploads::read_part(file, MAX_COVER_UPLOAD_BYTES).await?;
+    tokio::task::spawn_blocking(move || {
+        uploads::store_custom_cover(&state.service, &state.covers_dir, &comic, &bytes)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Cover upload task failed: {error}"),
+        )
+    })?
+    .map(Json)
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/chapters/{chapter_id}/archive",
+    tag = "db",
+    params(
+        ("chapter_id" = String, Path, description = "Chapter id")
+    ),
+    request_body(
+        content_type = "multipart/form-data",
+        description = "CBZ archive sent as the `file` part, up to 1 GiB"
+    ),
+    responses(
+        (status = 200, description = "Chapter marked offline at its new archive", body = DbRecord),
+        (status = 400, description = "Missing `file` part or archive without pages", body = ErrorResponse),
+        (status = 404, description = "Chapter or comic not found", body = ErrorResponse),
+        (status = 409, description = "An archive already exists at the chapter's path", body = ErrorResponse),
+        (status = 413, description = "Archive too large", body = ErrorResponse),
+        (status = 415, description = "Not a readable CBZ archive, or pages that fail to decode", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn upload_chapter_archive(
+    State(state): State<RestState>,
+    Path(chapter_id): Path<String>,
+    mut multipart: Multipart,
+) -> Result<Json<DbRecord>, (StatusCode, String)> {
+    let source = resolve_chapter_source(&state, &chapter_id)?;
+    let target = canonical_chapter_archive_path(
+        &state.comics_dir,
+        &source.comic_id,
+        &source.comic_name,
+        &source.chapter_name,
+    );
+    if target.exists() {
+        return Err(uploads::archive_exists(&target));
+    }
+
+    // The comic folder is only created once the archive validates, so the upload lands in
+    // the library root first. Hidden and without an archive extension, scans never pick up
+    // a partial upload.
+    fs::create_dir_all(&state.comics_dir).map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create comics folder: {error}"),
+        )
+    })?;
+    let temp_path = state
+        .comics_dir
+        .join(format!(".upload-{}.part", Uuid::new_v4()));
+    let file = uploads::file_part(&mut multipart).await?;
+    uploads::write_part(file, &temp_path, MAX_ARCHIVE_UPLOAD_BYTES).await?;
+
+    let was_offline = source
+        .chapter
+        .data
+        .get("hasOffline")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let blocking_state = state.clone();
+    let chapter = tokio::task::spawn_blocking(move || {
+        uploads::install_chapter_archive(
+            &blocking_state.service,
+            &blocking_state.comics_dir,
+            &source.chapter,
+            &temp_path,
+            &target,
+        )
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Archive upload task failed: {error}"),
+        )
+    })??;
+
+    if !was_offline {
+        state
+            .events
+            .publish(LibraryEvent::OfflineChanged(OfflineChangedEvent {
+                comic_id: source.comic_id,
+                chapters: vec![ChapterAvailability {
+                    chapter_id: chapter.id.clone(),
+                    has_offline: true,
+                }],
+            }));
+    }
+    Ok(Json(chapter))
+}
+
+enum ComicCover {
+    Remote(String),
+    Local(PathBuf),
+}
+
+/// The cover a comic already has: a custom upload, its recorded cover reference, an image
+/// placed in its folder or a cover extracted earlier. `None` means one has to be extracted.
+fn resolve_comic_cover(
+    comics_dir: &FsPath,
+    covers_dir: &FsPath,
+    comic: &DbRecord,
+    comic_name: &str,
+) -> Option<ComicCover> {
+    if let Some(path) = chapter_value_as_string(comic, CUSTOM_COVER_FIELD)
+        .and_then(|file_name| resolve_local_path(covers_dir, &file_name))
+    {
+        return Some(ComicCover::Local(path));
+    }
+
+    let path = if let Some(cover_ref) = chapter_value_as_string(comic, "coverUrl")
+        .or_else(|| chapter_value_as_string(comic, "cover"))
+        .or_else(|| chapter_value_as_string(comic, "image"))
+    {
+        let trimmed = cover_ref.trim();
+        if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
+            return Some(ComicCover::Remote(trimmed.to_string()));
+        }
+
+        resolve_comic_cover_path(comics_dir, &comic.id, comic_name, trimmed)
+    } else {
+        find_local_cover_in_comic_dir(comics_dir, &comic.id, comic_name)
+    };
+    path.or_else(|| {
+        chapter_value_as_string(comic, LOCAL_COVER_FIELD)
+            .and_then(|file_name| resolve_local_path(covers_dir, &file_name))
+    })
+    .map(ComicCover::Local)
+}
+
+/// The cover extracted for a comic earlier, or a new one taken from its first local
+/// chapter. Only the file is written here; the library scan records it on the comic.
+async fn extracted_comic_cover(
+    state: &RestState,
+    comic: DbRecord,
+    comic_name: String,
+) -> Result<Option<PathBuf>, (StatusCode, String)> {
+    let saved = extracted_cover_path(&state.covers_dir, &comic.id);
+    if saved.is_file() {
+        return Ok(Some(saved));
+    }
+
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        extract_comic_cover(
+            &state.service,
+            &state.comics_dir,
+            &state.covers_dir,
+            &comic,
+            &comic_name,
+        )
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Cover extraction task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)
+}
+
+/// Records a cover extracted from the first local chapter on a comic that has no cover of
+/// its own, so listings can show it without another extraction.
+fn record_extracted_cover(
+    service: &DocumentService,
+    comics_dir: &FsPath,
+    covers_dir: &FsPath,
+    comic_id: &str,
+) -> Result<(), AppError> {
+    let Some(comic) = service.get("comics", comic_id)? else {
+        return Ok(());
+    };
+    let comic_name = chapter_value_as_string(&comic, "name").unwrap_or_else(|| comic.id.clone());
+    if resolve_comic_cover(comics_dir, covers_dir, &comic, &comic_name).is_some() {
+        return Ok(());
+    }
+
+    let saved = extracted_cover_path(covers_dir, &comic.id);
+    let path = if saved.is_file() {
+        Some(saved)
+    } else {
+        extract_comic_cover(service, comics_dir, covers_dir, &comic, &comic_name)?
+    };
+    let Some(file_name) = path
+        .as_deref()
+        .and_then(FsPath::file_name)
+        .and_then(|value| value.to_str())
+    else {
+        return Ok(());
+    };
+
+    service.set_json_field(
+        "comics",
+        &comic.id,
+        LOCAL_COVER_FIELD,
+        &Value::String(file_name.to_string()),
+    )?;
+    Ok(())
+}
+
+fn extract_comic_cover(
+    service: &DocumentService,
+    comics_dir: &FsPath,
+    covers_dir: &FsPath,
+    comic: &DbRecord,
+    comic_name: &str,
+) -> Result<Option<PathBuf>, AppError> {
+    let mut chapters = service.find_by_json_field(
+        "chapters",
+        "comicId",
+        Value::String(comic.id.clone()),
+        Some(u32::MAX),
+    )?;
+    chapters.sort_by(compare_chapter_order);
+
+    for chapter in chapters {
+        let Some(archive_path) =
+            locate_chapter_archive(comics_dir, &chapter, &comic.id, comic_name)
+        else {
+            continue;
+        };
+        // Unreadable archives or pages just move on to the next chapter.
+        let bytes = match open_archive(&archive_path)
+            .and_then(|archive| extract_archive_cover(archive.as_ref()))
+        {
+            Ok(Some(bytes)) => bytes,
+            Ok(None) => continue,
+            Err(error) => {
+                eprintln!(
+                    "Failed to extract a cover from {}: {error}",
+                    archive_path.display()
+                );
+                continue;
+            }
+        };
+
+        return save_extracted_cover(covers_dir, &comic.id, &bytes).map(Some);
+    }
+
+    Ok(None)
+}
+
+/// Reading order: numbered chapters by number, then the rest by name.
+fn compare_chapter_order(left: &DbRecord, right: &DbRecord) -> std::cmp::Ordering {
+    match (chapter_number(left), chapter_number(right)) {
+        (Some(left), Some(right)) => left.total_cmp(&right),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+    .then_with(|| natural_cmp(&chapter_display_name(left), &chapter_display_name(right)))
+}
+
+fn image_variant_spec(
+    query: &ImageVariantQuery,
+) -> Result<Option<ImageVariantSpec>, (StatusCode, String)> {
+    ImageVariantSpec::from_options(
+        query.w,
+        query.h,
+        query.fit.as_deref(),
+        query.format.as_deref(),
+    )
+    .map_err(internal_error)
+}
+
+async fn serve_local_image(
+    state: &RestState,
+    method: &Method,
+    headers: &HeaderMap,
+    path: &FsPath,
+    content_type: &str,
+    variant: Option<ImageVariantSpec>,
+) -> Result<Response, (StatusCode, String)> {
+    let Some(spec) = variant else {
+        return serve_file(method, headers, path, content_type).await;
+    };
+
+    let metadata = fs::metadata(path).map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to read file metadata: {error}"),
+        )
+    })?;
+    let source = CacheValidators::for_file(path, &metadata);
+    serve_image_variant(state, method, headers, &source, spec, || {
+        fs::read(path).map_err(|e| AppError::infrastructure(format!("Failed to read image: {e}")))
+    })
+    .await
+}
+
+/// Serves a resized rendition from the disk cache, rendering it on a blocking thread first
+/// when it is missing. The source is only loaded on a cache miss.
+async fn serve_image_variant(
+    state: &RestState,
+    method: &Method,
+    headers: &HeaderMap,
+    source: &CacheValidators,
+    spec: ImageVariantSpec,
+    load_source: impl FnOnce() -> Result<Vec<u8>, AppError>,
+) -> Result<Response, (StatusCode, String)> {
+    let validators = source.for_variant(&format!("{spec:?}"));
+    if validators.is_not_modified(headers) {
+        return not_modified(&validators);
+    }
+
+    let (path, format) = match state.image_cache.lookup(source.etag(), &spec) {
+        Some(cached) => cached,
+        None => {
+            let bytes = load_source().map_err(internal_error)?;
+            let cache = state.image_cache.clone();
+            let source_key = source.etag().to_string();
+            tokio::task::spawn_blocking(move || {
+                let rendered = render_variant(&bytes, &spec)?;
+                let path = cache.store(&source_key, &spec, &rendered)?;
+                Ok::<_, AppError>((path, rendered.format))
+            })
+            .await
+            .map_err(|error| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Image rendering task failed: {error}"),
+                )
+            })?
+            .map_err(internal_error)?
+        }
+    };
+
+    serve_file_with_validators(method, headers, &path, &validators, format.content_type()).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/chapters/mark",
+    tag = "db",
+    request_body = MarkChaptersBody,
+    responses(
+        (status = 200, description = "Marked chapters as read/unread", body = MarkChaptersResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn mark_chapters_read_state(
+    State(state): State<RestState>,
+    Json(payload): Json<MarkChaptersBody>,
+) -> Result<Json<MarkChaptersResponse>, (StatusCode, String)> {
+    let has_filters = payload.from_number.is_some()
+        || payload.up_to_number.is_some()
+        || payload.language.is_some();
+    let selection = match payload.comic_id {
+        Some(comic_id) => Some(ChapterSelection {
+            comic_id,
+            from_number: payload.from_number,
+            up_to_number: payload.up_to_number,
+            language: payload.language,
+        }),
+        None if has_filters => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "fromNumber, upToNumber and language require a comicId".to_string(),
+            ));
+        }
+        None => None,
+    };
+    if payload.chapter_ids.is_empty() && selection.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "chapterIds cannot be empty without a comicId".to_string(),
+        ));
+    }
+
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let (updated, skipped) = tokio::task::spawn_blocking(move || {
+        let page_count = reading::chapter_page_counter(&service, &comics_dir);
+        service.mark_chapters_read_state(
+            &payload.chapter_ids,
+            selection.as_ref(),
+            payload.read,
+            &page_count,
+        )
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Mark chapters task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(MarkChaptersResponse { updated, skipped }))
+}
+
+/// Recounts progress rows that recorded a single page, as chapters marked read without
+/// progress used to, from each chapter's archive or page list. Read chapters stay read.
+#[utoipa::path(
+    post,
+    path = "/api/library/repair-progress",
+    tag = "db",
+    responses(
+        (status = 200, description = "Checked and repaired progress rows", body = RepairProgressResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn repair_progress_page_counts(
+    State(state): State<RestState>,
+) -> Result<Json<RepairProgressResponse>, (StatusCode, String)> {
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let (checked, repaired) = tokio::task::spawn_blocking(move || {
+        let page_count = reading::chapter_page_counter(&service, &comics_dir);
+        service.repair_progress_page_counts(&page_count)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Progress repair task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(RepairProgressResponse { checked, repaired }))
+}
+
+/// The chapter to resume in a comic: the unfinished chapter read last, or the next unread
+/// chapter in number order.
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/next",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "Chapter to open next", body = NextChapterResponse),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_next_chapter(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Json<NextChapterResponse>, (StatusCode, String)> {
+    let comic = state
+        .service
+        .get("comics", &comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+    let next = reading::next_chapter(&state.service, &comic).map_err(internal_error)?;
+
+    Ok(Json(NextChapterResponse { comic_id, next }))
+}
+
+/// The chapter to resume in every started, unfinished comic, most recently read first.
+#[utoipa::path(
+    get,
+    path = "/api/library/continue-reading",
+    tag = "db",
+    params(
+        ("limit" = Option<usize>, Query, description = "Maximum number of comics, at most 100")
+    ),
+    responses(
+        (status = 200, description = "Chapters to resume", body = ContinueReadingResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_continue_reading(
+    State(state): State<RestState>,
+    Query(query): Query<ContinueReadingQuery>,
+) -> Result<Json<ContinueReadingResponse>, (StatusCode, String)> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CONTINUE_READING_LIMIT)
+        .min(MAX_CONTINUE_READING_LIMIT);
+    let service = state.service.clone();
+    let items = tokio::task::spawn_blocking(move || reading::continue_reading(&service, limit))
+        .await
+        .map_err(|error| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Continue reading task failed: {error}"),
+            )
+        })?
+        .map_err(internal_error)?;
+
+    Ok(Json(ContinueReadingResponse { items }))
+}
+
+/// Read, in-progress and unread chapter counts for every comic, for library badges.
+#[utoipa::path(
+    get,
+    path = "/api/library/progress",
+    tag = "db",
+    responses(
+        (status = 200, description = "Progress of every comic", body = LibraryProgressResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_library_progress(
+    State(state): State<RestState>,
+) -> Result<Json<LibraryProgressResponse>, (StatusCode, String)> {
+    let comics = state.service.library_progress().map_err(internal_error)?;
+
+    Ok(Json(LibraryProgressResponse { comics }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/progress",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "Progress of the comic", body = ComicProgress),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_comic_progress(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Json<ComicProgress>, (StatusCode, String)> {
+    let progress = state
+        .service
+        .comic_progress(&comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+
+    Ok(Json(progress))
+}
+
+/// Bookmarks a page of a chapter, optionally with a short note and a region of the page.
+#[utoipa::path(
+    post,
+    path = "/api/bookmarks",
+    tag = "db",
+    request_body = CreateBookmarkBody,
+    responses(
+        (status = 200, description = "Created bookmark", body = Bookmark),
+        (status = 400, description = "Invalid page, note or region", body = ErrorResponse),
+        (status = 404, description = "Chapter not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn create_bookmark(
+    State(state): State<RestState>,
+    Json(payload): Json<CreateBookmarkBody>,
+) -> Result<Json<Bookmark>, (StatusCode, String)> {
+    let chapter = state
+        .service
+        .get("chapters", &payload.chapter_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Chapter not found".to_string()))?;
+    let bookmark =
+        bookmarks::create_bookmark(&state.service, &chapter, payload).map_err(internal_error)?;
+
+    Ok(Json(bookmark))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/bookmarks",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "Bookmarks of the comic", body = BookmarkListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_comic_bookmarks(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Json<BookmarkListResponse>, (StatusCode, String)> {
+    let items =
+        bookmarks::list_bookmarks(&state.service, "comicId", &comic_id).map_err(internal_error)?;
+
+    Ok(Json(BookmarkListResponse { items }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/chapters/{chapter_id}/bookmarks",
+    tag = "db",
+    params(
+        ("chapter_id" = String, Path, description = "Chapter id")
+    ),
+    responses(
+        (status = 200, description = "Bookmarks of the chapter", body = BookmarkListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_chapter_bookmarks(
+    State(state): State<RestState>,
+    Path(chapter_id): Path<String>,
+) -> Result<Json<BookmarkListResponse>, (StatusCode, String)> {
+    let items = bookmarks::list_bookmarks(&state.service, "chapterId", &chapter_id)
+        .map_err(internal_error)?;
+
+    Ok(Json(BookmarkListResponse { items }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/bookmarks/{bookmark_id}",
+    tag = "db",
+    params(
+        ("bookmark_id" = String, Path, description = "Bookmark id")
+    ),
+    responses(
+        (status = 200, description = "Whether the bookmark existed", body = DeleteResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn delete_bookmark(
+    State(state): State<RestState>,
+    Path(bookmark_id): Path<String>,
+) -> Result<Json<DeleteResponse>, (StatusCode, String)> {
+    let deleted = state
+        .service
+        .delete("bookmarks", &bookmark_id)
+        .map_err(internal_error)?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+fn collection_not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Collection not found".to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/collections",
+    tag = "db",
+    responses(
+        (status = 200, description = "Collections and reading lists", body = CollectionListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_collections(
+    State(state): State<RestState>,
+) -> Result<Json<CollectionListResponse>, (StatusCode, String)> {
+    let items = collections::list_collections(&state.service).map_err(internal_error)?;
+
+    Ok(Json(CollectionListResponse { items }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/collections",
+    tag = "db",
+    request_body = CollectionBody,
+    responses(
+        (status = 200, description = "Created collection", body = CollectionDetail),
+        (status = 400, description = "Missing name or unknown kind", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn create_collection(
+    State(state): State<RestState>,
+    Json(payload): Json<CollectionBody>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection =
+        collections::create_collection(&state.service, payload).map_err(internal_error)?;
+
+    Ok(Json(collection))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/collections/{collection_id}",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id")
+    ),
+    responses(
+        (status = 200, description = "Collection with its items in order", body = CollectionDetail),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_collection(
+    State(state): State<RestState>,
+    Path(collection_id): Path<String>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection = collections::collection_detail(&state.service, &collection_id)
+        .map_err(internal_error)?
+        .ok_or_else(collection_not_found)?;
+
+    Ok(Json(collection))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/collections/{collection_id}",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id")
+    ),
+    request_body = CollectionBody,
+    responses(
+        (status = 200, description = "Updated collection", body = CollectionDetail),
+        (status = 400, description = "Invalid name or kind", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn update_collection(
+    State(state): State<RestState>,
+    Path(collection_id): Path<String>,
+    Json(payload): Json<CollectionBody>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection = collections::update_collection(&state.service, &collection_id, payload)
+        .map_err(internal_error)?
+        .ok_or_else(collection_not_found)?;
+
+    Ok(Json(collection))
+}
+
+/// Deletes a collection together with its items; the comics and chapters stay.
+#[utoipa::path(
+    delete,
+    path = "/api/collections/{collection_id}",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id")
+    ),
+    responses(
+        (status = 200, description = "Whether the collection existed", body = DeleteResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn delete_collection(
+    State(state): State<RestState>,
+    Path(collection_id): Path<String>,
+) -> Result<Json<DeleteResponse>, (StatusCode, String)> {
+    let deleted = state
+        .service
+        .delete("collections", &collection_id)
+        .map_err(internal_error)?;
+    Ok(Json(DeleteResponse { deleted }))
+}
+
+/// Adds comics, works or chapters, from any comic, at a position or at the end.
+#[utoipa::path(
+    post,
+    path = "/api/collections/{collection_id}/items",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id")
+    ),
+    request_body = AddCollectionItemsBody,
+    responses(
+        (status = 200, description = "Collection with the added items", body = CollectionDetail),
+        (status = 400, description = "Unknown item type or missing item", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn add_collection_items(
+    State(state): State<RestState>,
+    Path(collection_id): Path<String>,
+    Json(payload): Json<AddCollectionItemsBody>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection = collections::add_items(&state.service, &collection_id, payload)
+        .map_err(internal_error)?
+        .ok_or_else(collection_not_found)?;
+
+    Ok(Json(collection))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/collections/{collection_id}/items/{item_id}",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id"),
+        ("item_id" = String, Path, description = "Collection item id")
+    ),
+    responses(
+        (status = 200, description = "Collection without the item", body = CollectionDetail),
+        (status = 404, description = "Collection or item not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn remove_collection_item(
+    State(state): State<RestState>,
+    Path((collection_id, item_id)): Path<(String, String)>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection = collections::remove_item(&state.service, &collection_id, &item_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                "Collection or item not found".to_string(),
+            )
+        })?;
+
+    Ok(Json(collection))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/collections/{collection_id}/items/order",
+    tag = "db",
+    params(
+        ("collection_id" = String, Path, description = "Collection id")
+    ),
+    request_body = ReorderCollectionItemsBody,
+    responses(
+        (status = 200, description = "Collection in its new order", body = CollectionDetail),
+        (status = 400, description = "Item not in the collection", body = ErrorResponse),
+        (status = 404, description = "Collection not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn reorder_collection_items(
+    State(state): State<RestState>,
+    Path(collection_id): Path<String>,
+    Json(payload): Json<ReorderCollectionItemsBody>,
+) -> Result<Json<CollectionDetail>, (StatusCode, String)> {
+    let collection = collections::reorder_items(&state.service, &collection_id, payload)
+        .map_err(internal_error)?
+        .ok_or_else(collection_not_found)?;
+
+    Ok(Json(collection))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/tags",
+    tag = "db",
+    responses(
+        (status = 200, description = "Tags with the number of comics carrying each", body = TagListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_tags(
+    State(state): State<RestState>,
+) -> Result<Json<TagListResponse>, (StatusCode, String)> {
+    let items = state.service.list_tags().map_err(internal_error)?;
+
+    Ok(Json(TagListResponse { items }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/tags/{tag_id}",
+    tag = "db",
+    params(
+        ("tag_id" = String, Path, description = "Tag id")
+    ),
+    request_body = UpdateTagBody,
+    responses(
+        (status = 200, description = "Updated tag", body = TagSummary),
+        (status = 404, description = "Tag not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn update_tag(
+    State(state): State<RestState>,
+    Path(tag_id): Path<String>,
+    Json(payload): Json<UpdateTagBody>,
+) -> Result<Json<TagSummary>, (StatusCode, String)> {
+    let tag = state
+        .service
+        .update_tag(&tag_id, payload.name.as_deref(), payload.aliases.as_deref())
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Tag not found".to_string()))?;
+
+    Ok(Json(tag))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/tags",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "Tags of the comic, from its genres and added by hand", body = ComicTagListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_comic_tags(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Json<ComicTagListResponse>, (StatusCode, String)> {
+    let items = state
+        .service
+        .comic_tags(&comic_id)
+        .map_err(internal_error)?;
+
+    Ok(Json(ComicTagListResponse { comic_id, items }))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/comics/{comic_id}/tags",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    request_body = ComicTagsBody,
+    responses(
+        (status = 200, description = "Tags of the comic after the change", body = ComicTagListResponse),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn set_comic_tags(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+    Json(payload): Json<ComicTagsBody>,
+) -> Result<Json<ComicTagListResponse>, (StatusCode, String)> {
+    state
+        .service
+        .get("comics", &comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+    let items = state
+        .service
+        .set_comic_user_tags(&comic_id, &payload.tags)
+        .map_err(internal_error)?;
+
+    Ok(Json(ComicTagListResponse { comic_id, items }))
+}
+
+/// Comics carrying all of `tags` and none of `excludeTags`, matched by tag name or alias,
+/// narrowed and sorted by personal rating and favorite flag.
+#[utoipa::path(
+    get,
+    path = "/api/library/comics",
+    tag = "db",
+    params(
+        ("tags" = Option<String>, Query, description = "Comma-separated tags every comic must have"),
+        ("excludeTags" = Option<String>, Query, description = "Comma-separated tags no comic may have"),
+        ("favorite" = Option<bool>, Query, description = "Only favorites, or only comics that are not"),
+        ("minRating" = Option<i64>, Query, description = "Lowest personal rating, 0 to 10"),
+        ("sort" = Option<String>, Query, description = "`name` (default), `rating` or `favorite`")
+    ),
+    responses(
+        (status = 200, description = "Matching comics in the requested order", body = LibraryComicsResponse),
+        (status = 400, description = "Unknown sort or rating out of range", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_library_comics(
+    State(state): State<RestState>,
+    Query(query): Query<LibraryComicsQuery>,
+) -> Result<Json<LibraryComicsResponse>, (StatusCode, String)> {
+    let split = |value: Option<String>| {
+        value
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    if query
+        .min_rating
+        .is_some_and(|rating| !(0..=10).contains(&rating))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "minRating must be between 0 and 10".to_string(),
+        ));
+    }
+    let sort = query
+        .sort
+        .as_deref()
+        .map(LibrarySort::parse)
+        .transpose()
+        .map_err(internal_error)?
+        .unwrap_or_default();
+    let filter = LibraryFilter {
+        include_tags: split(query.tags),
+        exclude_tags: split(query.exclude_tags),
+        favorite: query.favorite,
+        min_rating: query.min_rating,
+        sort,
+    };
+    let items = state
+        .service
+        .filter_comics(&filter)
+        .map_err(internal_error)?;
+
+    Ok(Json(LibraryComicsResponse { items }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/comics/{comic_id}/rating",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    responses(
+        (status = 200, description = "Personal rating, favorite flag and review", body = PersonalRating),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_comic_rating(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+) -> Result<Json<PersonalRating>, (StatusCode, String)> {
+    let rating = ratings::personal_rating(&state.service, "comic", &comic_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+
+    Ok(Json(rating))
+}
+
+/// Changes the personal rating, favorite flag and review of a comic. They are kept apart
+/// from the comic's metadata, so refreshing it from a plugin leaves them alone.
+#[utoipa::path(
+    put,
+    path = "/api/comics/{comic_id}/rating",
+    tag = "db",
+    params(
+        ("comic_id" = String, Path, description = "Comic id")
+    ),
+    request_body = PersonalRatingBody,
+    responses(
+        (status = 200, description = "Saved rating", body = PersonalRating),
+        (status = 400, description = "Rating, stars or review out of range", body = ErrorResponse),
+        (status = 404, description = "Comic not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn set_comic_rating(
+    State(state): State<RestState>,
+    Path(comic_id): Path<String>,
+    Json(payload): Json<PersonalRatingBody>,
+) -> Result<Json<PersonalRating>, (StatusCode, String)> {
+    let rating = ratings::set_personal_rating(&state.service, "comic", &comic_id, payload)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Comic not found".to_string()))?;
+
+    Ok(Json(rating))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/works/{work_id}/rating",
+    tag = "db",
+    params(
+        ("work_id" = String, Path, description = "Work id")
+    ),
+    responses(
+        (status = 200, description = "Personal rating, favorite flag and review", body = PersonalRating),
+        (status = 404, description = "Work not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_work_rating(
+    State(state): State<RestState>,
+    Path(work_id): Path<String>,
+) -> Result<Json<PersonalRating>, (StatusCode, String)> {
+    let rating = ratings::personal_rating(&state.service, "work", &work_id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Work not found".to_string()))?;
+
+    Ok(Json(rating))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/works/{work_id}/rating",
+    tag = "db",
+    params(
+        ("work_id" = String, Path, description = "Work id")
+    ),
+    request_body = PersonalRatingBody,
+    responses(
+        (status = 200, description = "Saved rating", body = PersonalRating),
+        (status = 400, description = "Rating, stars or review out of range", body = ErrorResponse),
+        (status = 404, description = "Work not found", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn set_work_rating(
+    State(state): State<RestState>,
+    Path(work_id): Path<String>,
+    Json(payload): Json<PersonalRatingBody>,
+) -> Result<Json<PersonalRating>, (StatusCode, String)> {
+    let rating = ratings::set_personal_rating(&state.service, "work", &work_id, payload)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Work not found".to_string()))?;
+
+    Ok(Json(rating))
+}
+
+/// Every personal rating of comics and works, to carry them to another library with
+/// `POST /api/ratings/import`.
+#[utoipa::path(
+    get,
+    path = "/api/ratings/export",
+    tag = "db",
+    responses(
+        (status = 200, description = "Saved ratings by item", body = PersonalRatingListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn export_ratings(
+    State(state): State<RestState>,
+) -> Result<Json<PersonalRatingListResponse>, (StatusCode, String)> {
+    let items = ratings::export_personal_ratings(&state.service).map_err(internal_error)?;
 
-    Ok((
-        [
-            (header::CONTENT_TYPE, content_type.to_string()),
-            (header::CACHE_CONTROL, "public, max-age=300".to_string()),
-        ],
-        bytes,
+    Ok(Json(PersonalRatingListResponse { items }))
+}
+
+/// Restores the ratings of `GET /api/ratings/export` for comics and works of this library
+/// that have no rating yet.
+#[utoipa::path(
+    post,
+    path = "/api/ratings/import",
+    tag = "db",
+    request_body = ImportPersonalRatingsBody,
+    responses(
+        (status = 200, description = "Restored and skipped ratings", body = ImportPersonalRatingsResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
     )
-        .into_response())
+)]
+async fn import_ratings(
+    State(state): State<RestState>,
+    Json(payload): Json<ImportPersonalRatingsBody>,
+) -> Result<Json<ImportPersonalRatingsResponse>, (StatusCode, String)> {
+    let report =
+        ratings::import_personal_ratings(&state.service, payload).map_err(internal_error)?;
+
+    Ok(Json(report))
 }
 
+/// Maps every chapter variant of a work to a canonical chapter by number and title,
+/// creating canonical chapters for variants that match none. Mappings with strategy
+/// `manual` are kept.
 #[utoipa::path(
     post,
-    path = "/api/chapters/mark",
+    path = "/api/works/{work_id}/remap",
     tag = "db",
-    request_body = MarkChaptersBody,
+    params(
+        ("work_id" = String, Path, description = "Work id")
+    ),
     responses(
-        (status = 200, description = "Marked chapters as read/unread", body = MarkChaptersResponse),
-        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 200, description = "Mapping summary with low-confidence matches to review", body = RemapWorkResponse),
+        (status = 404, description = "Work not found", body = ErrorResponse),
         (status = 500, description = "Internal error", body = ErrorResponse)
     )
 )]
-async fn mark_chapters_read_state(
+async fn remap_work(
     State(state): State<RestState>,
-    Json(payload): Json<MarkChaptersBody>,
-) -> Result<Json<MarkChaptersResponse>, (StatusCode, String)> {
-    if payload.chapter_ids.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "chapterIds cannot be empty".to_string()));
-    }
+    Path(work_id): Path<String>,
+) -> Result<Json<RemapWorkResponse>, (StatusCode, String)> {
+    let service = state.service.clone();
+    let response =
+        tokio::task::spawn_blocking(move || chapter_mappings::remap_work(&service, &work_id))
+            .await
+            .map_err(|error| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Remap task failed: {error}"),
+                )
+            })?
+            .map_err(internal_error)?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Work not found".to_string()))?;
 
-    let (updated, skipped) = state
+    Ok(Json(response))
+}
+
+/// Reading statistics built from the sessions recorded as progress is saved.
+#[utoipa::path(
+    get,
+    path = "/api/stats/reading",
+    tag = "db",
+    params(
+        ("from" = Option<String>, Query, description = "First local day, YYYY-MM-DD (default: 29 days before `to`)"),
+        ("to" = Option<String>, Query, description = "Last local day, YYYY-MM-DD (default: today)"),
+        ("utcOffsetMinutes" = Option<i32>, Query, description = "Reader's offset from UTC in minutes, east positive"),
+        ("limit" = Option<usize>, Query, description = "Number of top comics and genres")
+    ),
+    responses(
+        (status = 200, description = "Reading statistics for the range", body = ReadingStats),
+        (status = 400, description = "Invalid date range", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_reading_stats(
+    State(state): State<RestState>,
+    Query(query): Query<ReadingStatsQuery>,
+) -> Result<Json<ReadingStats>, (StatusCode, String)> {
+    let range = ReadingStatsRange {
+        from: query.from,
+        to: query.to,
+        utc_offset_minutes: query.utc_offset_minutes.unwrap_or(0),
+        top_limit: query.limit.unwrap_or(DEFAULT_STATS_TOP_LIMIT),
+    };
+    let stats = state
         .service
-        .mark_chapters_read_state(&payload.chapter_ids, payload.read)
+        .reading_stats(&range)
         .map_err(internal_error)?;
 
-    Ok(Json(MarkChaptersResponse { updated, skipped }))
+    Ok(Json(stats))
 }
 
 #[utoipa::path(
@@ -787,6 +2537,72 @@ async fn import_comic(
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/library/scan",
+    tag = "db",
+    responses(
+        (status = 200, description = "Created, matched and ambiguous library items", body = LibraryScanResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn scan_library(
+    State(state): State<RestState>,
+) -> Result<Json<LibraryScanResponse>, (StatusCode, String)> {
+    let report = tokio::task::spawn_blocking(move || {
+        library_scan::scan_library(&state.service, &state.comics_dir, &state.covers_dir)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Library scan task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(report))
+}
+
+/// Moves archives saved under the old ASCII-only names to their Unicode names. Pass
+/// `dryRun` to only list what would move.
+#[utoipa::path(
+    post,
+    path = "/api/admin/library/rename-legacy",
+    tag = "db",
+    request_body = LibraryRenameBody,
+    responses(
+        (status = 200, description = "Renamed and skipped chapter archives", body = LibraryRenameResponse),
+        (status = 404, description = "Admin endpoints disabled", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn rename_legacy_files(
+    State(state): State<RestState>,
+    body: Option<Json<LibraryRenameBody>>,
+) -> Result<Json<LibraryRenameResponse>, (StatusCode, String)> {
+    if !state.admin_enabled {
+        return Err((StatusCode::NOT_FOUND, "Not Found".to_string()));
+    }
+
+    let Json(payload) = body.unwrap_or_default();
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        library_rename::rename_legacy_files(&service, &comics_dir, payload.dry_run)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Rename task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(report))
+}
+
 #[utoipa::path(
     post,
     path = "/api/admin/migrate-legacy",
@@ -827,6 +2643,173 @@ async fn migrate_legacy(
     Ok(Json(response))
 }
 
+/// Bytes used on disk by each comic and its local chapter archives.
+#[utoipa::path(
+    get,
+    path = "/api/storage/usage",
+    tag = "db",
+    responses(
+        (status = 200, description = "Storage used by the library, largest comics first", body = StorageUsageResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_storage_usage(
+    State(state): State<RestState>,
+) -> Result<Json<StorageUsageResponse>, (StatusCode, String)> {
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let covers_dir = state.covers_dir.clone();
+    let usage = tokio::task::spawn_blocking(move || {
+        storage::storage_usage(&service, &comics_dir, &covers_dir)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Storage usage task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(usage))
+}
+
+/// Files and folders in the library and covers directories that no comic or chapter
+/// refers to.
+#[utoipa::path(
+    get,
+    path = "/api/storage/orphans",
+    tag = "db",
+    responses(
+        (status = 200, description = "Orphaned files and folders", body = OrphanListResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn list_storage_orphans(
+    State(state): State<RestState>,
+) -> Result<Json<OrphanListResponse>, (StatusCode, String)> {
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let covers_dir = state.covers_dir.clone();
+    let orphans = tokio::task::spawn_blocking(move || {
+        storage::find_orphans(&service, &comics_dir, &covers_dir)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Orphan scan task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(OrphanListResponse {
+        total_bytes: orphans.iter().map(|orphan| orphan.bytes).sum(),
+        orphans,
+    }))
+}
+
+/// Moves orphaned files and folders to the trash, or deletes them. Unless `dryRun` is
+/// `false`, only reports what would be removed.
+#[utoipa::path(
+    post,
+    path = "/api/admin/storage/orphans/cleanup",
+    tag = "db",
+    request_body = OrphanCleanupBody,
+    responses(
+        (status = 200, description = "Removed orphans and the ones that could not be removed", body = OrphanCleanupResponse),
+        (status = 400, description = "Unsupported cleanup mode, or an unconfirmed delete of every orphan", body = ErrorResponse),
+        (status = 404, description = "Admin endpoints disabled", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn clean_storage_orphans(
+    State(state): State<RestState>,
+    body: Option<Json<OrphanCleanupBody>>,
+) -> Result<Json<OrphanCleanupResponse>, (StatusCode, String)> {
+    if !state.admin_enabled {
+        return Err((StatusCode::NOT_FOUND, "Not Found".to_string()));
+    }
+
+    let Json(payload) = body.unwrap_or_default();
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let covers_dir = state.covers_dir.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        storage::clean_orphans(&service, &comics_dir, &covers_dir, payload)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Orphan cleanup task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(report))
+}
+
+/// Reads every page of each local chapter archive, checking ZIP CRCs and that each image
+/// decodes, and records the result on the chapter as `archiveHealth`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/archives/verify",
+    tag = "db",
+    request_body = ArchiveVerifyBody,
+    responses(
+        (status = 200, description = "Checked and skipped archives, with the broken ones", body = ArchiveVerifyResponse),
+        (status = 404, description = "Admin endpoints disabled", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn verify_archives(
+    State(state): State<RestState>,
+    body: Option<Json<ArchiveVerifyBody>>,
+) -> Result<Json<ArchiveVerifyResponse>, (StatusCode, String)> {
+    if !state.admin_enabled {
+        return Err((StatusCode::NOT_FOUND, "Not Found".to_string()));
+    }
+
+    let Json(payload) = body.unwrap_or_default();
+    let service = state.service.clone();
+    let comics_dir = state.comics_dir.clone();
+    let report = tokio::task::spawn_blocking(move || {
+        archive_health::verify_archives(&service, &comics_dir, payload.force)
+    })
+    .await
+    .map_err(|error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Archive verification task failed: {error}"),
+        )
+    })?
+    .map_err(internal_error)?;
+
+    Ok(Json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/admin/archives/health",
+    tag = "db",
+    responses(
+        (status = 200, description = "Chapters whose last verification found a broken archive", body = ArchiveHealthResponse),
+        (status = 404, description = "Admin endpoints disabled", body = ErrorResponse),
+        (status = 500, description = "Internal error", body = ErrorResponse)
+    )
+)]
+async fn get_archives_health(
+    State(state): State<RestState>,
+) -> Result<Json<ArchiveHealthResponse>, (StatusCode, String)> {
+    if !state.admin_enabled {
+        return Err((StatusCode::NOT_FOUND, "Not Found".to_string()));
+    }
+
+    let chapters = archive_health::broken_chapters(&state.service).map_err(internal_error)?;
+    Ok(Json(ArchiveHealthResponse { chapters }))
+}
+
 fn admin_endpoints_enabled() -> bool {
     if let Ok(value) = std::env::var("REST_ADMIN_ENABLED") {
         let normalized = value.trim().to_ascii_lowercase();
@@ -836,6 +2819,14 @@ fn admin_endpoints_enabled() -> bool {
     cfg!(debug_assertions)
 }
 
+fn image_cache_max_bytes() -> u64 {
+    std::env::var("IMAGE_CACHE_MAX_MB")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .unwrap_or(512)
+        .saturating_mul(1024 * 1024)
+}
+
 fn internal_error(error: AppError) -> (StatusCode, String) {
     match error {
         AppError::InvalidTable(message) => (StatusCode::BAD_REQUEST, message),
@@ -853,6 +2844,20 @@ fn chapter_value_as_string(record: &DbRecord, field: &str) -> Option<String> {
         .filter(|value| !value.is_empty())
 }
 
+fn list_all_records(service: &DocumentService, table: &str) -> Result<Vec<DbRecord>, AppError> {
+    let mut records = Vec::new();
+    let mut offset = 0u32;
+    loop {
+        let batch = service.list(table, Some(LIST_PAGE_SIZE), Some(offset))?;
+        let batch_len = batch.len() as u32;
+        records.extend(batch);
+        if batch_len < LIST_PAGE_SIZE {
+            return Ok(records);
+        }
+        offset = offset.saturating_add(LIST_PAGE_SIZE);
+    }
+}
+
 fn pick_string_from_map(map: &Map<String, Value>, keys: &[&str]) -> Option<String> {
     for key in keys {
         if let Some(value) = map.get(*key) {
@@ -906,10 +2911,16 @@ fn normalize_source_tag(value: Option<&str>) -> Option<String> {
     }
 }
 
+/// Ids keep the ASCII naming scheme so re-importing a comic resolves to the records it
+/// created before.
 fn stable_import_id(prefix: &str, parts: &[&str]) -> String {
     let normalized = parts
         .iter()
-        .map(|part| sanitize_segment(part).to_ascii_lowercase().replace(' ', "-"))
+        .map(|part| {
+            legacy_sanitize_segment(part)
+                .to_ascii_lowercase()
+                .replace(' ', "-")
+        })
         .filter(|part| !part.is_empty())
         .collect::<Vec<_>>()
         .join(":");
@@ -989,6 +3000,11 @@ fn parse_pages_value(value: Option<&Value>) -> Vec<Value> {
         .collect()
 }
 
+/// Parsed `number` of a chapter, stored either as a label or as a JSON number.
+fn chapter_number(chapter: &DbRecord) -> Option<f64> {
+    crate::application::chapter_number(&chapter.data)
+}
+
 fn chapter_display_name(chapter: &DbRecord) -> String {
     chapter_value_as_string(chapter, "name")
         .or_else(|| {
@@ -1099,47 +3115,6 @@ fn resolve_external_page_file_path(comics_dir: &FsPath, source: &str) -> Option<
     None
 }
 
-fn resolve_chapter_cbz_path(
-    comics_dir: &FsPath,
-    comic_id: &str,
-    comic_name: &str,
-    chapter_name: &str,
-    chapter_number: Option<&str>,
-) -> Option<PathBuf> {
-    let comic_base = sanitize_segment(comic_name);
-    let chapter_file_candidates = chapter_file_candidates(chapter_name, chapter_number);
-    let mut dir_candidates = vec![
-        comics_dir.join(&comic_base),
-        comics_dir.join(sanitize_segment(comic_id)),
-    ];
-
-    if let Ok(entries) = fs::read_dir(comics_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-
-            if let Some(name) = path.file_name().and_then(|value| value.to_str()) {
-                if name == comic_base || name.starts_with(&format!("{comic_base} (")) {
-                    dir_candidates.push(path);
-                }
-            }
-        }
-    }
-
-    for dir in dir_candidates {
-        for candidate in &chapter_file_candidates {
-            let cbz = dir.join(candidate);
-            if cbz.exists() {
-                return Some(cbz);
-            }
-        }
-    }
-
-    None
-}
-
 fn resolve_cover_image_path(comics_dir: &FsPath, cover_ref: &str) -> Option<PathBuf> {
     let normalized = cover_ref.trim().trim_start_matches('/');
     if normalized.is_empty() || normalized.contains("..") {
@@ -1185,28 +3160,7 @@ fn resolve_comic_cover_path(
     let file_name = FsPath::new(cover_ref)
         .file_name()
         .and_then(|value| value.to_str())?;
-    let comic_base = sanitize_segment(comic_name);
-
-    let mut dir_candidates = vec![
-        comics_dir.join(&comic_base),
-        comics_dir.join(sanitize_segment(comic_id)),
-    ];
-
-    if let Ok(entries) = fs::read_dir(comics_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-            if let Some(name) = path.file_name().and_then(|value| value.to_str()) {
-                if name == comic_base || name.starts_with(&format!("{comic_base} (")) {
-                    dir_candidates.push(path.clone());
-                }
-            }
-        }
-    }
-
-    for dir in dir_candidates {
+    for dir in comic_dir_candidates(comics_dir, comic_id, comic_name) {
         let candidate = dir.join(file_name);
         if candidate.is_file() {
             return Some(candidate);
@@ -1221,27 +3175,7 @@ fn find_local_cover_in_comic_dir(
     comic_id: &str,
     comic_name: &str,
 ) -> Option<PathBuf> {
-    let comic_base = sanitize_segment(comic_name);
-    let mut dir_candidates = vec![
-        comics_dir.join(&comic_base),
-        comics_dir.join(sanitize_segment(comic_id)),
-    ];
-
-    if let Ok(entries) = fs::read_dir(comics_dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-            if let Some(name) = path.file_name().and_then(|value| value.to_str()) {
-                if name == comic_base || name.starts_with(&format!("{comic_base} (")) {
-                    dir_candidates.push(path);
-                }
-            }
-        }
-    }
-
-    for dir in dir_candidates {
+    for dir in comic_dir_candidates(comics_dir, comic_id, comic_name) {
         // Prefer explicit cover filenames first.
         for file_name in [
             "cover.jpg",
@@ -1278,107 +3212,3 @@ fn find_local_cover_in_comic_dir(
 
     None
 }
-
-fn chapter_file_candidates(chapter_name: &str, chapter_number: Option<&str>) -> Vec<String> {
-    let mut values = Vec::new();
-    let mut seen = HashSet::new();
-    let sanitized_name = sanitize_segment(chapter_name);
-
-    if seen.insert(sanitized_name.clone()) {
-        values.push(format!("{sanitized_name}.cbz"));
-    }
-
-    if let Some(number) = chapter_number {
-        let sanitized_number = sanitize_segment(number);
-        if seen.insert(sanitized_number.clone()) {
-            values.push(format!("{sanitized_number}.cbz"));
-        }
-
-        let old_format = sanitize_segment(&format!("{number} - {chapter_name}"));
-        if seen.insert(old_format.clone()) {
-            values.push(format!("{old_format}.cbz"));
-        }
-
-        let fallback = sanitize_segment(&format!("Chapter {number}"));
-        if seen.insert(fallback.clone()) {
-            values.push(format!("{fallback}.cbz"));
-        }
-    }
-
-    values
-}
-
-fn sanitize_segment(value: &str) -> String {
-    let mut out = String::new();
-    for ch in value.chars() {
-        let accepted = ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '.' | ' ');
-        if accepted {
-            out.push(ch);
-        } else {
-            out.push('_');
-        }
-    }
-
-    let cleaned = out.trim().trim_start_matches('.').to_string();
-    if cleaned.is_empty() {
-        "untitled".to_string()
-    } else if cleaned.len() > 180 {
-        cleaned[..180].to_string()
-    } else {
-        cleaned
-    }
-}
-
-fn list_image_entries(cbz_path: &FsPath) -> Result<Vec<CbzPageEntry>, AppError> {
-    let file = File::open(cbz_path).map_err(|error| AppError::infrastructure(error.to_string()))?;
-    let mut archive =
-        ZipArchive::new(file).map_err(|error| AppError::infrastructure(error.to_string()))?;
-
-    let mut pages = Vec::new();
-    for index in 0..archive.len() {
-        let entry = archive
-            .by_index(index)
-            .map_err(|error| AppError::infrastructure(error.to_string()))?;
-        if !entry.is_file() {
-            continue;
-        }
-
-        let file_name = entry.name().to_string();
-        if !is_image_file(&file_name) {
-            continue;
-        }
-
-        pages.push(CbzPageEntry {
-            archive_index: index,
-            file_name,
-        });
-    }
-
-    Ok(pages)
-}
-
-fn read_entry_bytes(cbz_path: &FsPath, archive_index: usize) -> Result<Vec<u8>, AppError> {
-    let file = File::open(cbz_path).map_err(|error| AppError::infrastructure(error.to_string()))?;
-    let mut archive =
-        ZipArchive::new(file).map_err(|error| AppError::infrastructure(error.to_string()))?;
-    let mut entry = archive
-        .by_index(archive_index)
-        .map_err(|error| AppError::infrastructure(error.to_string()))?;
-    let mut bytes = Vec::new();
-    entry
-        .read_to_end(&mut bytes)
-        .map_err(|error| AppError::infrastructure(error.to_string()))?;
-    Ok(bytes)
-}
-
-fn is_image_file(path: &str) -> bool {
-    path.rsplit('.')
-        .next()
-        .map(|ext| {
-            matches!(
-                ext.to_ascii_lowercase().as_str(),
-                "jpg" | "jpeg" | "png" | "webp" | "gif" | "bmp" | "avif"
-            )
-        })
-        .unwrap_or(false)
-}
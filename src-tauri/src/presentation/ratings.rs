@@ -0,0 +1,300 @@
+use std::{fs, path::Path};
+
+use serde_json::{json, Value};
+
+use super::{
+    chapter_value_as_string,
+    dto::{
+        ImportPersonalRatingsBody, ImportPersonalRatingsResponse, PersonalRating,
+        PersonalRatingBody,
+    },
+    list_all_records,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord},
+};
+
+const MAX_RATING: i64 = 10;
+const MAX_STARS: f64 = 5.0;
+const MAX_REVIEW_CHARS: usize = 20_000;
+
+/// The personal rating of a comic or work, empty when none was saved. `None` when the item
+/// does not exist.
+pub fn personal_rating(
+    service: &DocumentService,
+    item_type: &str,
+    item_id: &str,
+) -> Result<Option<PersonalRating>, AppError> {
+    if service.get(item_table(item_type)?, item_id)?.is_none() {
+        return Ok(None);
+    }
+    let record = service.get("personal_ratings", &rating_id(item_type, item_id))?;
+    Ok(Some(rating(item_type, item_id, record.as_ref())))
+}
+
+/// Changes the rating, favorite flag and review of a comic or work given in `body`. A
+/// cleared rating keeps its row, so a library scan does not restore it from an old
+/// `personal.json`.
+pub fn set_personal_rating(
+    service: &DocumentService,
+    item_type: &str,
+    item_id: &str,
+    body: PersonalRatingBody,
+) -> Result<Option<PersonalRating>, AppError> {
+    if service.get(item_table(item_type)?, item_id)?.is_none() {
+        return Ok(None);
+    }
+    let id = rating_id(item_type, item_id);
+    let stored = service.get("personal_ratings", &id)?;
+    let stored_field = |field: &str| {
+        stored
+            .as_ref()
+            .and_then(|record| record.data.get(field).cloned())
+            .unwrap_or(Value::Null)
+    };
+
+    let rating_value = if body.rating.is_some() || body.stars.is_some() {
+        json!(rating_value(body.rating.flatten(), body.stars.flatten())?)
+    } else {
+        stored_field("rating")
+    };
+    let review = match body.review {
+        Some(review) => {
+            let review = review
+                .map(|review| review.trim().to_string())
+                .filter(|review| !review.is_empty());
+            if review
+                .as_ref()
+                .is_some_and(|review| review.chars().count() > MAX_REVIEW_CHARS)
+            {
+                return Err(AppError::Validation(format!(
+                    "review cannot be longer than {MAX_REVIEW_CHARS} characters"
+                )));
+            }
+            json!(review)
+        }
+        None => stored_field("review"),
+    };
+    let favorite = body
+        .favorite
+        .or_else(|| stored_field("favorite").as_bool())
+        .unwrap_or(false);
+
+    let record = service.upsert(
+        "personal_ratings",
+        Some(id),
+        json!({
+            "itemType": item_type,
+            "itemId": item_id,
+            "rating": rating_value,
+            "favorite": favorite,
+            "review": review,
+        }),
+    )?;
+    Ok(Some(rating(item_type, item_id, Some(&record))))
+}
+
+/// What a comic export carries of the comic's personal rating, `None` when nothing was saved.
+pub fn export_personal_rating(
+    service: &DocumentService,
+    comic_id: &str,
+) -> Result<Option<Value>, AppError> {
+    Ok(service
+        .get("personal_ratings", &rating_id("comic", comic_id))?
+        .filter(|record| !is_cleared(record))
+        .map(|record| {
+            json!({
+                "rating": record.data.get("rating").cloned().unwrap_or(Value::Null),
+                "favorite": record.data.get("favorite").cloned().unwrap_or(Value::Bool(false)),
+                "review": record.data.get("review").cloned().unwrap_or(Value::Null),
+            })
+        }))
+}
+
+/// Every saved rating of comics and works, by item. Cleared ratings are left out.
+pub fn export_personal_ratings(service: &DocumentService) -> Result<Vec<PersonalRating>, AppError> {
+    let mut items = list_all_records(service, "personal_ratings")?
+        .iter()
+        .filter(|record| !is_cleared(record))
+        .filter_map(|record| {
+            let item_type = chapter_value_as_string(record, "itemType")?;
+            let item_id = chapter_value_as_string(record, "itemId")?;
+            Some(rating(&item_type, &item_id, Some(record)))
+        })
+        .collect::<Vec<_>>();
+    items.sort_by(|left, right| {
+        (&left.item_type, &left.item_id).cmp(&(&right.item_type, &right.item_id))
+    });
+    Ok(items)
+}
+
+/// Restores the ratings of a ratings export, skipping items that are not in this library
+/// or already have a rating.
+pub fn import_personal_ratings(
+    service: &DocumentService,
+    body: ImportPersonalRatingsBody,
+) -> Result<ImportPersonalRatingsResponse, AppError> {
+    let mut report = ImportPersonalRatingsResponse {
+        restored: 0,
+        skipped: 0,
+    };
+    for item in body.items {
+        let restored = restore_rating(
+            service,
+            &item.item_type,
+            &item.item_id,
+            PersonalRatingBody {
+                rating: Some(item.rating),
+                stars: None,
+                favorite: item.favorite,
+                review: Some(item.review),
+            },
+        );
+        match restored {
+            Ok(true) => report.restored += 1,
+            Ok(false) | Err(AppError::Validation(_)) => report.skipped += 1,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(report)
+}
+
+/// Restores a personal rating written by a comic export, unless the comic already has one.
+/// A file that cannot be read or parsed is skipped.
+pub fn import_personal_rating(
+    service: &DocumentService,
+    comic_id: &str,
+    path: &Path,
+) -> Result<(), AppError> {
+    let Some(saved) = fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+    else {
+        return Ok(());
+    };
+    let body = PersonalRatingBody {
+        rating: Some(saved.get("rating").and_then(Value::as_i64)),
+        stars: None,
+        favorite: saved.get("favorite").and_then(Value::as_bool),
+        review: Some(
+            saved
+                .get("review")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        ),
+    };
+    match restore_rating(service, "comic", comic_id, body) {
+        Ok(_) => Ok(()),
+        Err(AppError::Validation(message)) => {
+            eprintln!("Skipping personal rating in {}: {message}", path.display());
+            Ok(())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Saves `body` as the rating of an item without a rating row, cleared ones included.
+/// Returns whether the item exists and had none.
+fn restore_rating(
+    service: &DocumentService,
+    item_type: &str,
+    item_id: &str,
+    body: PersonalRatingBody,
+) -> Result<bool, AppError> {
+    if service
+        .get("personal_ratings", &rating_id(item_type, item_id))?
+        .is_some()
+    {
+        return Ok(false);
+    }
+    Ok(set_personal_rating(service, item_type, item_id, body)?.is_some())
+}
+
+/// The 0 to 10 rating given either directly or as 0 to 5 stars in half steps.
+fn rating_value(rating: Option<i64>, stars: Option<f64>) -> Result<Option<i64>, AppError> {
+    match (rating, stars) {
+        (Some(rating), _) if !(0..=MAX_RATING).contains(&rating) => Err(AppError::Validation(
+            format!("rating must be between 0 and {MAX_RATING}"),
+        )),
+        (_, Some(stars)) if !(0.0..=MAX_STARS).contains(&stars) || (stars * 2.0).fract() != 0.0 => {
+            Err(AppError::Validation(format!(
+                "stars must be between 0 and {MAX_STARS} in half steps"
+            )))
+        }
+        (Some(rating), Some(stars)) if rating != (stars * 2.0) as i64 => Err(AppError::Validation(
+            "rating and stars disagree".to_string(),
+        )),
+        (Some(rating), _) => Ok(Some(rating)),
+        (None, Some(stars)) => Ok(Some((stars * 2.0) as i64)),
+        (None, None) => Ok(None),
+    }
+}
+
+fn item_table(item_type: &str) -> Result<&'static str, AppError> {
+    match item_type {
+        "comic" => Ok("comics"),
+        "work" => Ok("works"),
+        _ => Err(AppError::Validation(format!(
+            "Unsupported item type: {item_type}"
+        ))),
+    }
+}
+
+fn rating_id(item_type: &str, item_id: &str) -> String {
+    format!("{item_type}:{item_id}")
+}
+
+/// A row kept after its rating, favorite flag and review were all cleared.
+fn is_cleared(record: &DbRecord) -> bool {
+    record.data.get("rating").is_none_or(Value::is_null)
+        && !record
+            .data
+            .get("favorite")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        && record.data.get("review").is_none_or(Value::is_null)
+}
+
+fn rating(item_type: &str, item_id: &str, record: Option<&DbRecord>) -> PersonalRating {
+    let rating = record.and_then(|record| record.data.get("rating").and_then(Value::as_i64));
+    PersonalRating {
+        item_type: item_type.to_string(),
+        item_id: item_id.to_string(),
+        rating,
+        stars: rating.map(|rating| rating as f64 / 2.0),
+        favorite: record
+            .and_then(|record| record.data.get("favorite").and_then(Value::as_bool))
+            .unwrap_or(false),
+        review: record.and_then(|record| chapter_value_as_string(record, "review")),
+        updated_at: record.map(|record| record.updated_at.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ratings_out_of_ten_or_half_stars() {
+        assert_eq!(rating_value(Some(7), None).expect("rating"), Some(7));
+        assert_eq!(rating_value(None, Some(3.5)).expect("stars"), Some(7));
+        assert_eq!(rating_value(Some(8), Some(4.0)).expect("both"), Some(8));
+        assert_eq!(rating_value(None, None).expect("cleared"), None);
+
+        assert!(rating_value(Some(11), None).is_err());
+        assert!(rating_value(None, Some(3.3)).is_err());
+        assert!(rating_value(None, Some(-0.5)).is_err());
+        assert!(rating_value(Some(6), Some(4.0)).is_err());
+    }
+
+    #[test]
+    fn tells_cleared_fields_from_missing_ones() {
+        let body: PersonalRatingBody =
+            serde_json::from_value(json!({ "rating": null, "favorite": true })).expect("body");
+        assert_eq!(body.rating, Some(None));
+        assert_eq!(body.stars, None);
+        assert_eq!(body.review, None);
+        assert_eq!(body.favorite, Some(true));
+    }
+}
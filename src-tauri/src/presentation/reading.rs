@@ -0,0 +1,210 @@
+use std::{cell::RefCell, collections::HashMap, path::Path};
+
+use serde_json::Value;
+
+use super::{
+    chapter_display_name, chapter_external_pages, chapter_value_as_string, compare_chapter_order,
+    dto::ContinueReadingItem, locate_chapter_archive,
+};
+use crate::{
+    application::{page_number, DocumentService},
+    domain::{AppError, DbRecord},
+    infrastructure::archive::{list_image_entries, open_archive},
+};
+
+struct ChapterProgress {
+    page: i64,
+    total_pages: Option<i64>,
+    updated_at: String,
+}
+
+impl ChapterProgress {
+    fn from_record(record: &DbRecord) -> Option<(String, Self)> {
+        let chapter_id = chapter_value_as_string(record, "chapterId")?;
+        let progress = Self {
+            page: page_number(record.data.get("page")).unwrap_or(0),
+            total_pages: page_number(record.data.get("totalPages")),
+            updated_at: record.updated_at.clone(),
+        };
+        Some((chapter_id, progress))
+    }
+
+    fn is_read(&self) -> bool {
+        self.total_pages
+            .is_some_and(|total| total > 0 && self.page >= total)
+    }
+}
+
+/// The chapter to open next in a comic, or `None` once every chapter is read.
+pub fn next_chapter(
+    service: &DocumentService,
+    comic: &DbRecord,
+) -> Result<Option<ContinueReadingItem>, AppError> {
+    let comic_id = Value::String(comic.id.clone());
+    let chapters =
+        service.find_by_json_field("chapters", "comicId", comic_id.clone(), Some(u32::MAX))?;
+    let progress = service
+        .find_by_json_field("read_progress", "comicId", comic_id, Some(u32::MAX))?
+        .iter()
+        .filter_map(ChapterProgress::from_record)
+        .collect();
+
+    Ok(resolve_next(comic, chapters, &progress))
+}
+
+/// The next chapter of every started comic that is not finished yet, most recently read
+/// first.
+pub fn continue_reading(
+    service: &DocumentService,
+    limit: usize,
+) -> Result<Vec<ContinueReadingItem>, AppError> {
+    let limit = u32::try_from(limit).unwrap_or(u32::MAX);
+    service
+        .recently_read_comics(limit)?
+        .iter()
+        .filter_map(|comic| next_chapter(service, comic).transpose())
+        .collect()
+}
+
+/// Resumes the chapter read last if it is unfinished; otherwise moves on to the first unread
+/// chapter after it, wrapping around to earlier chapters that were skipped.
+fn resolve_next(
+    comic: &DbRecord,
+    mut chapters: Vec<DbRecord>,
+    progress: &HashMap<String, ChapterProgress>,
+) -> Option<ContinueReadingItem> {
+    chapters.sort_by(compare_chapter_order);
+    let progress_of = |chapter: &DbRecord| progress.get(&chapter.id);
+
+    let last_active = chapters
+        .iter()
+        .enumerate()
+        .filter_map(|(index, chapter)| Some((index, progress_of(chapter)?)))
+        .max_by(|left, right| left.1.updated_at.cmp(&right.1.updated_at));
+    let start = match last_active {
+        Some((index, progress)) if !progress.is_read() => index,
+        Some((index, _)) => index + 1,
+        None => 0,
+    };
+    let position = (start..chapters.len())
+        .chain(0..start.min(chapters.len()))
+        .find(|&index| !progress_of(&chapters[index]).is_some_and(ChapterProgress::is_read))?;
+
+    let chapter = &chapters[position];
+    let chapter_progress = progress_of(chapter);
+    let resume_page = chapter_progress.map_or(0, |progress| progress.page);
+    Some(ContinueReadingItem {
+        comic_id: comic.id.clone(),
+        comic_name: chapter_value_as_string(comic, "name"),
+        chapter_id: chapter.id.clone(),
+        chapter_name: chapter_display_name(chapter),
+        chapter_number: chapter_value_as_string(chapter, "number"),
+        resume_page,
+        total_pages: chapter_progress.and_then(|progress| progress.total_pages),
+        status: if resume_page > 0 {
+            "inProgress"
+        } else {
+            "unread"
+        }
+        .to_string(),
+        last_read_at: last_active.map(|(_, progress)| progress.updated_at.clone()),
+    })
+}
+
+/// Counts a chapter's pages from its local archive, or else from its remote page list.
+/// Comic names are looked up once per comic.
+pub(super) fn chapter_page_counter<'a>(
+    service: &'a DocumentService,
+    comics_dir: &'a Path,
+) -> impl Fn(&DbRecord) -> Option<i64> + 'a {
+    let comic_names = RefCell::new(HashMap::new());
+    move |chapter| {
+        let comic_id = chapter_value_as_string(chapter, "comicId").unwrap_or_default();
+        let comic_name = comic_names
+            .borrow_mut()
+            .entry(comic_id.clone())
+            .or_insert_with(|| {
+                service
+                    .get("comics", &comic_id)
+                    .ok()
+                    .flatten()
+                    .and_then(|comic| chapter_value_as_string(&comic, "name"))
+                    .unwrap_or_else(|| comic_id.clone())
+            })
+            .clone();
+
+        let archive_pages = locate_chapter_archive(comics_dir, chapter, &comic_id, &comic_name)
+            .and_then(|path| open_archive(&path).ok())
+            .and_then(|archive| list_image_entries(archive.as_ref()).ok())
+            .map_or(0, |entries| entries.len());
+        let pages = match archive_pages {
+            0 => chapter_external_pages(chapter).len(),
+            count => count,
+        };
+        (pages > 0).then_some(pages as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(id: &str, data: Value, updated_at: &str) -> DbRecord {
+        DbRecord {
+            id: id.to_string(),
+            data,
+            created_at: updated_at.to_string(),
+            updated_at: updated_at.to_string(),
+        }
+    }
+
+    #[test]
+    fn moves_past_finished_chapters_in_number_order() {
+        let comic = record("comic-1", json!({ "name": "Pablo" }), "");
+        let chapters = ["Ch. 10", "2", "1"]
+            .iter()
+            .map(|number| {
+                record(
+                    &format!("c-{number}"),
+                    json!({ "comicId": "comic-1", "number": number }),
+                    "",
+                )
+            })
+            .collect::<Vec<_>>();
+        let progress = [
+            record(
+                "p-1",
+                json!({ "chapterId": "c-1", "page": 20, "totalPages": 20 }),
+                "2026-03-01T10:00:00.000Z",
+            ),
+            record(
+                "p-2",
+                json!({ "chapterId": "c-2", "page": 18, "totalPages": 18 }),
+                "2026-03-02T10:00:00.000Z",
+            ),
+        ]
+        .iter()
+        .filter_map(ChapterProgress::from_record)
+        .collect::<HashMap<_, _>>();
+
+        let next = resolve_next(&comic, chapters.clone(), &progress).expect("next chapter");
+        assert_eq!(next.chapter_id, "c-Ch. 10");
+        assert_eq!(next.status, "unread");
+        assert_eq!(
+            next.last_read_at.as_deref(),
+            Some("2026-03-02T10:00:00.000Z")
+        );
+
+        let mut progress = progress;
+        progress.insert(
+            "c-Ch. 10".to_string(),
+            ChapterProgress {
+                page: 20,
+                total_pages: Some(20),
+                updated_at: "2026-03-03T10:00:00.000Z".to_string(),
+            },
+        );
+        assert!(resolve_next(&comic, chapters, &progress).is_none());
+    }
+}
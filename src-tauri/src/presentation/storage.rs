@@ -0,0 +1,450 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use super::{
+    chapter_display_name, chapter_value_as_string,
+    dto::{
+        ChapterStorageUsage, ComicStorageUsage, OrphanCleanupBody, OrphanCleanupFailure,
+        OrphanCleanupResponse, OrphanEntry, StorageUsageResponse,
+    },
+    list_all_records, locate_chapter_archive,
+    uploads::CUSTOM_COVER_FIELD,
+    LOCAL_COVER_FIELD,
+};
+use crate::{
+    application::DocumentService,
+    domain::{AppError, DbRecord},
+    infrastructure::{
+        archive::is_chapter_archive_path,
+        covers::extracted_cover_path,
+        library_files::{comic_dir_matches, resolve_local_path, to_local_path},
+        storage::{delete_path, disk_usage, move_to_trash},
+    },
+};
+
+/// Comic fields that may name a file in the covers directory.
+const COVER_REFERENCE_FIELDS: [&str; 5] = [
+    LOCAL_COVER_FIELD,
+    CUSTOM_COVER_FIELD,
+    "coverUrl",
+    "cover",
+    "image",
+];
+/// Partial files left behind by an interrupted archive upload.
+const UPLOAD_TEMP_PREFIX: &str = ".upload-";
+/// How long a partial upload stays untouched before it counts as interrupted rather than
+/// still being written.
+const UPLOAD_TEMP_GRACE: Duration = Duration::from_secs(60 * 60);
+const CLEANUP_MODES: [&str; 2] = ["trash", "delete"];
+
+struct IndexedComic {
+    record: DbRecord,
+    name: String,
+    /// Chapters with the local archive each one resolves to.
+    archives: Vec<(DbRecord, PathBuf)>,
+}
+
+struct Orphan {
+    entry: OrphanEntry,
+    path: PathBuf,
+}
+
+/// Bytes used by each comic: its folders, chapter archives kept outside them, and its
+/// covers. Comics are listed largest first.
+pub fn storage_usage(
+    service: &DocumentService,
+    comics_dir: &Path,
+    covers_dir: &Path,
+) -> Result<StorageUsageResponse, AppError> {
+    let folders = top_level_folders(comics_dir, covers_dir);
+    let mut comics = index_library(service, comics_dir)?
+        .into_iter()
+        .map(|comic| {
+            let comic_folders = folders
+                .iter()
+                .filter(|(_, name)| comic_dir_matches(&comic.record.id, &comic.name, name))
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            let chapters = comic
+                .archives
+                .iter()
+                .map(|(chapter, path)| ChapterStorageUsage {
+                    chapter_id: chapter.id.clone(),
+                    name: chapter_display_name(chapter),
+                    local_path: to_local_path(comics_dir, path),
+                    bytes: disk_usage(path),
+                })
+                .collect::<Vec<_>>();
+            let outside_folders = comic
+                .archives
+                .iter()
+                .zip(&chapters)
+                .filter(|((_, path), _)| !comic_folders.iter().any(|dir| path.starts_with(dir)))
+                .map(|(_, usage)| usage.bytes)
+                .sum::<u64>();
+            let covers_bytes = cover_file_names(&comic.record)
+                .iter()
+                .filter_map(|file_name| resolve_local_path(covers_dir, file_name))
+                .map(|path| disk_usage(&path))
+                .sum::<u64>();
+            let folders_bytes = comic_folders.iter().map(|dir| disk_usage(dir)).sum::<u64>();
+
+            ComicStorageUsage {
+                comic_id: comic.record.id.clone(),
+                name: comic.name,
+                total_bytes: folders_bytes + outside_folders + covers_bytes,
+                covers_bytes,
+                chapters,
+            }
+        })
+        .collect::<Vec<_>>();
+    comics.sort_by_key(|comic| Reverse(comic.total_bytes));
+
+    let covers_outside = if covers_dir.starts_with(comics_dir) {
+        0
+    } else {
+        disk_usage(covers_dir)
+    };
+    Ok(StorageUsageResponse {
+        total_bytes: disk_usage(comics_dir) + covers_outside,
+        comics,
+    })
+}
+
+/// Files and folders that no comic or chapter accounts for: comic folders no comic
+/// matches, chapter archives no chapter resolves to, interrupted uploads, and covers no
+/// comic refers to. Other files inside a comic's folder are left alone.
+pub fn find_orphans(
+    service: &DocumentService,
+    comics_dir: &Path,
+    covers_dir: &Path,
+) -> Result<Vec<OrphanEntry>, AppError> {
+    Ok(collect_orphans(service, comics_dir, covers_dir)?
+        .into_iter()
+        .map(|orphan| orphan.entry)
+        .collect())
+}
+
+/// Moves the current orphans, or the selected ones among them, to the trash or deletes
+/// them. Only paths that are orphans at the time of the call are ever touched, and
+/// deleting them all takes an explicit `confirm`.
+pub fn clean_orphans(
+    service: &DocumentService,
+    comics_dir: &Path,
+    covers_dir: &Path,
+    body: OrphanCleanupBody,
+) -> Result<OrphanCleanupResponse, AppError> {
+    let mode = cleanup_mode(&body)?;
+    let selected = body.only.map(|targets| {
+        targets
+            .into_iter()
+            .map(|target| (target.location, target.path))
+            .collect::<HashSet<_>>()
+    });
+
+    let mut report = OrphanCleanupResponse {
+        dry_run: body.dry_run,
+        mode,
+        removed: Vec::new(),
+        failed: Vec::new(),
+        freed_bytes: 0,
+    };
+    for orphan in collect_orphans(service, comics_dir, covers_dir)? {
+        let key = (orphan.entry.location.clone(), orphan.entry.path.clone());
+        if selected
+            .as_ref()
+            .is_some_and(|selected| !selected.contains(&key))
+        {
+            continue;
+        }
+
+        let result = if report.dry_run {
+            Ok(())
+        } else if report.mode == "delete" {
+            delete_path(&orphan.path)
+        } else {
+            move_to_trash(&orphan.path)
+        };
+        match result {
+            Ok(()) => {
+                report.freed_bytes += orphan.entry.bytes;
+                report.removed.push(orphan.entry);
+            }
+            Err(error) => report.failed.push(OrphanCleanupFailure {
+                entry: orphan.entry,
+                error: error.to_string(),
+            }),
+        }
+    }
+
+    Ok(report)
+}
+
+fn cleanup_mode(body: &OrphanCleanupBody) -> Result<String, AppError> {
+    let mode = body
+        .mode
+        .clone()
+        .unwrap_or_else(|| CLEANUP_MODES[0].to_string());
+    if !CLEANUP_MODES.contains(&mode.as_str()) {
+        return Err(AppError::Validation(format!(
+            "Unsupported cleanup mode: {mode}"
+        )));
+    }
+    if mode == "delete" && !body.dry_run && body.only.is_none() && !body.confirm {
+        return Err(AppError::Validation(
+            "Deleting every orphan needs `confirm: true`, or list them in `only`".to_string(),
+        ));
+    }
+    Ok(mode)
+}
+
+fn collect_orphans(
+    service: &DocumentService,
+    comics_dir: &Path,
+    covers_dir: &Path,
+) -> Result<Vec<Orphan>, AppError> {
+    let comics = index_library(service, comics_dir)?;
+    let archives = comics
+        .iter()
+        .flat_map(|comic| comic.archives.iter().map(|(_, path)| path.clone()))
+        .collect::<HashSet<_>>();
+    let holds_archive = |path: &Path| archives.iter().any(|archive| archive.starts_with(path));
+
+    let mut orphans = Vec::new();
+    let mut push = |location: &str, root: &Path, path: PathBuf| {
+        let entry = OrphanEntry {
+            location: location.to_string(),
+            path: to_local_path(root, &path).unwrap_or_default(),
+            kind: if path.is_dir() { "folder" } else { "file" }.to_string(),
+            bytes: disk_usage(&path),
+        };
+        orphans.push(Orphan { entry, path });
+    };
+
+    for path in children(comics_dir) {
+        let Some(name) = file_name(&path) else {
+            continue;
+        };
+        if name.starts_with(UPLOAD_TEMP_PREFIX) {
+            if is_interrupted_upload(&path, SystemTime::now()) {
+                push("comics", comics_dir, path);
+            }
+            continue;
+        }
+        if path == covers_dir || name.starts_with('.') {
+            continue;
+        }
+
+        if !path.is_dir() {
+            if is_chapter_archive_path(&path) && !archives.contains(&path) {
+                push("comics", comics_dir, path);
+            }
+            continue;
+        }
+
+        let claimed = comics
+            .iter()
+            .any(|comic| comic_dir_matches(&comic.record.id, &comic.name, &name));
+        if !claimed && !holds_archive(&path) {
+            push("comics", comics_dir, path);
+            continue;
+        }
+
+        for child in children(&path) {
+            let Some(child_name) = file_name(&child) else {
+                continue;
+            };
+            let orphaned = if child_name.starts_with(UPLOAD_TEMP_PREFIX) {
+                is_interrupted_upload(&child, SystemTime::now())
+            } else {
+                !child_name.starts_with('.')
+                    && is_chapter_archive_path(&child)
+                    && !holds_archive(&child)
+            };
+            if orphaned {
+                push("comics", comics_dir, child);
+            }
+        }
+    }
+
+    let covers = referenced_covers(covers_dir, comics.iter().map(|comic| &comic.record));
+    for path in children(covers_dir) {
+        let referenced = file_name(&path).is_some_and(|name| covers.contains(&name));
+        if !referenced && path.is_file() {
+            push("covers", covers_dir, path);
+        }
+    }
+
+    Ok(orphans)
+}
+
+fn index_library(
+    service: &DocumentService,
+    comics_dir: &Path,
+) -> Result<Vec<IndexedComic>, AppError> {
+    let mut comics = list_all_records(service, "comics")?
+        .into_iter()
+        .map(|record| IndexedComic {
+            name: chapter_value_as_string(&record, "name").unwrap_or_else(|| record.id.clone()),
+            record,
+            archives: Vec::new(),
+        })
+        .collect::<Vec<_>>();
+    let positions = comics
+        .iter()
+        .enumerate()
+        .map(|(index, comic)| (comic.record.id.clone(), index))
+        .collect::<HashMap<_, _>>();
+
+    // Archives of chapters whose comic is gone are left unaccounted for, so they show up
+    // as orphans.
+    for chapter in list_all_records(service, "chapters")? {
+        let Some(comic) = chapter_value_as_string(&chapter, "comicId")
+            .and_then(|comic_id| positions.get(&comic_id))
+            .map(|index| &mut comics[*index])
+        else {
+            continue;
+        };
+        if let Some(path) =
+            locate_chapter_archive(comics_dir, &chapter, &comic.record.id, &comic.name)
+        {
+            comic.archives.push((chapter, path));
+        }
+    }
+
+    Ok(comics)
+}
+
+/// File names in the covers directory that a comic refers to. Remote URLs never do.
+fn cover_file_names(comic: &DbRecord) -> Vec<String> {
+    COVER_REFERENCE_FIELDS
+        .iter()
+        .filter_map(|field| chapter_value_as_string(comic, field))
+        .filter(|value| !value.starts_with("http://") && !value.starts_with("https://"))
+        .filter_map(|value| file_name(Path::new(&value)))
+        .collect()
+}
+
+/// Cover file names some comic accounts for. A comic's extracted cover counts even before
+/// the scan records it on the comic, since serving the cover writes it on its own.
+fn referenced_covers<'a>(
+    covers_dir: &Path,
+    comics: impl Iterator<Item = &'a DbRecord>,
+) -> HashSet<String> {
+    comics
+        .flat_map(|comic| {
+            let mut names = cover_file_names(comic);
+            names.extend(file_name(&extracted_cover_path(covers_dir, &comic.id)));
+            names
+        })
+        .collect()
+}
+
+/// Whether a partial upload has sat untouched for longer than an upload in progress would.
+fn is_interrupted_upload(path: &Path, now: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| {
+            now.duration_since(modified)
+                .is_ok_and(|age| age > UPLOAD_TEMP_GRACE)
+        })
+}
+
+fn top_level_folders(comics_dir: &Path, covers_dir: &Path) -> Vec<(PathBuf, String)> {
+    children(comics_dir)
+        .into_iter()
+        .filter(|path| path.is_dir() && path != covers_dir)
+        .filter_map(|path| Some((path.clone(), file_name(&path)?)))
+        .filter(|(_, name)| !name.starts_with('.'))
+        .collect()
+}
+
+fn children(dir: &Path) -> Vec<PathBuf> {
+    let mut children = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    children.sort();
+    children
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .and_then(|value| value.to_str())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(json: &str) -> OrphanCleanupBody {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn deletes_only_when_confirmed_or_selected() {
+        assert!(OrphanCleanupBody::default().dry_run);
+        assert!(body("{}").dry_run);
+        assert_eq!(cleanup_mode(&body("{}")).unwrap(), "trash");
+        assert!(cleanup_mode(&body(r#"{"mode":"shred"}"#)).is_err());
+
+        assert!(cleanup_mode(&body(r#"{"mode":"delete"}"#)).is_ok());
+        assert!(cleanup_mode(&body(r#"{"mode":"delete","dryRun":false}"#)).is_err());
+        assert!(cleanup_mode(&body(r#"{"mode":"delete","dryRun":false,"confirm":true}"#)).is_ok());
+        assert!(cleanup_mode(&body(
+            r#"{"mode":"delete","dryRun":false,"only":[{"location":"comics","path":"Old"}]}"#
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    fn keeps_uploads_in_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let upload = dir.path().join(".upload-1.part");
+        fs::write(&upload, b"partial").unwrap();
+        let now = SystemTime::now();
+
+        assert!(!is_interrupted_upload(&upload, now));
+        assert!(is_interrupted_upload(
+            &upload,
+            now + UPLOAD_TEMP_GRACE + Duration::from_secs(1)
+        ));
+        assert!(!is_interrupted_upload(
+            &dir.path().join("missing.part"),
+            now
+        ));
+    }
+
+    #[test]
+    fn counts_extracted_covers_as_referenced() {
+        let record = |id: &str, data: serde_json::Value| DbRecord {
+            id: id.to_string(),
+            data,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        let comics = [
+            record("comic-1", serde_json::json!({ "name": "Pablo" })),
+            record(
+                "comic-2",
+                serde_json::json!({ "customCover": "comic-2-custom.png" }),
+            ),
+        ];
+
+        let covers = referenced_covers(Path::new("covers"), comics.iter());
+        assert!(covers.contains("comic-1.jpg"));
+        assert!(covers.contains("comic-2.jpg"));
+        assert!(covers.contains("comic-2-custom.png"));
+        assert!(!covers.contains("comic-3.jpg"));
+    }
+}
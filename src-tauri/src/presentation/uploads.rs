@@ -0,0 +1,351 @@
+use std::{
+    fs,
+    io::{Cursor, ErrorKind},
+    path::Path,
+};
+
+use axum::{
+    extract::multipart::{Field, Multipart, MultipartError},
+    http::StatusCode,
+};
+use image::ImageReader;
+use serde_json::Value;
+use tokio::{fs::File, io::AsyncWriteExt};
+
+use super::{
+    chapter_value_as_string, internal_error,
+    library_scan::{mark_chapter_offline, merge_comic_info},
+};
+use crate::{
+    application::DocumentService,
+    domain::{ArchiveHealthStatus, DbRecord},
+    infrastructure::{
+        archive::{list_image_entries, CbzReader},
+        archive_health::verify_archive_pages,
+        library_files::{resolve_local_path, sanitize_segment, to_local_path},
+    },
+};
+
+pub(super) const MAX_COVER_UPLOAD_BYTES: usize = 20 * 1024 * 1024;
+pub(super) const MAX_ARCHIVE_UPLOAD_BYTES: usize = 1024 * 1024 * 1024;
+/// Comic field naming the user-selected cover stored in the covers directory.
+pub(super) const CUSTOM_COVER_FIELD: &str = "customCover";
+/// Room for the multipart boundaries and part headers around the file itself.
+const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+const FILE_PART: &str = "file";
+
+type UploadError = (StatusCode, String);
+
+/// Request body limit for an upload whose file may be up to `max_file_bytes`.
+pub(super) fn body_limit(max_file_bytes: usize) -> usize {
+    max_file_bytes + MULTIPART_OVERHEAD_BYTES
+}
+
+/// The uploaded file, which clients send as the first part, named `file`.
+pub(super) async fn file_part(multipart: &mut Multipart) -> Result<Field<'_>, UploadError> {
+    let field = multipart
+        .next_field()
+        .await
+        .map_err(multipart_error)?
+        .ok_or_else(|| (StatusCode::BAD_REQUEST, "Missing `file` part".to_string()))?;
+    if field.name() != Some(FILE_PART) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Expected the upload as a `file` part".to_string(),
+        ));
+    }
+    Ok(field)
+}
+
+pub(super) async fn read_part(
+    mut field: Field<'_>,
+    max_bytes: usize,
+) -> Result<Vec<u8>, UploadError> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        if bytes.len() + chunk.len() > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Streams the part into `path` so archives never sit in memory. A partial file is removed
+/// when the upload fails.
+pub(super) async fn write_part(
+    field: Field<'_>,
+    path: &Path,
+    max_bytes: usize,
+) -> Result<(), UploadError> {
+    let result = copy_part(field, path, max_bytes).await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+    result
+}
+
+async fn copy_part(mut field: Field<'_>, path: &Path, max_bytes: usize) -> Result<(), UploadError> {
+    let mut file = File::create(path).await.map_err(write_error)?;
+    let mut written = 0usize;
+    while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+        written += chunk.len();
+        if written > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        file.write_all(&chunk).await.map_err(write_error)?;
+    }
+    file.flush().await.map_err(write_error)?;
+
+    if written == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The uploaded file is empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Stores `bytes` as the comic's custom cover once they decode as an image. The previous
+/// custom cover is replaced.
+pub(super) fn store_custom_cover(
+    service: &DocumentService,
+    covers_dir: &Path,
+    comic: &DbRecord,
+    bytes: &[u8],
+) -> Result<DbRecord, UploadError> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .map_err(write_error)?;
+    let extension = reader
+        .format()
+        .and_then(|format| format.extensions_str().first().copied())
+        .ok_or_else(|| {
+            (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "The cover must be a BMP, GIF, JPEG, PNG or WebP image".to_string(),
+            )
+        })?;
+    reader.decode().map_err(|error| {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("The cover is not a readable image: {error}"),
+        )
+    })?;
+
+    fs::create_dir_all(covers_dir).map_err(write_error)?;
+    let file_name = format!("{}-custom.{extension}", sanitize_segment(&comic.id));
+    let path = covers_dir.join(&file_name);
+    let temp_path = covers_dir.join(format!("{file_name}.tmp"));
+    fs::write(&temp_path, bytes)
+        .and_then(|_| fs::rename(&temp_path, &path))
+        .map_err(|error| {
+            let _ = fs::remove_file(&temp_path);
+            write_error(error)
+        })?;
+
+    // A cover uploaded in another format would otherwise be left behind.
+    if let Some(previous) = chapter_value_as_string(comic, CUSTOM_COVER_FIELD)
+        .filter(|previous| *previous != file_name)
+        .and_then(|previous| resolve_local_path(covers_dir, &previous))
+    {
+        let _ = fs::remove_file(previous);
+    }
+
+    let not_found = || (StatusCode::NOT_FOUND, "Comic not found".to_string());
+    let recorded = service
+        .set_json_field(
+            "comics",
+            &comic.id,
+            CUSTOM_COVER_FIELD,
+            &Value::String(file_name),
+        )
+        .map_err(internal_error)?;
+    if !recorded {
+        return Err(not_found());
+    }
+    service
+        .get("comics", &comic.id)
+        .map_err(internal_error)?
+        .ok_or_else(not_found)
+}
+
+/// Moves an uploaded archive from `temp_path` to `target` once every page decodes, then
+/// marks the chapter offline at its new path. An archive already at `target` is kept and
+/// the upload rejected.
+pub(super) fn install_chapter_archive(
+    service: &DocumentService,
+    comics_dir: &Path,
+    chapter: &DbRecord,
+    temp_path: &Path,
+    target: &Path,
+) -> Result<DbRecord, UploadError> {
+    let installed = validate_chapter_archive(temp_path).and_then(|()| {
+        if let Some(folder) = target.parent() {
+            fs::create_dir_all(folder).map_err(write_error)?;
+        }
+        move_without_overwrite(temp_path, target)
+    });
+    if let Err(error) = installed {
+        let _ = fs::remove_file(temp_path);
+        return Err(error);
+    }
+
+    mark_chapter_offline(service, chapter, to_local_path(comics_dir, target))
+        .map_err(internal_error)?;
+    merge_comic_info(service, &chapter.id, target);
+    service
+        .get("chapters", &chapter.id)
+        .map_err(internal_error)?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "Chapter not found".to_string()))
+}
+
+pub(super) fn archive_exists(target: &Path) -> UploadError {
+    (
+        StatusCode::CONFLICT,
+        format!(
+            "An archive already exists at {}",
+            target
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        ),
+    )
+}
+
+fn validate_chapter_archive(path: &Path) -> Result<(), UploadError> {
+    let pages = list_image_entries(&CbzReader::new(path)).map_err(|error| {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("The archive is not a readable CBZ: {error}"),
+        )
+    })?;
+    if pages.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The archive contains no pages".to_string(),
+        ));
+    }
+
+    let health = verify_archive_pages(&CbzReader::new(path));
+    if health.status != ArchiveHealthStatus::Ok {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!(
+                "The archive has unreadable pages: {}",
+                health.error.unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Renames `from` to `to` unless `to` exists. A hard link claims the name atomically;
+/// file systems without hard links fall back to checking first.
+fn move_without_overwrite(from: &Path, to: &Path) -> Result<(), UploadError> {
+    match fs::hard_link(from, to) {
+        Ok(()) => {
+            let _ = fs::remove_file(from);
+            Ok(())
+        }
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => Err(archive_exists(to)),
+        Err(_) if to.exists() => Err(archive_exists(to)),
+        Err(_) => fs::rename(from, to).map_err(write_error),
+    }
+}
+
+fn multipart_error(error: MultipartError) -> UploadError {
+    (error.status(), error.body_text())
+}
+
+fn too_large(max_bytes: usize) -> UploadError {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("The upload exceeds {} MiB", max_bytes / (1024 * 1024)),
+    )
+}
+
+fn write_error(error: std::io::Error) -> UploadError {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        format!("Failed to store the upload: {error}"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, bytes) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn png_page() -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image::RgbImage::new(2, 2)
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn rejects_archives_without_readable_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_zip = dir.path().join("not-a-zip.part");
+        fs::write(&not_a_zip, b"plain text").unwrap();
+        assert_eq!(
+            validate_chapter_archive(&not_a_zip).unwrap_err().0,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let empty = dir.path().join("no-pages.part");
+        write_zip(&empty, &[("ComicInfo.xml", b"<ComicInfo/>")]);
+        assert_eq!(
+            validate_chapter_archive(&empty).unwrap_err().0,
+            StatusCode::BAD_REQUEST
+        );
+
+        let mut truncated = png_page();
+        truncated.truncate(truncated.len() / 2);
+        let corrupt = dir.path().join("corrupt.part");
+        write_zip(
+            &corrupt,
+            &[("001.png", &png_page()), ("002.png", &truncated)],
+        );
+        assert_eq!(
+            validate_chapter_archive(&corrupt).unwrap_err().0,
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+
+        let valid = dir.path().join("valid.part");
+        write_zip(&valid, &[("001.png", &png_page())]);
+        assert!(validate_chapter_archive(&valid).is_ok());
+    }
+
+    #[test]
+    fn keeps_an_existing_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let upload = dir.path().join(".upload-1.part");
+        let target = dir.path().join("Chapter 1.cbz");
+        fs::write(&upload, b"new").unwrap();
+        fs::write(&target, b"old").unwrap();
+
+        assert_eq!(
+            move_without_overwrite(&upload, &target).unwrap_err().0,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(fs::read(&target).unwrap(), b"old");
+
+        fs::remove_file(&target).unwrap();
+        move_without_overwrite(&upload, &target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert!(!upload.exists());
+    }
+}
@@ -3,7 +3,7 @@ import { useQueryClient } from '@tanstack/react-query'
 import { isTauri } from '@tauri-apps/api/core'
 import { toast } from 'sonner'
 import { useTranslation } from 'react-i18next'
-import { processDeepLinkUrl } from 'services/deepLink'
+import { buildReaderPath, processDeepLinkUrl } from 'services/deepLink'
 import { accountSessionQueryKey, restQueryKeys, setApiBaseUrl } from 'services'
 
 interface ApiEndpointPayload {
@@ -60,6 +60,11 @@ export const ApiEndpointBridge: FC = () => {
                 continue
               }
 
+              if (result.kind === 'reader') {
+                window.location.hash = buildReaderPath(result)
+                continue
+              }
+
               await Promise.all([
                 queryClient.invalidateQueries({ queryKey: restQueryKeys.comics }),
                 queryClient.invalidateQueries({ queryKey: ['rest', 'chapters'] })
@@ -1,6 +1,6 @@
 import { useQueryClient } from '@tanstack/react-query'
 import { useCallback, useEffect, useMemo, useRef, useState } from 'react'
-import { useNavigate, useParams } from 'react-router-dom'
+import { useNavigate, useParams, useSearchParams } from 'react-router-dom'
 import i18n from 'i18n'
 import {
   type CanonicalChapterData,
@@ -234,6 +234,10 @@ export const useReaderController = () => {
   const queryClient = useQueryClient()
   const navigate = useNavigate()
   const { comicId, chapterId } = useParams<{ comicId: string; chapterId: string }>()
+  const [searchParams] = useSearchParams()
+  // Set by bookmark deep links to open the chapter at a given page instead of the saved one.
+  const requestedPage = Number.parseInt(searchParams.get('page') || '', 10)
+  const startPage = Number.isFinite(requestedPage) && requestedPage > 0 ? requestedPage : undefined
 
   const [readingMode, setReadingMode] = useState<'horizontal' | 'vertical'>('horizontal')
   const [readingDirection, setReadingDirection] = useState<'ltr' | 'rtl'>('ltr')
@@ -875,7 +879,7 @@ export const useReaderController = () => {
     const record = readProgressQuery.data[0] ?? legacyReadProgressQuery.data?.[0]
     if (record?.data) {
       readProgressRecordIdRef.current = record.id
-      const page = Math.max(1, Math.min(record.data.page || 1, totalPages))
+      const page = Math.max(1, Math.min(startPage ?? (record.data.page || 1), totalPages))
 
       const nextProgress: ReadProgressData = {
         ...record.data,
@@ -895,11 +899,11 @@ export const useReaderController = () => {
     const initial: ReadProgressData = {
       chapterId,
       comicId,
-      page: 1,
+      page: Math.min(startPage ?? 1, totalPages),
       totalPages
     }
     setReadProgress(initial)
-    lastPersistedRef.current = { chapterId, page: 1 }
+    lastPersistedRef.current = { chapterId, page: initial.page }
     void persistReadProgressRef.current(initial)
   }, [
     chapterId,
@@ -911,7 +915,8 @@ export const useReaderController = () => {
     readProgressQuery.fetchStatus,
     readProgressQuery.data,
     legacyReadProgressQuery.data,
-    readProgress?.chapterId
+    readProgress?.chapterId,
+    startPage
   ])
 
   useEffect(() => {
@@ -2,4 +2,5 @@ export * from './types'
 export * from './utils'
 export * from './pluginInstall'
 export * from './comicImport'
+export * from './readerLink'
 export * from './processDeepLink'
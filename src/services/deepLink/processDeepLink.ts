@@ -1,5 +1,6 @@
 import { importComicFromDeepLink } from './comicImport'
 import { installPluginFromDeepLink, parsePluginInstallDeepLink } from './pluginInstall'
+import { parseReaderDeepLink } from './readerLink'
 import type { DeepLinkProcessResult } from './types'
 import { parseComicImportDeepLink } from './utils'
 
@@ -10,6 +11,11 @@ export const processDeepLinkUrl = async (raw: string): Promise<DeepLinkProcessRe
     return { kind: 'plugin', ...result }
   }
 
+  const readerPayload = parseReaderDeepLink(raw)
+  if (readerPayload) {
+    return { kind: 'reader', ...readerPayload }
+  }
+
   const comicImportPayload = parseComicImportDeepLink(raw)
   if (comicImportPayload) {
     const result = await importComicFromDeepLink(comicImportPayload)
@@ -0,0 +1,44 @@
+import type { ReaderDeepLinkPayload } from './types'
+import { SUPPORTED_DEEP_LINK_PROTOCOLS } from './utils'
+
+const decodeSegment = (value: string | undefined): string => {
+  if (!value) return ''
+  try {
+    return decodeURIComponent(value).trim()
+  } catch {
+    return ''
+  }
+}
+
+// comic-universe://reader/{workId}/{chapterId}?page=N&bookmark=ID
+// chapterId is a canonical chapter or chapter variant id of the work, as the reader route expects.
+export const parseReaderDeepLink = (raw: string): ReaderDeepLinkPayload | null => {
+  let url: URL
+  try {
+    url = new URL(raw)
+  } catch {
+    return null
+  }
+
+  if (!SUPPORTED_DEEP_LINK_PROTOCOLS.has(url.protocol)) return null
+  if (url.hostname.toLowerCase() !== 'reader') return null
+
+  const [workSegment, chapterSegment] = url.pathname.replace(/^\/+/, '').split('/')
+  const workId = decodeSegment(workSegment)
+  const chapterId = decodeSegment(chapterSegment)
+  if (!workId || !chapterId) return null
+
+  const page = Number.parseInt(url.searchParams.get('page') || '', 10)
+
+  return {
+    workId,
+    chapterId,
+    page: Number.isFinite(page) && page > 0 ? page : undefined,
+    bookmarkId: url.searchParams.get('bookmark')?.trim() || undefined
+  }
+}
+
+export const buildReaderPath = (payload: ReaderDeepLinkPayload): string => {
+  const path = `/reader/${encodeURIComponent(payload.workId)}/${encodeURIComponent(payload.chapterId)}`
+  return payload.page ? `${path}?page=${payload.page}` : path
+}
@@ -34,6 +34,13 @@ export interface ComicImportResult {
   chaptersSkipped: number
 }
 
+export interface ReaderDeepLinkPayload {
+  workId: string
+  chapterId: string
+  page?: number
+  bookmarkId?: string
+}
+
 export type DeepLinkProcessResult =
   | {
       kind: 'plugin'
@@ -44,3 +51,6 @@ export type DeepLinkProcessResult =
       kind: 'comic'
       result: ComicImportResult
     }
+  | ({
+      kind: 'reader'
+    } & ReaderDeepLinkPayload)
@@ -71,8 +71,12 @@ export interface ChapterPagesResponse {
 }
 
 export interface MarkChaptersPayload {
-  chapterIds: string[];
+  chapterIds?: string[];
   read: boolean;
+  comicId?: string;
+  fromNumber?: number;
+  upToNumber?: number;
+  language?: string;
 }
 
 export interface MarkChaptersResponse {
@@ -92,6 +96,7 @@ export interface ChapterData {
   name?: string;
   number?: string;
   hasOffline?: number | boolean;
+  localPath?: string;
   [key: string]: unknown;
 }
 